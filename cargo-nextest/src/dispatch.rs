@@ -9,7 +9,7 @@ use crate::{
 };
 use camino::{Utf8Path, Utf8PathBuf};
 use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
-use guppy::graph::PackageGraph;
+use guppy::{graph::PackageGraph, PackageId};
 use itertools::Itertools;
 use nextest_filtering::FilteringExpr;
 use nextest_metadata::{BinaryListSummary, BuildPlatform};
@@ -21,20 +21,31 @@ use nextest_runner::{
         VersionOnlyConfig,
     },
     double_spawn::DoubleSpawnInfo,
-    errors::WriteTestListError,
+    errors::{RunStoreError, WriteTestListError},
     list::{
         BinaryList, OutputFormat, RustTestArtifact, SerializableFormat, TestExecuteContext,
         TestList,
     },
     partition::PartitionerBuilder,
     platform::BuildPlatforms,
-    reporter::{FinalStatusLevel, StatusLevel, TestOutputDisplay, TestReporterBuilder},
+    reporter::{
+        FinalStatusLevel, ReporterMessageFormat, StatusLevel, TestOutputDisplay,
+        TestReporterBuilder,
+    },
     reuse_build::{archive_to_file, ArchiveReporter, MetadataOrPath, PathMapper, ReuseBuildInfo},
-    runner::{configure_handle_inheritance, RunStatsFailureKind, TestRunnerBuilder},
-    show_config::{ShowNextestVersion, ShowTestGroupSettings, ShowTestGroups, ShowTestGroupsMode},
+    run_store::{RunOutcomes, TestDurations},
+    runner::{
+        configure_handle_inheritance, RunStats, RunStatsFailureKind, StressCondition,
+        TestRunnerBuilder,
+    },
+    show_config::{
+        ShowFilter, ShowNextestVersion, ShowTestGroupSettings, ShowTestGroups, ShowTestGroupsMode,
+        ShowTestSettings,
+    },
     signal::SignalHandlerKind,
     target_runner::{PlatformRunner, TargetRunner},
     test_filter::{RunIgnored, TestFilterBuilder},
+    watch::WorkspaceWatcher,
 };
 use once_cell::sync::OnceCell;
 use owo_colors::{OwoColorize, Style};
@@ -105,6 +116,7 @@ impl AppOpts {
 
         match self.command {
             Command::List {
+                profile,
                 cargo_options,
                 build_filter,
                 message_format,
@@ -121,7 +133,7 @@ impl AppOpts {
                     output_writer,
                 )?;
                 let app = App::new(base, build_filter)?;
-                app.exec_list(message_format, list_type, output_writer)?;
+                app.exec_list(profile.as_deref(), message_format, list_type, output_writer)?;
                 Ok(0)
             }
             Command::Run(run_opts) => {
@@ -137,12 +149,32 @@ impl AppOpts {
                 app.exec_run(
                     run_opts.profile.as_deref(),
                     run_opts.no_capture,
+                    run_opts.rerun_failed,
                     &run_opts.runner_opts,
                     &run_opts.reporter_opts,
                     output_writer,
                 )?;
                 Ok(0)
             }
+            Command::Watch(watch_opts) => {
+                let base = BaseApp::new(
+                    output,
+                    ReuseBuildOpts::default(),
+                    watch_opts.cargo_options,
+                    self.common.config_opts,
+                    self.common.manifest_path,
+                    output_writer,
+                )?;
+                let app = App::new(base, watch_opts.build_filter)?;
+                app.exec_watch(
+                    watch_opts.profile.as_deref(),
+                    watch_opts.no_capture,
+                    &watch_opts.runner_opts,
+                    &watch_opts.reporter_opts,
+                    output_writer,
+                )?;
+                Ok(0)
+            }
             Command::Archive {
                 cargo_options,
                 archive_file,
@@ -166,6 +198,12 @@ impl AppOpts {
                 self.common.config_opts,
                 output_writer,
             ),
+            Command::Show { command } => command.exec(
+                self.common.manifest_path,
+                self.common.output,
+                self.common.config_opts,
+                output_writer,
+            ),
             Command::Self_ { command } => command.exec(self.common.output),
         }
     }
@@ -263,6 +301,12 @@ enum Command {
     ///
     /// For more information, see <https://nexte.st/book/listing>.
     List {
+        /// Nextest profile to use
+        ///
+        /// Expected test durations are read from this profile's store directory.
+        #[arg(long, short = 'P', env = "NEXTEST_PROFILE")]
+        profile: Option<String>,
+
         #[clap(flatten)]
         cargo_options: CargoOptions,
 
@@ -301,6 +345,17 @@ enum Command {
     /// For more information, see <https://nexte.st/book/running>.
     #[command(visible_alias = "r")]
     Run(RunOpts),
+    /// Build and run tests, then rerun affected tests whenever sources change
+    ///
+    /// This command runs tests like `cargo nextest run`, then watches workspace packages for
+    /// changes. When a package's sources change, test binaries are rebuilt, and only the tests in
+    /// that package and in packages that depend on it are rerun. A run that's still in progress
+    /// when a change is detected is canceled.
+    ///
+    /// Press Ctrl-C to stop watching.
+    ///
+    /// For more information, see <https://nexte.st/book/watch>.
+    Watch(WatchOpts),
     /// Build and archive tests
     ///
     /// This command builds test binaries and archives them to a file. The archive can then be
@@ -355,6 +410,14 @@ enum Command {
         #[clap(subcommand)]
         command: ShowConfigCommand,
     },
+    /// Show how nextest interprets its inputs.
+    ///
+    /// This command explains how nextest applies filters and other inputs to the tests in this
+    /// workspace, without running any tests.
+    Show {
+        #[clap(subcommand)]
+        command: ShowCommand,
+    },
     /// Manage the nextest installation
     #[clap(name = "self")]
     Self_ {
@@ -388,6 +451,7 @@ impl NtrOpts {
         app.exec_run(
             self.run_opts.profile.as_deref(),
             self.run_opts.no_capture,
+            self.run_opts.rerun_failed,
             &self.run_opts.runner_opts,
             &self.run_opts.reporter_opts,
             output_writer,
@@ -408,6 +472,13 @@ struct RunOpts {
     #[clap(flatten)]
     build_filter: TestBuildFilter,
 
+    /// Only run tests that failed in the previous run
+    ///
+    /// Outcomes are recorded in the profile's store directory at the end of every run. Tests that
+    /// failed, timed out or could not be executed are rerun.
+    #[arg(long, help_heading = "Filter options")]
+    rerun_failed: bool,
+
     #[clap(flatten)]
     runner_opts: TestRunnerOpts,
 
@@ -428,6 +499,36 @@ struct RunOpts {
     reuse_build: ReuseBuildOpts,
 }
 
+#[derive(Debug, Args)]
+struct WatchOpts {
+    /// Nextest profile to use
+    #[arg(long, short = 'P', env = "NEXTEST_PROFILE")]
+    profile: Option<String>,
+
+    #[clap(flatten)]
+    cargo_options: CargoOptions,
+
+    #[clap(flatten)]
+    build_filter: TestBuildFilter,
+
+    #[clap(flatten)]
+    runner_opts: TestRunnerOpts,
+
+    /// Run tests serially and do not capture output
+    #[arg(
+        long,
+        name = "no-capture",
+        alias = "nocapture",
+        help_heading = "Runner options",
+        display_order = 100
+    )]
+    no_capture: bool,
+
+    #[clap(flatten)]
+    reporter_opts: TestReporterOpts,
+    // ReuseBuildOpts doesn't make sense here: sources are watched so that they can be rebuilt.
+}
+
 #[derive(Copy, Clone, Debug, ValueEnum)]
 pub(crate) enum PlatformFilterOpts {
     Target,
@@ -493,10 +594,17 @@ struct TestBuildFilter {
     #[arg(long, value_enum, value_name = "WHICH")]
     run_ignored: Option<RunIgnoredOpt>,
 
-    /// Test partition, e.g. hash:1/2 or count:2/3
+    /// Test partition, e.g. hash:1/2, count:2/3 or weighted:1/4
     #[arg(long)]
     partition: Option<PartitionerBuilder>,
 
+    /// Test durations file to use for weighted partitioning (required with weighted:)
+    ///
+    /// Every shard must be passed the same file, e.g. test-durations.json from the store directory
+    /// of a previous run.
+    #[arg(long, value_name = "PATH", requires = "partition")]
+    partition_durations: Option<Utf8PathBuf>,
+
     /// Filter test binaries by build platform (DEPRECATED)
     ///
     /// Instead, use -E with 'platform(host)' or 'platform(target)'.
@@ -739,6 +847,20 @@ pub struct TestRunnerOpts {
     /// Run all tests regardless of failure
     #[arg(long, conflicts_with = "no-run", overrides_with = "fail-fast")]
     no_fail_fast: bool,
+
+    /// Run each test repeatedly to find flaky tests: either a number of times (e.g. 100) or for a
+    /// duration (e.g. 5m)
+    ///
+    /// Each test is run repeatedly whether or not it passes, and fails if any of its runs fail.
+    /// The final summary ranks tests by failure rate. Retries are disabled in this mode, and
+    /// fail-fast is off unless --fail-fast is passed in.
+    #[arg(
+        long,
+        value_name = "N|DURATION",
+        conflicts_with_all = &["no-run", "retries"],
+        env = "NEXTEST_STRESS",
+    )]
+    stress: Option<StressCondition>,
 }
 
 impl TestRunnerOpts {
@@ -760,6 +882,9 @@ impl TestRunnerOpts {
         if let Some(test_threads) = self.test_threads {
             builder.set_test_threads(test_threads);
         }
+        if let Some(stress) = self.stress {
+            builder.set_stress(stress);
+        }
 
         Some(builder)
     }
@@ -818,6 +943,30 @@ struct TestReporterOpts {
     /// Do not display the progress bar
     #[arg(long, env = "NEXTEST_HIDE_PROGRESS_BAR")]
     hide_progress_bar: bool,
+
+    /// Display test output as it's written, prefixed with the test's name
+    ///
+    /// Unlike --no-capture, tests still run in parallel, and their output is still captured and
+    /// displayed according to --failure-output and --success-output.
+    #[arg(
+        long,
+        conflicts_with_all = &["no-capture", "no-run"],
+        env = "NEXTEST_STREAM_OUTPUT"
+    )]
+    stream_output: bool,
+
+    /// Format to write machine-readable run events to standard output in
+    ///
+    /// With nextest-json, each event in the run is written to standard output as a single line of
+    /// JSON. Human-readable output continues to be written to standard error.
+    #[arg(
+        long,
+        value_enum,
+        conflicts_with_all = &["no-capture", "no-run"],
+        value_name = "FMT",
+        env = "NEXTEST_MESSAGE_FORMAT",
+    )]
+    message_format: Option<RunMessageFormatOpt>,
 }
 
 impl TestReporterOpts {
@@ -837,10 +986,29 @@ impl TestReporterOpts {
             builder.set_final_status_level(final_status_level.into());
         }
         builder.set_hide_progress_bar(self.hide_progress_bar);
+        builder.set_stream_output(self.stream_output);
+        if let Some(message_format) = self.message_format {
+            builder.set_message_format(message_format.into());
+        }
         builder
     }
 }
 
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum RunMessageFormatOpt {
+    Human,
+    NextestJson,
+}
+
+impl From<RunMessageFormatOpt> for ReporterMessageFormat {
+    fn from(opt: RunMessageFormatOpt) -> Self {
+        match opt {
+            RunMessageFormatOpt::Human => ReporterMessageFormat::Human,
+            RunMessageFormatOpt::NextestJson => ReporterMessageFormat::NextestJson,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, ValueEnum)]
 enum TestOutputDisplayOpt {
     Immediate,
@@ -1308,12 +1476,18 @@ impl App {
         Ok(Self { base, build_filter })
     }
 
-    fn build_filtering_expressions(&self) -> Result<Vec<FilteringExpr>> {
+    fn build_filtering_expressions(&self, config: &NextestConfig) -> Result<Vec<FilteringExpr>> {
         let (exprs, all_errors): (Vec<_>, Vec<_>) = self
             .build_filter
             .filter_expr
             .iter()
-            .map(|input| FilteringExpr::parse(input.clone(), self.base.graph()))
+            .map(|input| {
+                FilteringExpr::parse_with_filtersets(
+                    input.clone(),
+                    self.base.graph(),
+                    config.filtersets(),
+                )
+            })
             .partition_result();
 
         if !all_errors.is_empty() {
@@ -1327,10 +1501,19 @@ impl App {
         &self,
         ctx: &TestExecuteContext<'_>,
         binary_list: Arc<BinaryList>,
-        test_filter_builder: TestFilterBuilder,
+        mut test_filter_builder: TestFilterBuilder,
+        profile: &NextestProfile<'_, PreBuildPlatform>,
     ) -> Result<TestList> {
+        let build_platforms = binary_list.rust_build_meta.build_platforms()?;
+        test_filter_builder.set_test_groups(
+            profile
+                .clone()
+                .apply_build_platforms(&build_platforms)
+                .test_group_assignments(),
+        );
+
         let env = EnvironmentMap::new(&self.base.cargo_configs);
-        self.build_filter.compute_test_list(
+        let mut test_list = self.build_filter.compute_test_list(
             ctx,
             self.base.graph(),
             self.base.workspace_root.clone(),
@@ -1338,7 +1521,48 @@ impl App {
             test_filter_builder,
             env,
             &self.base.reuse_build,
-        )
+        )?;
+
+        let durations = self
+            .load_test_durations(profile)
+            .map_err(|err| ExpectedError::PartitionDurationsReadError { err })?;
+        test_list.apply_expected_durations(&durations);
+        if let Some(PartitionerBuilder::Weighted {
+            shard,
+            total_shards,
+        }) = &self.build_filter.partition
+        {
+            // Local histories differ across machines, so only durations passed in explicitly can
+            // be used to assign tests to shards.
+            if self.build_filter.partition_durations.is_none() {
+                return Err(ExpectedError::PartitionDurationsRequired);
+            }
+            if durations.is_empty() {
+                log::warn!(
+                    "no test durations found in the partition durations file, \
+                     weighted partitioning will split tests evenly by count"
+                );
+            }
+            test_list.apply_weighted_partition(&durations, *shard, *total_shards);
+        }
+
+        Ok(test_list)
+    }
+
+    fn load_test_durations(
+        &self,
+        profile: &NextestProfile<'_, PreBuildPlatform>,
+    ) -> Result<TestDurations, RunStoreError> {
+        if let Some(path) = &self.build_filter.partition_durations {
+            return TestDurations::read_from_path(path);
+        }
+        match TestDurations::load(profile.store_dir()) {
+            Ok(durations) => Ok(durations.unwrap_or_default()),
+            Err(err) => {
+                log::warn!("ignoring previously recorded test durations: {err}");
+                Ok(TestDurations::default())
+            }
+        }
     }
 
     fn load_profile<'cfg>(
@@ -1368,12 +1592,14 @@ impl App {
 
     fn exec_list(
         &self,
+        profile_name: Option<&str>,
         message_format: MessageFormatOpts,
         list_type: ListType,
         output_writer: &mut OutputWriter,
     ) -> Result<()> {
-        let (version_only_config, _) = self.base.load_config()?;
-        let filter_exprs = self.build_filtering_expressions()?;
+        let (version_only_config, config) = self.base.load_config()?;
+        let profile = self.load_profile(profile_name, &config)?;
+        let filter_exprs = self.build_filtering_expressions(&config)?;
         let test_filter_builder = self.build_filter.make_test_filter_builder(filter_exprs)?;
 
         let binary_list = self.base.build_binary_list()?;
@@ -1401,7 +1627,8 @@ impl App {
                     target_runner,
                 };
 
-                let test_list = self.build_test_list(&ctx, binary_list, test_filter_builder)?;
+                let test_list =
+                    self.build_test_list(&ctx, binary_list, test_filter_builder, &profile)?;
 
                 let mut writer = output_writer.stdout_writer();
                 test_list.write(
@@ -1440,7 +1667,7 @@ impl App {
         };
         let settings = ShowTestGroupSettings { mode, show_default };
 
-        let filter_exprs = self.build_filtering_expressions()?;
+        let filter_exprs = self.build_filtering_expressions(&config)?;
         let test_filter_builder = self.build_filter.make_test_filter_builder(filter_exprs)?;
 
         let binary_list = self.base.build_binary_list()?;
@@ -1455,7 +1682,7 @@ impl App {
             target_runner,
         };
 
-        let test_list = self.build_test_list(&ctx, binary_list, test_filter_builder)?;
+        let test_list = self.build_test_list(&ctx, binary_list, test_filter_builder, &profile)?;
 
         let profile = profile.apply_build_platforms(&build_platforms);
 
@@ -1476,10 +1703,97 @@ impl App {
         Ok(())
     }
 
+    fn exec_show_test_settings(
+        &self,
+        profile_name: Option<&str>,
+        name: &str,
+        output_writer: &mut OutputWriter,
+    ) -> Result<()> {
+        let (_, config) = self.base.load_config()?;
+        let profile = self.load_profile(profile_name, &config)?;
+        let filter_exprs = self.build_filtering_expressions(&config)?;
+        let test_filter_builder = self.build_filter.make_test_filter_builder(filter_exprs)?;
+
+        let binary_list = self.base.build_binary_list()?;
+        let build_platforms = binary_list.rust_build_meta.build_platforms()?;
+
+        let double_spawn = self.base.load_double_spawn();
+        let target_runner = self.base.load_runner(&build_platforms);
+        let ctx = TestExecuteContext {
+            double_spawn,
+            target_runner,
+        };
+
+        let test_list = self.build_test_list(&ctx, binary_list, test_filter_builder, &profile)?;
+
+        let profile = profile.apply_build_platforms(&build_platforms);
+
+        let mut writer = output_writer.stdout_writer();
+
+        let show_test_settings = ShowTestSettings::new(&profile, &test_list, name)?;
+        show_test_settings
+            .write_human(
+                &mut writer,
+                self.base
+                    .output
+                    .color
+                    .should_colorize(supports_color::Stream::Stdout),
+            )
+            .map_err(WriteTestListError::Io)?;
+        writer.flush().map_err(WriteTestListError::Io)?;
+
+        Ok(())
+    }
+
+    fn exec_show_filter(
+        &self,
+        profile_name: Option<&str>,
+        message_format: MessageFormatOpts,
+        output_writer: &mut OutputWriter,
+    ) -> Result<()> {
+        let (_, config) = self.base.load_config()?;
+        let profile = self.load_profile(profile_name, &config)?;
+        let filter_exprs = self.build_filtering_expressions(&config)?;
+        let test_filter_builder = self
+            .build_filter
+            .make_test_filter_builder(filter_exprs.clone())?;
+
+        let binary_list = self.base.build_binary_list()?;
+        let build_platforms = binary_list.rust_build_meta.build_platforms()?;
+
+        let double_spawn = self.base.load_double_spawn();
+        let target_runner = self.base.load_runner(&build_platforms);
+        let ctx = TestExecuteContext {
+            double_spawn,
+            target_runner,
+        };
+
+        let test_list = self.build_test_list(&ctx, binary_list, test_filter_builder, &profile)?;
+
+        let test_groups = profile
+            .apply_build_platforms(&build_platforms)
+            .test_group_assignments();
+        let show_filter = ShowFilter::new(&test_list, &filter_exprs, &test_groups);
+
+        let mut writer = output_writer.stdout_writer();
+        show_filter.write(
+            message_format.to_output_format(self.base.output.verbose),
+            &mut writer,
+            self.base
+                .output
+                .color
+                .should_colorize(supports_color::Stream::Stdout),
+        )?;
+        writer.flush().map_err(WriteTestListError::Io)?;
+
+        Ok(())
+    }
+
     fn exec_run(
         &self,
         profile_name: Option<&str>,
         no_capture: bool,
+        rerun_failed: bool,
         runner_opts: &TestRunnerOpts,
         reporter_opts: &TestReporterOpts,
         output_writer: &mut OutputWriter,
@@ -1487,9 +1801,195 @@ impl App {
         let (version_only_config, config) = self.base.load_config()?;
         let profile = self.load_profile(profile_name, &config)?;
 
-        let filter_exprs = self.build_filtering_expressions()?;
-        let test_filter_builder = self.build_filter.make_test_filter_builder(filter_exprs)?;
+        let filter_exprs = self.build_filtering_expressions(&config)?;
+        let mut test_filter_builder = self.build_filter.make_test_filter_builder(filter_exprs)?;
+        if rerun_failed {
+            match RunOutcomes::load(profile.store_dir())? {
+                Some(outcomes) => {
+                    let failed_tests = outcomes.failed_tests();
+                    let failed_count = failed_tests.len();
+                    log::info!(
+                        "rerunning {} {} that failed previously",
+                        failed_count,
+                        if failed_count == 1 { "test" } else { "tests" },
+                    );
+                    test_filter_builder.set_rerun_failed(failed_tests);
+                }
+                None => {
+                    log::warn!("no previous runs recorded for this profile, running all tests");
+                }
+            }
+        }
+
+        let run_stats = match self.run_tests(
+            profile,
+            &test_filter_builder,
+            no_capture,
+            runner_opts,
+            reporter_opts,
+            None,
+            output_writer,
+        )? {
+            Some(run_stats) => run_stats,
+            None => {
+                // This means --no-run was passed in. Exit.
+                return Ok(());
+            }
+        };
+        self.base
+            .check_version_config_final(version_only_config.nextest_version())?;
+        check_run_stats(run_stats)
+    }
+
+    fn exec_watch(
+        &self,
+        profile_name: Option<&str>,
+        no_capture: bool,
+        runner_opts: &TestRunnerOpts,
+        reporter_opts: &TestReporterOpts,
+        output_writer: &mut OutputWriter,
+    ) -> Result<()> {
+        let (version_only_config, config) = self.base.load_config()?;
+        let profile = self.load_profile(profile_name, &config)?;
+        self.base
+            .check_version_config_final(version_only_config.nextest_version())?;
+
+        // Start watching before the first build, so that changes made during it are picked up.
+        // Tests write to the store directory, and builds to the target directory: neither are
+        // sources.
+        let ignore_dirs = [
+            self.base.graph().workspace().target_directory().to_owned(),
+            profile.store_dir().to_owned(),
+        ];
+        let mut watcher =
+            WorkspaceWatcher::new(self.base.graph(), ignore_dirs, SignalHandlerKind::Standard)?;
+
+        // Packages whose tests need to be run: None means all packages.
+        let mut packages_to_test: Option<BTreeSet<PackageId>> = None;
+        loop {
+            let required_expr = match &packages_to_test {
+                Some(packages) => {
+                    // Run tests in changed packages, and in the packages that depend on them.
+                    let input = packages
+                        .iter()
+                        .map(|id| {
+                            let name = self
+                                .base
+                                .graph()
+                                .metadata(id)
+                                .expect("watched package IDs come from the package graph")
+                                .name();
+                            format!("rdeps(={name})")
+                        })
+                        .join(" | ");
+                    let expr = FilteringExpr::parse(input, self.base.graph())
+                        .map_err(|err| ExpectedError::filter_expression_parse_error(vec![err]))?;
+                    Some(expr)
+                }
+                None => None,
+            };
 
+            let res = self.run_watched_tests(
+                profile_name,
+                required_expr,
+                no_capture,
+                runner_opts,
+                reporter_opts,
+                &watcher,
+                output_writer,
+            );
+            let canceled = watcher.finish_run();
+            match res {
+                Ok(()) => {}
+                Err(_) if canceled => {
+                    log::info!("sources changed, canceled test run");
+                }
+                Err(err) => {
+                    // Failures are expected while iterating, so keep watching.
+                    err.display_to_stderr();
+                }
+            }
+
+            log::info!("waiting for changes...");
+            let Some(changed) = watcher.wait_for_changes() else {
+                return Ok(());
+            };
+            log::info!(
+                "sources changed for {} {}, rerunning affected tests",
+                changed.len(),
+                if changed.len() == 1 {
+                    "package"
+                } else {
+                    "packages"
+                },
+            );
+            packages_to_test = match packages_to_test {
+                // Tests for the previous set of packages didn't get to finish, so run them again.
+                Some(mut packages) if canceled => {
+                    packages.extend(changed);
+                    Some(packages)
+                }
+                // If the first run was canceled, keep running all tests.
+                None if canceled => None,
+                _ => Some(changed),
+            };
+        }
+    }
+
+    /// Builds and runs tests for one iteration of `cargo nextest watch`.
+    ///
+    /// The config and filter expressions are loaded again for each run, so that predicates like
+    /// `changed()` see the current state of the repository.
+    #[allow(clippy::too_many_arguments)]
+    fn run_watched_tests(
+        &self,
+        profile_name: Option<&str>,
+        required_expr: Option<FilteringExpr>,
+        no_capture: bool,
+        runner_opts: &TestRunnerOpts,
+        reporter_opts: &TestReporterOpts,
+        watcher: &WorkspaceWatcher,
+        output_writer: &mut OutputWriter,
+    ) -> Result<()> {
+        let (_, config) = self.base.load_config()?;
+        let profile = self.load_profile(profile_name, &config)?;
+        let filter_exprs = self.build_filtering_expressions(&config)?;
+        let mut test_filter_builder = self.build_filter.make_test_filter_builder(filter_exprs)?;
+        if let Some(expr) = required_expr {
+            test_filter_builder.set_required_expr(expr);
+        }
+
+        match self.run_tests(
+            profile,
+            &test_filter_builder,
+            no_capture,
+            runner_opts,
+            reporter_opts,
+            Some(watcher),
+            output_writer,
+        )? {
+            Some(run_stats) => check_run_stats(run_stats),
+            None => Ok(()),
+        }
+    }
+
+    /// Builds and runs tests.
+    ///
+    /// If `watcher` is passed in, the run is canceled as soon as the watcher sees a change. Call
+    /// [`WorkspaceWatcher::finish_run`] afterwards.
+    ///
+    /// Returns `None` if `--no-run` was passed in.
+    #[allow(clippy::too_many_arguments)]
+    fn run_tests(
+        &self,
+        profile: NextestProfile<'_, PreBuildPlatform>,
+        test_filter_builder: &TestFilterBuilder,
+        no_capture: bool,
+        runner_opts: &TestRunnerOpts,
+        reporter_opts: &TestReporterOpts,
+        watcher: Option<&WorkspaceWatcher>,
+        output_writer: &mut OutputWriter,
+    ) -> Result<Option<RunStats>> {
         let binary_list = self.base.build_binary_list()?;
         let build_platforms = binary_list.rust_build_meta.build_platforms()?;
         let double_spawn = self.base.load_double_spawn();
@@ -1499,7 +1999,8 @@ impl App {
             target_runner,
         };
 
-        let test_list = self.build_test_list(&ctx, binary_list, test_filter_builder)?;
+        let test_list =
+            self.build_test_list(&ctx, binary_list, test_filter_builder.clone(), &profile)?;
 
         let output = output_writer.reporter_output();
         let profile = profile.apply_build_platforms(&build_platforms);
@@ -1518,13 +2019,14 @@ impl App {
         }
 
         let handler = SignalHandlerKind::Standard;
-        let runner_builder = match runner_opts.to_builder(no_capture) {
+        let mut runner_builder = match runner_opts.to_builder(no_capture) {
             Some(runner_builder) => runner_builder,
             None => {
-                // This means --no-run was passed in. Exit.
-                return Ok(());
+                // This means --no-run was passed in.
+                return Ok(None);
             }
         };
+        runner_builder.set_stream_output(reporter_opts.stream_output);
 
         let runner = runner_builder.build(
             &test_list,
@@ -1535,30 +2037,35 @@ impl App {
         )?;
 
         configure_handle_inheritance(no_capture)?;
+        if let Some(watcher) = watcher {
+            watcher.start_run(runner.cancel_handle());
+        }
         let run_stats = runner.try_execute(|event| {
             // Write and flush the event.
             reporter.report_event(event)
         })?;
-        self.base
-            .check_version_config_final(version_only_config.nextest_version())?;
-        if !run_stats.is_success() {
-            match run_stats.failure_kind() {
-                Some(RunStatsFailureKind::SetupScript) => {
-                    return Err(ExpectedError::setup_script_failed());
-                }
-                Some(RunStatsFailureKind::Test) => {
-                    return Err(ExpectedError::test_run_failed());
-                }
-                None => {
-                    // XXX This means that the final number run of tests was less than the initial
-                    // number. Why can this be except if tests were failed or canceled for some
-                    // reason?
-                    return Err(ExpectedError::test_run_failed());
-                }
+        Ok(Some(run_stats))
+    }
+}
+
+fn check_run_stats(run_stats: RunStats) -> Result<()> {
+    if !run_stats.is_success() {
+        match run_stats.failure_kind() {
+            Some(RunStatsFailureKind::SetupScript) => {
+                return Err(ExpectedError::setup_script_failed());
+            }
+            Some(RunStatsFailureKind::Test) => {
+                return Err(ExpectedError::test_run_failed());
+            }
+            None => {
+                // XXX This means that the final number run of tests was less than the initial
+                // number. Why can this be except if tests were failed or canceled for some
+                // reason?
+                return Err(ExpectedError::test_run_failed());
             }
         }
-        Ok(())
     }
+    Ok(())
 }
 
 #[derive(Debug, Subcommand)]
@@ -1585,6 +2092,25 @@ enum ShowConfigCommand {
         #[clap(flatten)]
         build_filter: TestBuildFilter,
 
+        #[clap(flatten)]
+        reuse_build: Box<ReuseBuildOpts>,
+    },
+    /// Show the settings that apply to a test, and where each setting comes from.
+    Test {
+        /// Nextest profile to show settings for
+        #[arg(long, short = 'P', env = "NEXTEST_PROFILE")]
+        profile: Option<String>,
+
+        /// The name of the test, as shown by `cargo nextest list`
+        #[arg(value_name = "NAME")]
+        name: String,
+
+        #[clap(flatten)]
+        cargo_options: Box<CargoOptions>,
+
+        #[clap(flatten)]
+        build_filter: TestBuildFilter,
+
         #[clap(flatten)]
         reuse_build: Box<ReuseBuildOpts>,
     },
@@ -1686,6 +2212,95 @@ impl ShowConfigCommand {
 
                 app.exec_show_test_groups(profile.as_deref(), show_default, groups, output_writer)?;
 
+                Ok(0)
+            }
+            Self::Test {
+                profile,
+                name,
+                cargo_options,
+                build_filter,
+                reuse_build,
+            } => {
+                let base = BaseApp::new(
+                    output,
+                    *reuse_build,
+                    *cargo_options,
+                    config_opts,
+                    manifest_path,
+                    output_writer,
+                )?;
+                let app = App::new(base, build_filter)?;
+
+                app.exec_show_test_settings(profile.as_deref(), &name, output_writer)?;
+
+                Ok(0)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum ShowCommand {
+    /// Show whether each test matches the provided filters, and why.
+    ///
+    /// For each filter expression passed in with `-E`, this shows the predicates within the
+    /// expression that decided whether each test matched it.
+    Filter {
+        /// Nextest profile to use for test groups and other settings
+        #[arg(long, short = 'P', env = "NEXTEST_PROFILE")]
+        profile: Option<String>,
+
+        #[clap(flatten)]
+        cargo_options: Box<CargoOptions>,
+
+        #[clap(flatten)]
+        build_filter: TestBuildFilter,
+
+        /// Output format
+        #[arg(
+            short = 'T',
+            long,
+            value_enum,
+            default_value_t,
+            help_heading = "Output options",
+            value_name = "FMT"
+        )]
+        message_format: MessageFormatOpts,
+
+        #[clap(flatten)]
+        reuse_build: Box<ReuseBuildOpts>,
+    },
+}
+
+impl ShowCommand {
+    fn exec(
+        self,
+        manifest_path: Option<Utf8PathBuf>,
+        output: OutputOpts,
+        config_opts: ConfigOpts,
+        output_writer: &mut OutputWriter,
+    ) -> Result<i32> {
+        let output = output.init();
+        match self {
+            Self::Filter {
+                profile,
+                cargo_options,
+                build_filter,
+                message_format,
+                reuse_build,
+            } => {
+                let base = BaseApp::new(
+                    output,
+                    *reuse_build,
+                    *cargo_options,
+                    config_opts,
+                    manifest_path,
+                    output_writer,
+                )?;
+                let app = App::new(base, build_filter)?;
+
+                app.exec_show_filter(profile.as_deref(), message_format, output_writer)?;
+
                 Ok(0)
             }
         }
@@ -1956,6 +2571,9 @@ mod tests {
             // ---
             "cargo nextest list -E deps(foo)",
             "cargo nextest run --filter-expr 'test(bar)' --package=my-package test-filter",
+            "cargo nextest show filter -E 'test(foo) | group(serial)'",
+            "cargo nextest show-config test tests::test_foo --profile ci",
+            "cargo nextest show filter -E 'test(foo)' --run-ignored all --message-format json",
             // ---
             // Test binary arguments
             // ---
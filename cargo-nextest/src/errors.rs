@@ -69,6 +69,15 @@ pub enum ExpectedError {
         #[source]
         err: std::io::Error,
     },
+    #[error("error reading previous run outcomes")]
+    RunStoreError {
+        #[from]
+        err: RunStoreError,
+    },
+    #[error("error reading test durations")]
+    PartitionDurationsReadError { err: RunStoreError },
+    #[error("weighted partitioning requires --partition-durations")]
+    PartitionDurationsRequired,
     #[error("cargo config error")]
     CargoConfigError {
         #[from]
@@ -164,6 +173,11 @@ pub enum ExpectedError {
         #[from]
         err: TestRunnerBuildError,
     },
+    #[error("creating workspace watcher failed")]
+    WatcherBuildError {
+        #[from]
+        err: WatcherBuildError,
+    },
     #[error("writing test list to output failed")]
     WriteTestListError {
         #[from]
@@ -184,6 +198,11 @@ pub enum ExpectedError {
         #[from]
         err: ShowTestGroupsError,
     },
+    #[error("show test settings error")]
+    ShowTestSettingsError {
+        #[from]
+        err: ShowTestSettingsError,
+    },
     #[error("setup script failed")]
     SetupScriptFailed,
     #[error("test run failed")]
@@ -378,6 +397,9 @@ impl ExpectedError {
             | Self::SetCurrentDirFailed { .. }
             | Self::ProfileNotFound { .. }
             | Self::StoreDirCreateError { .. }
+            | Self::RunStoreError { .. }
+            | Self::PartitionDurationsReadError { .. }
+            | Self::PartitionDurationsRequired
             | Self::RootManifestNotFound { .. }
             | Self::CargoConfigError { .. }
             | Self::TestFilterBuilderError { .. }
@@ -389,12 +411,14 @@ impl ExpectedError {
             | Self::PathMapperConstructError { .. }
             | Self::ArgumentJsonParseError { .. }
             | Self::TestRunnerBuildError { .. }
+            | Self::WatcherBuildError { .. }
             | Self::ConfigureHandleInheritanceError { .. }
             | Self::CargoMetadataParseError { .. }
             | Self::TestBinaryArgsParseError { .. }
             | Self::DialoguerError { .. }
             | Self::SignalHandlerSetupError { .. }
-            | Self::ShowTestGroupsError { .. } => NextestExitCode::SETUP_ERROR,
+            | Self::ShowTestGroupsError { .. }
+            | Self::ShowTestSettingsError { .. } => NextestExitCode::SETUP_ERROR,
             Self::ConfigParseError { err } => {
                 // Experimental features not being enabled are their own error.
                 match err.kind() {
@@ -506,6 +530,21 @@ impl ExpectedError {
                 );
                 Some(err as &dyn Error)
             }
+            Self::RunStoreError { err } => {
+                log::error!("failed to read outcomes of previous runs");
+                Some(err as &dyn Error)
+            }
+            Self::PartitionDurationsReadError { err } => {
+                log::error!("failed to read test durations for weighted partitioning");
+                Some(err as &dyn Error)
+            }
+            Self::PartitionDurationsRequired => {
+                log::error!(
+                    "--partition weighted:M/N requires --partition-durations, so that every shard \
+                     assigns tests to shards in the same way"
+                );
+                None
+            }
             Self::CargoConfigError { err } => {
                 log::error!("{}", err);
                 err.source()
@@ -529,6 +568,26 @@ impl ExpectedError {
                         }
                         None
                     }
+                    ConfigParseErrorKind::FiltersetParseErrors(errors) => {
+                        // Filterset errors are printed out using miette.
+                        for filterset_error in errors {
+                            log::error!(
+                                "for config file `{}`{}, failed to parse filterset: {}",
+                                err.config_file(),
+                                provided_by_tool(err.tool()),
+                                filterset_error
+                                    .name
+                                    .if_supports_color_2(Stream::Stderr, |p| p.bold()),
+                            );
+                            let parse_errors = &filterset_error.errors;
+                            for single_error in &parse_errors.errors {
+                                let report = miette::Report::new(single_error.clone())
+                                    .with_source_code(parse_errors.input.to_owned());
+                                log::error!(target: "cargo_nextest::no_heading", "{report:?}");
+                            }
+                        }
+                        None
+                    }
                     ConfigParseErrorKind::UnknownTestGroups {
                         errors,
                         known_groups,
@@ -731,6 +790,10 @@ impl ExpectedError {
                 log::error!("failed to build test runner");
                 Some(err as &dyn Error)
             }
+            Self::WatcherBuildError { err } => {
+                log::error!("failed to start watching the workspace");
+                Some(err as &dyn Error)
+            }
             Self::ConfigureHandleInheritanceError { err } => {
                 log::error!("{err}");
                 err.source()
@@ -755,6 +818,10 @@ impl ExpectedError {
                 log::error!("{err}");
                 err.source()
             }
+            Self::ShowTestSettingsError { err } => {
+                log::error!("{err}");
+                err.source()
+            }
             Self::RequiredVersionNotMet {
                 required,
                 current,
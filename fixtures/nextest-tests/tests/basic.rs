@@ -144,6 +144,11 @@ fn test_cargo_env_vars() {
         Ok("process-per-test"),
         "NEXTEST_EXECUTION_MODE set to process-per-test"
     );
+    let tmpdir = std::env::var("NEXTEST_TEST_TMPDIR").expect("NEXTEST_TEST_TMPDIR must be set");
+    assert!(
+        Path::new(&tmpdir).is_dir(),
+        "NEXTEST_TEST_TMPDIR is an existing directory"
+    );
     // https://doc.rust-lang.org/cargo/reference/environment-variables.html#environment-variables-cargo-sets-for-crates
     assert_env!("CARGO");
     assert_env!(
@@ -673,3 +673,63 @@ fn test_setup_script_error() {
 
     assert_eq!(output.exit_code, Some(NextestExitCode::SETUP_SCRIPT_FAILED));
 }
+
+#[test]
+fn test_run_tmpdir() {
+    set_env_vars();
+    let p = TempProject::new().unwrap();
+
+    let output = CargoNextestCli::new()
+        .args([
+            "--manifest-path",
+            p.manifest_path().as_str(),
+            "run",
+            "--workspace",
+            "--all-targets",
+            "--profile",
+            "with-junit",
+            "--success-output",
+            "immediate",
+            "-E",
+            "test(=test_cargo_env_vars) | test(=test_failure_assert)",
+        ])
+        .unchecked(true)
+        .output();
+    assert_eq!(
+        output.exit_code,
+        Some(NextestExitCode::TEST_RUN_FAILED),
+        "correct exit code for command\n{output}"
+    );
+
+    // test_cargo_env_vars prints out its environment, and checks that NEXTEST_TEST_TMPDIR is set.
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let success_tmpdir = stderr
+        .lines()
+        .find_map(|line| line.strip_prefix("NEXTEST_TEST_TMPDIR = "))
+        .unwrap_or_else(|| panic!("NEXTEST_TEST_TMPDIR printed out\n{output}"));
+    assert!(
+        !Utf8PathBuf::from(success_tmpdir).exists(),
+        "temporary directory for the passing test was deleted: {success_tmpdir}"
+    );
+
+    // Only the failing test's temporary directory is kept, and reported in JUnit.
+    let junit_path = p.target_dir().join("nextest/with-junit/junit.xml");
+    let junit = std::fs::read_to_string(&junit_path).unwrap();
+    let tmpdirs: Vec<_> = junit
+        .match_indices(r#"<property name="nextest.tmpdir" value=""#)
+        .map(|(start, prefix)| {
+            let value = &junit[start + prefix.len()..];
+            &value[..value.find('"').expect("property value is terminated")]
+        })
+        .collect();
+    assert_eq!(tmpdirs.len(), 1, "one tmpdir is reported in JUnit: {junit}");
+    assert!(
+        Utf8PathBuf::from(tmpdirs[0]).is_dir(),
+        "temporary directory for the failing test was kept: {}",
+        tmpdirs[0]
+    );
+    assert!(
+        stderr.contains(tmpdirs[0]),
+        "temporary directory for the failing test is shown in the output\n{output}"
+    );
+}
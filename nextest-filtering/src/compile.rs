@@ -4,26 +4,40 @@
 use crate::{
     errors::ParseSingleError,
     expression::*,
+    named::NamedFiltersets,
     parsing::{ParsedExpr, SetDef},
 };
+use camino::{Utf8Path, Utf8PathBuf};
 use guppy::{
     graph::{DependsCache, PackageGraph, PackageMetadata},
     PackageId,
 };
 use miette::SourceSpan;
-use std::collections::HashSet;
+use std::{
+    collections::{HashMap, HashSet},
+    process::Command,
+};
 
 pub(crate) fn compile(
     expr: &ParsedExpr,
     graph: &PackageGraph,
+    filtersets: &NamedFiltersets,
 ) -> Result<CompiledExpr, Vec<ParseSingleError>> {
     let in_workspace_packages: Vec<_> = graph
         .resolve_workspace()
         .packages(guppy::graph::DependencyDirection::Forward)
         .collect();
     let mut cache = graph.new_depends_cache();
+    let mut changed_files = ChangedFilesCache::new(graph.workspace().root());
     let mut errors = vec![];
-    let expr = compile_expr(expr, &in_workspace_packages, &mut cache, &mut errors);
+    let expr = compile_expr(
+        expr,
+        &in_workspace_packages,
+        &mut cache,
+        &mut changed_files,
+        filtersets,
+        &mut errors,
+    );
 
     if errors.is_empty() {
         Ok(expr)
@@ -85,10 +99,126 @@ fn rdependencies_packages(
     set
 }
 
+/// Returns the workspace packages that contain files which differ from `rev`.
+fn changed_packages(
+    rev: &str,
+    changed_files: &mut ChangedFilesCache<'_>,
+    all_packages: &[PackageMetadata],
+) -> Result<HashSet<PackageId>, String> {
+    let changed_files = changed_files.get(rev)?;
+
+    // Sort packages so that nested packages are matched before the packages they're nested in.
+    let mut package_dirs: Vec<_> = all_packages
+        .iter()
+        .filter_map(|p| Some((p.manifest_path().parent()?, p.id())))
+        .collect();
+    package_dirs.sort_by_key(|(dir, _)| std::cmp::Reverse(dir.components().count()));
+
+    Ok(changed_files
+        .iter()
+        .filter_map(|path| {
+            package_dirs
+                .iter()
+                .find(|(dir, _)| path.starts_with(dir))
+                .map(|(_, id)| (*id).clone())
+        })
+        .collect())
+}
+
+/// The files that differ from each git revision used by an expression.
+///
+/// This is scoped to the compilation of a single expression: git is run once for each revision the
+/// expression uses, and again each time an expression is parsed, for example on each iteration of
+/// `cargo nextest watch`.
+struct ChangedFilesCache<'a> {
+    workspace_root: &'a Utf8Path,
+    // The top of the repository, which isn't necessarily the workspace root.
+    repo_root: Option<Utf8PathBuf>,
+    changed_files: HashMap<String, Vec<Utf8PathBuf>>,
+}
+
+impl<'a> ChangedFilesCache<'a> {
+    fn new(workspace_root: &'a Utf8Path) -> Self {
+        Self {
+            workspace_root,
+            repo_root: None,
+            changed_files: HashMap::new(),
+        }
+    }
+
+    /// Returns the absolute paths of files in the repository containing the workspace which differ
+    /// from `rev`.
+    ///
+    /// Errors aren't cached.
+    fn get(&mut self, rev: &str) -> Result<&[Utf8PathBuf], String> {
+        if !self.changed_files.contains_key(rev) {
+            let repo_root = match &self.repo_root {
+                Some(repo_root) => repo_root,
+                None => self.repo_root.insert(Utf8PathBuf::from(
+                    run_git(self.workspace_root, &["rev-parse", "--show-toplevel"])?.trim_end(),
+                )),
+            };
+            let changed_files = changed_files(rev, repo_root)?;
+            self.changed_files.insert(rev.to_owned(), changed_files);
+        }
+        Ok(&self.changed_files[rev])
+    }
+}
+
+/// Returns the absolute paths of files in the repository at `repo_root` which differ from `rev`.
+fn changed_files(rev: &str, repo_root: &Utf8Path) -> Result<Vec<Utf8PathBuf>, String> {
+    // Both commands print paths relative to the top of the repository when run from there.
+    let mut output = run_git(repo_root, &["diff", "--name-only", "-z", rev, "--"])?;
+    // If a single revision is passed in, it's compared against the working tree, which also
+    // includes files that haven't been added to git yet. Ranges like `a...b` only compare commits.
+    if !rev.contains("..") {
+        output.push_str(&run_git(
+            repo_root,
+            &["ls-files", "--others", "--exclude-standard", "-z"],
+        )?);
+    }
+
+    Ok(output
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(|path| repo_root.join(path))
+        .collect())
+}
+
+fn git_changed_packages(
+    rev: &str,
+    span: SourceSpan,
+    changed_files: &mut ChangedFilesCache<'_>,
+    all_packages: &[PackageMetadata],
+    errors: &mut Vec<ParseSingleError>,
+) -> HashSet<PackageId> {
+    changed_packages(rev, changed_files, all_packages).unwrap_or_else(|message| {
+        errors.push(ParseSingleError::GitDiffError { span, message });
+        HashSet::new()
+    })
+}
+
+fn run_git(dir: &Utf8Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|err| format!("failed to run git: {err}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(match stderr.lines().next() {
+            Some(line) => line.to_owned(),
+            None => format!("git {} failed with {}", args.join(" "), output.status),
+        });
+    }
+    String::from_utf8(output.stdout).map_err(|_| "git output was not valid UTF-8".to_owned())
+}
+
 fn compile_set_def(
     set: &SetDef,
     packages: &[PackageMetadata],
     cache: &mut DependsCache,
+    changed_files: &mut ChangedFilesCache<'_>,
     errors: &mut Vec<ParseSingleError>,
 ) -> FilteringSet {
     match set {
@@ -112,6 +242,34 @@ fn compile_set_def(
         SetDef::BinaryId(matcher, span) => FilteringSet::BinaryId(matcher.clone(), *span),
         SetDef::Platform(platform, span) => FilteringSet::Platform(*platform, *span),
         SetDef::Test(matcher, span) => FilteringSet::Test(matcher.clone(), *span),
+        SetDef::Group(matcher, span) => FilteringSet::Group(matcher.clone(), *span),
+        SetDef::Ignored => FilteringSet::Ignored,
+        // Unlike the other package predicates, it isn't an error for these to match no packages:
+        // that just means nothing changed.
+        SetDef::Changed(rev, span) => FilteringSet::Packages(git_changed_packages(
+            rev,
+            *span,
+            changed_files,
+            packages,
+            errors,
+        )),
+        SetDef::Affected(rev, span) => {
+            let changed = git_changed_packages(rev, *span, changed_files, packages, errors);
+            FilteringSet::Packages(
+                packages
+                    .iter()
+                    .map(|p| p.id())
+                    .filter(|id| {
+                        changed
+                            .iter()
+                            .any(|changed| cache.depends_on(id, changed).unwrap_or(false))
+                    })
+                    .cloned()
+                    .collect(),
+            )
+        }
+        // Named filtersets are resolved in compile_expr.
+        SetDef::Named(..) => FilteringSet::None,
         SetDef::All => FilteringSet::All,
         SetDef::None => FilteringSet::None,
     }
@@ -132,13 +290,22 @@ fn compile_expr(
     expr: &ParsedExpr,
     packages: &[PackageMetadata],
     cache: &mut DependsCache,
+    changed_files: &mut ChangedFilesCache<'_>,
+    filtersets: &NamedFiltersets,
     errors: &mut Vec<ParseSingleError>,
 ) -> CompiledExpr {
     use crate::expression::ExprFrame::*;
     use recursion::CollapsibleExt;
 
     Wrapped(expr).collapse_frames(|layer: ExprFrame<&SetDef, CompiledExpr>| match layer {
-        Set(set) => CompiledExpr::Set(compile_set_def(set, packages, cache, errors)),
+        Set(SetDef::Named(name, span)) => match filtersets.get(name) {
+            Some(expr) => expr.compiled.clone(),
+            None => {
+                errors.push(ParseSingleError::UnknownFilterset(*span));
+                CompiledExpr::Set(FilteringSet::None)
+            }
+        },
+        Set(set) => CompiledExpr::Set(compile_set_def(set, packages, cache, changed_files, errors)),
         Not(expr) => CompiledExpr::Not(Box::new(expr)),
         Union(expr_1, expr_2) => CompiledExpr::Union(Box::new(expr_1), Box::new(expr_2)),
         Intersection(expr_1, expr_2) => {
@@ -151,3 +318,88 @@ fn compile_expr(
         Parens(expr_1) => expr_1,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use camino_tempfile::tempdir;
+
+    fn git(dir: &Utf8Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .status()
+            .expect("git ran successfully");
+        assert!(status.success(), "git {} succeeded", args.join(" "));
+    }
+
+    #[test]
+    fn test_changed_files() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize_utf8().unwrap();
+        git(&root, &["init", "-q"]);
+        std::fs::create_dir_all(root.join("a/src")).unwrap();
+        std::fs::write(root.join("a/src/lib.rs"), "").unwrap();
+        std::fs::write(root.join("a/src/unchanged.rs"), "").unwrap();
+        std::fs::write(root.join(".gitignore"), "/ignored\n").unwrap();
+        git(&root, &["add", "."]);
+        git(&root, &["commit", "-q", "-m", "initial"]);
+
+        std::fs::write(root.join("a/src/lib.rs"), "mod new;").unwrap();
+        std::fs::write(root.join("a/src/new.rs"), "").unwrap();
+        std::fs::create_dir_all(root.join("ignored")).unwrap();
+        std::fs::write(root.join("ignored/file"), "").unwrap();
+
+        let mut changed = changed_files("HEAD", &root).unwrap();
+        changed.sort();
+        assert_eq!(
+            changed,
+            [root.join("a/src/lib.rs"), root.join("a/src/new.rs")],
+            "modified and untracked files are changed, ignored files aren't"
+        );
+
+        // Untracked files aren't part of a range of commits.
+        let changed = changed_files("HEAD...HEAD", &root).unwrap();
+        assert_eq!(changed, Vec::<Utf8PathBuf>::new());
+    }
+
+    #[test]
+    fn test_changed_files_cache() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize_utf8().unwrap();
+        git(&root, &["init", "-q"]);
+        std::fs::write(root.join("a.rs"), "").unwrap();
+        git(&root, &["add", "."]);
+        git(&root, &["commit", "-q", "-m", "initial"]);
+        std::fs::write(root.join("a.rs"), "fn a() {}").unwrap();
+        std::fs::create_dir(root.join("nested")).unwrap();
+
+        let nested = root.join("nested");
+        let mut cache = ChangedFilesCache::new(&nested);
+        assert_eq!(
+            cache.get("HEAD").unwrap(),
+            [root.join("a.rs")],
+            "paths are relative to the repository root rather than the workspace root"
+        );
+
+        std::fs::write(root.join("b.rs"), "").unwrap();
+        assert_eq!(
+            cache.get("HEAD").unwrap(),
+            [root.join("a.rs")],
+            "results are cached for the same revision"
+        );
+        let mut cache = ChangedFilesCache::new(&nested);
+        let mut changed = cache.get("HEAD").unwrap().to_vec();
+        changed.sort();
+        assert_eq!(
+            changed,
+            [root.join("a.rs"), root.join("b.rs")],
+            "results aren't shared between caches"
+        );
+
+        assert!(cache.get("no-such-rev").is_err(), "errors are returned");
+        git(&root, &["tag", "no-such-rev"]);
+        assert!(cache.get("no-such-rev").is_ok(), "errors aren't cached");
+    }
+}
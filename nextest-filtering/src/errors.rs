@@ -26,6 +26,19 @@ impl FilterExpressionParseErrors {
     }
 }
 
+/// Errors that occurred while parsing the definition of a named filterset.
+///
+/// Returned by [`NamedFiltersets::new`](crate::NamedFiltersets::new).
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct NamedFiltersetParseErrors {
+    /// The name of the filterset.
+    pub name: String,
+
+    /// The errors in the filterset's definition.
+    pub errors: FilterExpressionParseErrors,
+}
+
 /// An individual error that occurred while parsing a filter expression.
 #[derive(Clone, Debug, Error, Diagnostic, PartialEq, Eq)]
 #[non_exhaustive]
@@ -100,6 +113,40 @@ pub enum ParseSingleError {
     #[error("invalid argument for platform")]
     InvalidPlatformArgument(#[label("expected \"target\" or \"host\"")] SourceSpan),
 
+    /// Expected a git revision for a `changed()` or `affected()` predicate.
+    #[error("invalid git revision")]
+    InvalidGitRevision(#[label("expected a git revision, such as `origin/main`")] SourceSpan),
+
+    /// A `set()` predicate referred to a filterset that isn't defined.
+    #[error("unknown filterset")]
+    UnknownFilterset(#[label("no filterset with this name is defined")] SourceSpan),
+
+    /// Named filtersets refer to each other in a cycle.
+    #[error("filtersets refer to each other in a cycle")]
+    FiltersetCycle {
+        /// The reference that closes the cycle.
+        #[label("cycle: {}", cycle)]
+        span: SourceSpan,
+
+        /// The names of the filtersets in the cycle, for example `a -> b -> a`.
+        cycle: String,
+    },
+
+    /// A `group()` predicate was used where test groups aren't known.
+    #[error("`group()` is not allowed in this context")]
+    GroupNotAllowed(#[label("test groups are not known here")] SourceSpan),
+
+    /// Running git to find changed files failed.
+    #[error("error finding changed files")]
+    GitDiffError {
+        /// The part of the input that failed.
+        #[label("{}", message)]
+        span: SourceSpan,
+
+        /// A message indicating the failure.
+        message: String,
+    },
+
     /// An unknown parsing error occurred.
     #[error("unknown parsing error")]
     Unknown,
@@ -3,6 +3,7 @@
 
 use crate::{
     errors::{FilterExpressionParseErrors, ParseSingleError, State},
+    named::NamedFiltersets,
     parsing::{
         parse, DisplayParsedRegex, DisplayParsedString, ExprResult, GenericGlob, ParsedExpr,
         SetDef, Span,
@@ -123,6 +124,10 @@ pub enum FilteringSet {
     BinaryId(NameMatcher, SourceSpan),
     /// All tests matching a name
     Test(NameMatcher, SourceSpan),
+    /// All tests assigned to a test group matching a name
+    Group(NameMatcher, SourceSpan),
+    /// All tests marked as ignored
+    Ignored,
     /// All tests
     All,
     /// No tests
@@ -156,6 +161,68 @@ pub struct TestQuery<'a> {
 
     /// The name of the test.
     pub test_name: &'a str,
+
+    /// True if the test is marked `#[ignore]`.
+    pub ignored: bool,
+
+    /// The name of the test group the test is assigned to, if known.
+    ///
+    /// If this is `None`, `group()` predicates don't match the test.
+    pub test_group: Option<&'a str>,
+}
+
+impl<'a> TestQuery<'a> {
+    /// Creates a new query for a test that isn't ignored, and isn't assigned to a test group.
+    pub fn new(binary_query: BinaryQuery<'a>, test_name: &'a str) -> Self {
+        Self {
+            binary_query,
+            test_name,
+            ignored: false,
+            test_group: None,
+        }
+    }
+
+    /// Sets whether the test is marked `#[ignore]`.
+    pub fn with_ignored(mut self, ignored: bool) -> Self {
+        self.ignored = ignored;
+        self
+    }
+
+    /// Sets the name of the test group the test is assigned to.
+    pub fn with_test_group(mut self, test_group: Option<&'a str>) -> Self {
+        self.test_group = test_group;
+        self
+    }
+}
+
+/// An explanation of why a test or binary matched or didn't match a filter expression.
+///
+/// Returned by [`FilteringExpr::explain_test`] and [`FilteringExpr::explain_binary`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TestMatchExplanation {
+    /// True if the test matches the expression.
+    pub matches: bool,
+
+    /// The predicates that decided the result, in the order they appear in the expression.
+    ///
+    /// For example, if `test(a)` matches a test, `test(a) | test(b)` is decided by `test(a)`
+    /// alone. If neither predicate matches, the result is decided by both of them.
+    pub deciding: Vec<DecidingPredicate>,
+}
+
+/// A predicate that decided whether a test matched a filter expression.
+///
+/// Part of [`TestMatchExplanation`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DecidingPredicate {
+    /// The predicate, for example `test(foo)`.
+    pub predicate: String,
+
+    /// True if the predicate itself matched the test.
+    ///
+    /// This can differ from whether the test matched the overall expression, for example if the
+    /// predicate is negated with `not`.
+    pub matches: bool,
 }
 
 /// Filtering expression.
@@ -207,6 +274,10 @@ impl FilteringSet {
             Self::Platform(platform, _) => query.binary_query.platform == *platform,
             Self::Kind(matcher, _) => matcher.is_match(query.binary_query.kind.as_str()),
             Self::Packages(packages) => packages.contains(query.binary_query.package_id),
+            Self::Group(matcher, _) => query
+                .test_group
+                .is_some_and(|test_group| matcher.is_match(test_group)),
+            Self::Ignored => query.ignored,
         }
     }
 
@@ -214,7 +285,7 @@ impl FilteringSet {
         match self {
             Self::All => Logic::top(),
             Self::None => Logic::bottom(),
-            Self::Test(_, _) => None,
+            Self::Test(_, _) | Self::Group(_, _) | Self::Ignored => None,
             Self::Binary(matcher, _) => Some(matcher.is_match(query.binary_name)),
             Self::BinaryId(matcher, _) => Some(matcher.is_match(query.binary_id.as_str())),
             Self::Platform(platform, _) => Some(query.platform == *platform),
@@ -227,27 +298,62 @@ impl FilteringSet {
 impl FilteringExpr {
     /// Parse a filtering expression
     pub fn parse(input: String, graph: &PackageGraph) -> Result<Self, FilterExpressionParseErrors> {
+        Self::parse_with_filtersets(input, graph, &NamedFiltersets::default())
+    }
+
+    /// Parse a filtering expression, resolving `set()` predicates against the given named
+    /// filtersets.
+    pub fn parse_with_filtersets(
+        input: String,
+        graph: &PackageGraph,
+        filtersets: &NamedFiltersets,
+    ) -> Result<Self, FilterExpressionParseErrors> {
+        let parsed = Self::parse_uncompiled(&input)?;
+        let compiled = crate::compile::compile(&parsed, graph, filtersets)
+            .map_err(|errors| FilterExpressionParseErrors::new(input.clone(), errors))?;
+        Ok(Self {
+            input,
+            parsed,
+            compiled,
+        })
+    }
+
+    /// Returns an error if this expression uses `group()`, either directly or through a named
+    /// filterset.
+    ///
+    /// Test groups are assigned by per-test overrides, so `group()` can't be used in expressions
+    /// that are evaluated before test groups are known.
+    pub fn check_no_group(
+        &self,
+        filtersets: &NamedFiltersets,
+    ) -> Result<(), FilterExpressionParseErrors> {
+        let mut errors = Vec::new();
+        group_spans(&self.parsed, filtersets, &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(FilterExpressionParseErrors::new(
+                self.input.clone(),
+                errors
+                    .into_iter()
+                    .map(ParseSingleError::GroupNotAllowed)
+                    .collect(),
+            ))
+        }
+    }
+
+    pub(crate) fn parse_uncompiled(input: &str) -> Result<ParsedExpr, FilterExpressionParseErrors> {
         let errors = RefCell::new(Vec::new());
-        match parse(Span::new_extra(&input, State::new(&errors))) {
+        match parse(Span::new_extra(input, State::new(&errors))) {
             Ok(parsed_expr) => {
                 let errors = errors.into_inner();
 
                 if !errors.is_empty() {
-                    return Err(FilterExpressionParseErrors::new(input.clone(), errors));
+                    return Err(FilterExpressionParseErrors::new(input, errors));
                 }
 
                 match parsed_expr {
-                    ExprResult::Valid(parsed) => {
-                        let compiled =
-                            crate::compile::compile(&parsed, graph).map_err(|errors| {
-                                FilterExpressionParseErrors::new(input.clone(), errors)
-                            })?;
-                        Ok(Self {
-                            input,
-                            parsed,
-                            compiled,
-                        })
-                    }
+                    ExprResult::Valid(parsed) => Ok(parsed),
                     _ => {
                         // should not happen
                         // If an ParsedExpr::Error is produced, we should also have an error inside
@@ -279,38 +385,57 @@ impl FilteringExpr {
     /// * `Some(false)` if this binary is definitely not accepted.
     /// * `None` if this binary might or might not be accepted.
     pub fn matches_binary(&self, query: &BinaryQuery<'_>) -> Option<bool> {
-        use ExprFrame::*;
-        Wrapped(&self.compiled).collapse_frames(|layer: ExprFrame<&FilteringSet, Option<bool>>| {
-            match layer {
-                Set(set) => set.matches_binary(query),
-                Not(a) => a.logic_not(),
-                // TODO: or_else/and_then?
-                Union(a, b) => a.logic_or(b),
-                Intersection(a, b) => a.logic_and(b),
-                Difference(a, b) => a.logic_and(b.logic_not()),
-                Parens(a) => a,
-            }
-        })
+        self.compiled.matches_binary(query)
+    }
+
+    /// Returns true if this expression uses `group()`, either directly or through a named
+    /// filterset.
+    ///
+    /// If it doesn't, the test group doesn't need to be set in the [`TestQuery`].
+    pub fn uses_group(&self) -> bool {
+        self.compiled.uses_group()
     }
 
     /// Returns true if the given test is accepted by this filter expression.
     pub fn matches_test(&self, query: &TestQuery<'_>) -> bool {
-        use ExprFrame::*;
-        Wrapped(&self.compiled).collapse_frames(|layer: ExprFrame<&FilteringSet, bool>| match layer
-        {
-            Set(set) => set.matches_test(query),
-            Not(a) => !a,
-            Union(a, b) => a || b,
-            Intersection(a, b) => a && b,
-            Difference(a, b) => a && !b,
-            Parens(a) => a,
-        })
+        self.compiled.matches_test(query)
+    }
+
+    /// Explains whether the given test is accepted by this filter expression, in terms of the
+    /// predicates that decided the result.
+    pub fn explain_test(&self, query: &TestQuery<'_>) -> TestMatchExplanation {
+        let mut deciding = Vec::new();
+        let matches = explain(
+            &self.parsed,
+            &self.compiled,
+            &|compiled| Some(compiled.matches_test(query)),
+            &mut deciding,
+        )
+        .expect("tests always either match or don't match");
+        TestMatchExplanation { matches, deciding }
+    }
+
+    /// Explains whether the given binary is accepted by this filter expression, in terms of the
+    /// binary-level predicates (such as `package()` or `kind()`) that decided the result.
+    ///
+    /// Returns `None` if this can't be determined without looking at the tests in the binary, in
+    /// the same cases where [`Self::matches_binary`] does.
+    pub fn explain_binary(&self, query: &BinaryQuery<'_>) -> Option<TestMatchExplanation> {
+        let mut deciding = Vec::new();
+        let matches = explain(
+            &self.parsed,
+            &self.compiled,
+            &|compiled| compiled.matches_binary(query),
+            &mut deciding,
+        )?;
+        Some(TestMatchExplanation { matches, deciding })
     }
 
     /// Returns true if the given expression needs dependencies information to work
     pub fn needs_deps(raw_expr: &str) -> bool {
-        // the expression needs dependencies expression if it uses deps(..) or rdeps(..)
-        raw_expr.contains("deps")
+        // the expression needs dependencies expression if it uses deps(..), rdeps(..) or
+        // affected(..)
+        raw_expr.contains("deps") || raw_expr.contains("affected")
     }
 }
 
@@ -471,3 +596,166 @@ impl<'a> Collapsible for Wrapped<&'a ParsedExpr> {
         }
     }
 }
+
+fn group_spans(expr: &ParsedExpr, filtersets: &NamedFiltersets, spans: &mut Vec<SourceSpan>) {
+    match expr {
+        ParsedExpr::Not(_, expr) | ParsedExpr::Parens(expr) => group_spans(expr, filtersets, spans),
+        ParsedExpr::Union(_, expr_1, expr_2)
+        | ParsedExpr::Intersection(_, expr_1, expr_2)
+        | ParsedExpr::Difference(_, expr_1, expr_2) => {
+            group_spans(expr_1, filtersets, spans);
+            group_spans(expr_2, filtersets, spans);
+        }
+        ParsedExpr::Set(SetDef::Group(_, span)) => spans.push(*span),
+        ParsedExpr::Set(SetDef::Named(name, span)) => {
+            if filtersets
+                .get(name)
+                .is_some_and(|expr| expr.compiled.uses_group())
+            {
+                spans.push(*span);
+            }
+        }
+        ParsedExpr::Set(_) => {}
+    }
+}
+
+/// Explains the result of `compiled`, whose leaves are evaluated with `eval`.
+///
+/// Results are `None` if they can't be determined, which happens for test-level predicates when
+/// explaining whether a binary matches. Predicates only decide a result if it's known.
+fn explain(
+    parsed: &ParsedExpr,
+    compiled: &CompiledExpr,
+    eval: &dyn Fn(&CompiledExpr) -> Option<bool>,
+    deciding: &mut Vec<DecidingPredicate>,
+) -> Option<bool> {
+    // The compiled expression has the same shape as the parsed one, except that parentheses are
+    // removed, `a - b` is compiled to `a & not b`, and named filtersets are replaced by their
+    // definitions.
+    match (parsed, compiled) {
+        (ParsedExpr::Parens(parsed), _) => explain(parsed, compiled, eval, deciding),
+        (ParsedExpr::Not(_, parsed), CompiledExpr::Not(compiled)) => {
+            explain(parsed, compiled, eval, deciding).logic_not()
+        }
+        (ParsedExpr::Union(_, parsed_1, parsed_2), CompiledExpr::Union(compiled_1, compiled_2)) => {
+            let mut deciding_1 = Vec::new();
+            let matches_1 = explain(parsed_1, compiled_1, eval, &mut deciding_1);
+            if matches_1 == Some(true) {
+                deciding.extend(deciding_1);
+                return matches_1;
+            }
+            let mut deciding_2 = Vec::new();
+            let matches = matches_1.logic_or(explain(parsed_2, compiled_2, eval, &mut deciding_2));
+            match matches {
+                Some(true) => deciding.extend(deciding_2),
+                Some(false) => {
+                    // Neither side matched, so both of them decided the result.
+                    deciding.extend(deciding_1);
+                    deciding.extend(deciding_2);
+                }
+                None => {}
+            }
+            matches
+        }
+        (
+            ParsedExpr::Intersection(_, parsed_1, parsed_2),
+            CompiledExpr::Intersection(compiled_1, compiled_2),
+        ) => explain_and(
+            |deciding| explain(parsed_1, compiled_1, eval, deciding),
+            |deciding| explain(parsed_2, compiled_2, eval, deciding),
+            deciding,
+        ),
+        (
+            ParsedExpr::Difference(_, parsed_1, parsed_2),
+            CompiledExpr::Intersection(compiled_1, compiled_2),
+        ) => match &**compiled_2 {
+            CompiledExpr::Not(compiled_2) => explain_and(
+                |deciding| explain(parsed_1, compiled_1, eval, deciding),
+                |deciding| explain(parsed_2, compiled_2, eval, deciding).logic_not(),
+                deciding,
+            ),
+            _ => explain_leaf(parsed, compiled, eval, deciding),
+        },
+        _ => explain_leaf(parsed, compiled, eval, deciding),
+    }
+}
+
+fn explain_and(
+    explain_1: impl FnOnce(&mut Vec<DecidingPredicate>) -> Option<bool>,
+    explain_2: impl FnOnce(&mut Vec<DecidingPredicate>) -> Option<bool>,
+    deciding: &mut Vec<DecidingPredicate>,
+) -> Option<bool> {
+    let mut deciding_1 = Vec::new();
+    let matches_1 = explain_1(&mut deciding_1);
+    if matches_1 == Some(false) {
+        deciding.extend(deciding_1);
+        return matches_1;
+    }
+    let mut deciding_2 = Vec::new();
+    let matches = matches_1.logic_and(explain_2(&mut deciding_2));
+    match matches {
+        Some(true) => {
+            // Both sides matched, so both of them decided the result.
+            deciding.extend(deciding_1);
+            deciding.extend(deciding_2);
+        }
+        Some(false) => deciding.extend(deciding_2),
+        None => {}
+    }
+    matches
+}
+
+fn explain_leaf(
+    parsed: &ParsedExpr,
+    compiled: &CompiledExpr,
+    eval: &dyn Fn(&CompiledExpr) -> Option<bool>,
+    deciding: &mut Vec<DecidingPredicate>,
+) -> Option<bool> {
+    let matches = eval(compiled);
+    if let Some(matches) = matches {
+        deciding.push(DecidingPredicate {
+            predicate: parsed.to_string(),
+            matches,
+        });
+    }
+    matches
+}
+
+impl CompiledExpr {
+    fn matches_binary(&self, query: &BinaryQuery<'_>) -> Option<bool> {
+        use ExprFrame::*;
+        Wrapped(self).collapse_frames(|layer: ExprFrame<&FilteringSet, Option<bool>>| {
+            match layer {
+                Set(set) => set.matches_binary(query),
+                Not(a) => a.logic_not(),
+                // TODO: or_else/and_then?
+                Union(a, b) => a.logic_or(b),
+                Intersection(a, b) => a.logic_and(b),
+                Difference(a, b) => a.logic_and(b.logic_not()),
+                Parens(a) => a,
+            }
+        })
+    }
+
+    fn matches_test(&self, query: &TestQuery<'_>) -> bool {
+        use ExprFrame::*;
+        Wrapped(self).collapse_frames(|layer: ExprFrame<&FilteringSet, bool>| match layer {
+            Set(set) => set.matches_test(query),
+            Not(a) => !a,
+            Union(a, b) => a || b,
+            Intersection(a, b) => a && b,
+            Difference(a, b) => a && !b,
+            Parens(a) => a,
+        })
+    }
+
+    fn uses_group(&self) -> bool {
+        match self {
+            Self::Not(expr) => expr.uses_group(),
+            Self::Union(expr_1, expr_2) | Self::Intersection(expr_1, expr_2) => {
+                expr_1.uses_group() || expr_2.uses_group()
+            }
+            Self::Set(set) => matches!(set, FilteringSet::Group(..)),
+        }
+    }
+}
@@ -7,11 +7,14 @@
 mod compile;
 pub mod errors;
 mod expression;
+mod named;
 mod parsing;
 #[cfg(any(test, feature = "internal-testing"))]
 mod proptest_helpers;
 
 pub use expression::{
-    BinaryQuery, CompiledExpr, FilteringExpr, FilteringSet, NameMatcher, TestQuery,
+    BinaryQuery, CompiledExpr, DecidingPredicate, FilteringExpr, FilteringSet, NameMatcher,
+    TestMatchExplanation, TestQuery,
 };
+pub use named::NamedFiltersets;
 pub use parsing::ParsedExpr;
@@ -0,0 +1,215 @@
+// Copyright (c) The nextest Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::{
+    errors::{FilterExpressionParseErrors, NamedFiltersetParseErrors, ParseSingleError},
+    expression::FilteringExpr,
+    parsing::{ParsedExpr, SetDef},
+};
+use guppy::graph::PackageGraph;
+use miette::SourceSpan;
+use std::collections::BTreeMap;
+
+/// A collection of named filter expressions, which can be referred to from other expressions
+/// through the `set(name)` predicate.
+#[derive(Clone, Debug, Default)]
+pub struct NamedFiltersets {
+    sets: BTreeMap<String, FilteringExpr>,
+}
+
+impl NamedFiltersets {
+    /// Parses and compiles the given `(name, expression)` pairs.
+    ///
+    /// Definitions may refer to each other through `set(name)`, in any order. References to
+    /// undefined filtersets and cycles between definitions are reported as errors, keyed by the
+    /// name of the definition they occur in.
+    pub fn new(
+        defs: impl IntoIterator<Item = (String, String)>,
+        graph: &PackageGraph,
+    ) -> Result<Self, Vec<NamedFiltersetParseErrors>> {
+        let mut errors = BTreeMap::new();
+        let mut parsed = BTreeMap::new();
+        let mut inputs = BTreeMap::new();
+        for (name, input) in defs {
+            match FilteringExpr::parse_uncompiled(&input) {
+                Ok(expr) => {
+                    parsed.insert(name.clone(), expr);
+                }
+                Err(error) => {
+                    errors.insert(name.clone(), error.errors);
+                }
+            }
+            inputs.insert(name, input);
+        }
+
+        // Collect references, reporting ones to filtersets that aren't defined. Definitions which
+        // failed to parse still count as defined, so their errors aren't reported twice.
+        let mut references = BTreeMap::new();
+        for (name, expr) in &parsed {
+            let mut refs = Vec::new();
+            collect_references(expr, &mut refs);
+            for (reference, span) in &refs {
+                if !inputs.contains_key(reference) {
+                    errors
+                        .entry(name.clone())
+                        .or_insert_with(Vec::new)
+                        .push(ParseSingleError::UnknownFilterset(*span));
+                }
+            }
+            references.insert(name.as_str(), refs);
+        }
+
+        // Detect cycles, and determine the order in which to compile the definitions so that
+        // each one is compiled after the filtersets it refers to.
+        let mut order = Vec::with_capacity(parsed.len());
+        let mut visit_state = BTreeMap::new();
+        for name in parsed.keys() {
+            visit(
+                name,
+                &references,
+                &mut visit_state,
+                &mut Vec::new(),
+                &mut order,
+                &mut errors,
+            );
+        }
+
+        if !errors.is_empty() {
+            return Err(into_parse_errors(errors, &mut inputs));
+        }
+
+        let mut sets = BTreeMap::new();
+        for name in order {
+            let input = inputs
+                .remove(name)
+                .expect("every parsed filterset has an input");
+            let parsed = parsed[name].clone();
+            let filtersets = Self { sets };
+            let compiled = crate::compile::compile(&parsed, graph, &filtersets);
+            sets = filtersets.sets;
+            match compiled {
+                Ok(compiled) => {
+                    sets.insert(
+                        name.to_owned(),
+                        FilteringExpr {
+                            input,
+                            parsed,
+                            compiled,
+                        },
+                    );
+                }
+                Err(compile_errors) => {
+                    errors.insert(name.to_owned(), compile_errors);
+                    inputs.insert(name.to_owned(), input);
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(Self { sets })
+        } else {
+            Err(into_parse_errors(errors, &mut inputs))
+        }
+    }
+
+    /// Returns the filterset with the given name, if it exists.
+    pub fn get(&self, name: &str) -> Option<&FilteringExpr> {
+        self.sets.get(name)
+    }
+
+    /// Returns true if no filtersets are defined.
+    pub fn is_empty(&self) -> bool {
+        self.sets.is_empty()
+    }
+
+    /// Iterates over the filtersets in order of name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &FilteringExpr)> + '_ {
+        self.sets.iter().map(|(name, expr)| (name.as_str(), expr))
+    }
+}
+
+fn collect_references(expr: &ParsedExpr, refs: &mut Vec<(String, SourceSpan)>) {
+    match expr {
+        ParsedExpr::Not(_, expr) | ParsedExpr::Parens(expr) => collect_references(expr, refs),
+        ParsedExpr::Union(_, expr_1, expr_2)
+        | ParsedExpr::Intersection(_, expr_1, expr_2)
+        | ParsedExpr::Difference(_, expr_1, expr_2) => {
+            collect_references(expr_1, refs);
+            collect_references(expr_2, refs);
+        }
+        ParsedExpr::Set(SetDef::Named(name, span)) => refs.push((name.clone(), *span)),
+        ParsedExpr::Set(_) => {}
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum VisitState {
+    InProgress,
+    Done,
+}
+
+/// Depth-first search over filterset references, pushing each filterset onto `order` after the
+/// filtersets it refers to.
+fn visit<'a>(
+    name: &'a str,
+    references: &BTreeMap<&'a str, Vec<(String, SourceSpan)>>,
+    visit_state: &mut BTreeMap<&'a str, VisitState>,
+    stack: &mut Vec<&'a str>,
+    order: &mut Vec<&'a str>,
+    errors: &mut BTreeMap<String, Vec<ParseSingleError>>,
+) {
+    if visit_state.contains_key(name) {
+        return;
+    }
+    // Definitions which failed to parse have no entry in `references`.
+    let Some((&name, refs)) = references.get_key_value(name) else {
+        return;
+    };
+
+    visit_state.insert(name, VisitState::InProgress);
+    stack.push(name);
+    for (reference, span) in refs {
+        match visit_state.get(reference.as_str()) {
+            Some(VisitState::InProgress) => {
+                let start = stack
+                    .iter()
+                    .position(|n| *n == reference.as_str())
+                    .expect("in-progress filterset is on the stack");
+                let mut cycle = stack[start..].to_vec();
+                cycle.push(reference.as_str());
+                errors
+                    .entry(name.to_owned())
+                    .or_default()
+                    .push(ParseSingleError::FiltersetCycle {
+                        span: *span,
+                        cycle: cycle.join(" -> "),
+                    });
+            }
+            Some(VisitState::Done) => {}
+            None => {
+                if let Some((&reference, _)) = references.get_key_value(reference.as_str()) {
+                    visit(reference, references, visit_state, stack, order, errors);
+                }
+            }
+        }
+    }
+    stack.pop();
+    visit_state.insert(name, VisitState::Done);
+    order.push(name);
+}
+
+fn into_parse_errors(
+    errors: BTreeMap<String, Vec<ParseSingleError>>,
+    inputs: &mut BTreeMap<String, String>,
+) -> Vec<NamedFiltersetParseErrors> {
+    errors
+        .into_iter()
+        .map(|(name, errors)| {
+            let input = inputs.remove(&name).unwrap_or_default();
+            NamedFiltersetParseErrors {
+                name,
+                errors: FilterExpressionParseErrors::new(input, errors),
+            }
+        })
+        .collect()
+}
@@ -56,6 +56,11 @@ pub enum SetDef<S = SourceSpan> {
     BinaryId(NameMatcher, S),
     Platform(BuildPlatform, S),
     Test(NameMatcher, S),
+    Changed(String, S),
+    Affected(String, S),
+    Named(String, S),
+    Group(NameMatcher, S),
+    Ignored,
     All,
     None,
 }
@@ -71,6 +76,11 @@ impl SetDef {
             Self::BinaryId(matcher, _) => SetDef::BinaryId(matcher, ()),
             Self::Platform(platform, _) => SetDef::Platform(platform, ()),
             Self::Test(matcher, _) => SetDef::Test(matcher, ()),
+            Self::Changed(rev, _) => SetDef::Changed(rev, ()),
+            Self::Affected(rev, _) => SetDef::Affected(rev, ()),
+            Self::Named(name, _) => SetDef::Named(name, ()),
+            Self::Group(matcher, _) => SetDef::Group(matcher, ()),
+            Self::Ignored => SetDef::Ignored,
             Self::All => SetDef::All,
             Self::None => SetDef::None,
         }
@@ -88,6 +98,11 @@ impl<S> fmt::Display for SetDef<S> {
             Self::BinaryId(matcher, _) => write!(f, "binary_id({matcher})"),
             Self::Platform(platform, _) => write!(f, "platform({platform})"),
             Self::Test(matcher, _) => write!(f, "test({matcher})"),
+            Self::Changed(rev, _) => write!(f, "changed({rev})"),
+            Self::Affected(rev, _) => write!(f, "affected({rev})"),
+            Self::Named(name, _) => write!(f, "set({name})"),
+            Self::Group(matcher, _) => write!(f, "group({matcher})"),
+            Self::Ignored => write!(f, "ignored()"),
             Self::All => write!(f, "all()"),
             Self::None => write!(f, "none()"),
         }
@@ -595,6 +610,58 @@ fn platform_def(i: Span) -> IResult<Option<SetDef>> {
     ))
 }
 
+fn git_revision_set_def(
+    name: &'static str,
+    make_set: fn(String, SourceSpan) -> SetDef,
+) -> impl FnMut(Span) -> IResult<Option<SetDef>> {
+    move |i| {
+        let (i, _) = tag(name)(i)?;
+        let (i, _) = expect_char('(', ParseSingleError::ExpectedOpenParenthesis)(i)?;
+        let start = i.location_offset();
+        let (i, res) = ws(parse_matcher_text)(i)?;
+        let end = i.location_offset();
+        let (i, _) = recover_unexpected_comma(i)?;
+        let (i, _) = expect_char(')', ParseSingleError::ExpectedCloseParenthesis)(i)?;
+
+        // The returned string will include leading and trailing whitespace.
+        let rev = match res.as_deref().map(|res| res.trim()) {
+            // Don't let revisions be interpreted as options to git.
+            Some(rev) if rev.starts_with('-') => {
+                i.extra.report_error(ParseSingleError::InvalidGitRevision(
+                    (start, end - start).into(),
+                ));
+                None
+            }
+            // Empty strings were already reported above.
+            Some("") | None => None,
+            Some(rev) => Some(rev.to_owned()),
+        };
+        Ok((i, rev.map(|rev| make_set(rev, (start, end - start).into()))))
+    }
+}
+
+fn named_set_def(i: Span) -> IResult<Option<SetDef>> {
+    let (i, _) = tag("set")(i)?;
+    let (i, _) = expect_char('(', ParseSingleError::ExpectedOpenParenthesis)(i)?;
+    let start = i.location_offset();
+    let (i, res) = ws(parse_matcher_text)(i)?;
+    let end = i.location_offset();
+    let (i, _) = recover_unexpected_comma(i)?;
+    let (i, _) = expect_char(')', ParseSingleError::ExpectedCloseParenthesis)(i)?;
+
+    // The returned string will include leading and trailing whitespace. Names are resolved when
+    // the expression is compiled.
+    let name = match res.as_deref().map(|res| res.trim()) {
+        // Empty strings were already reported above.
+        Some("") | None => None,
+        Some(name) => Some(name.to_owned()),
+    };
+    Ok((
+        i,
+        name.map(|name| SetDef::Named(name, (start, end - start).into())),
+    ))
+}
+
 #[tracable_parser]
 fn parse_set_def(input: Span) -> IResult<Option<SetDef>> {
     ws(alt((
@@ -607,6 +674,11 @@ fn parse_set_def(input: Span) -> IResult<Option<SetDef>> {
         unary_set_def("binary", DefaultMatcher::Glob, SetDef::Binary),
         unary_set_def("test", DefaultMatcher::Contains, SetDef::Test),
         platform_def,
+        git_revision_set_def("changed", SetDef::Changed),
+        git_revision_set_def("affected", SetDef::Affected),
+        named_set_def,
+        unary_set_def("group", DefaultMatcher::Equal, SetDef::Group),
+        nullary_set_def("ignored", || SetDef::Ignored),
         nullary_set_def("all", || SetDef::All),
         nullary_set_def("none", || SetDef::None),
     )))(input)
@@ -1141,6 +1213,8 @@ mod tests {
 
         assert_eq!(SetDef::None, parse_set("none()"));
 
+        assert_eq!(SetDef::Ignored, parse_set("ignored()"));
+
         assert_set_def!(
             parse_set("package(=something)"),
             Package,
@@ -1178,6 +1252,30 @@ mod tests {
             Platform,
             BuildPlatform::Host
         );
+        assert_set_def!(parse_set("changed(origin/main)"), Changed, "origin/main");
+        assert_set_def!(
+            parse_set("affected( origin/main...HEAD )"),
+            Affected,
+            "origin/main...HEAD"
+        );
+        assert_set_def!(parse_set("set(db-tests)"), Named, "db-tests");
+        assert_set_def!(parse_set("set(  db-tests )"), Named, "db-tests");
+        assert_set_def!(
+            parse_set("group(serial-db)"),
+            Group,
+            NameMatcher::Equal {
+                value: "serial-db".to_string(),
+                implicit: true,
+            }
+        );
+        assert_set_def!(
+            parse_set("group(~serial)"),
+            Group,
+            NameMatcher::Contains {
+                value: "serial".to_string(),
+                implicit: false,
+            }
+        );
     }
 
     #[track_caller]
@@ -1488,6 +1586,30 @@ mod tests {
         assert_error!(error, InvalidPlatformArgument, 9, 8);
     }
 
+    #[test]
+    fn test_invalid_git_revision() {
+        let src = "changed(--output=foo)";
+        let mut errors = parse_err(src);
+        assert_eq!(1, errors.len());
+        let error = errors.remove(0);
+        assert_error!(error, InvalidGitRevision, 8, 12);
+
+        let src = "affected(  -x )";
+        let mut errors = parse_err(src);
+        assert_eq!(1, errors.len());
+        let error = errors.remove(0);
+        assert_error!(error, InvalidGitRevision, 9, 5);
+    }
+
+    #[test]
+    fn test_empty_filterset_name() {
+        let src = "set()";
+        let mut errors = parse_err(src);
+        assert_eq!(1, errors.len());
+        let error = errors.remove(0);
+        assert_error!(error, InvalidString, 4, 0);
+    }
+
     #[test]
     fn test_missing_close_regex() {
         let src = "package(/aaa)";
@@ -10,7 +10,7 @@ use guppy::{
 };
 use nextest_filtering::{
     errors::{FilterExpressionParseErrors, ParseSingleError},
-    BinaryQuery, FilteringExpr, TestQuery,
+    BinaryQuery, FilteringExpr, NamedFiltersets, TestQuery,
 };
 use nextest_metadata::{RustBinaryId, RustTestBinaryKind};
 use test_case::test_case;
@@ -86,17 +86,23 @@ fn test_expr_package_contains() {
     assert!(expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
     assert!(!expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_b, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
     assert!(!expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_c, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
 }
 
@@ -111,17 +117,23 @@ fn test_expr_package_equal() {
     assert!(expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
     assert!(!expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_b, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
     assert!(!expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_c, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
 }
 
@@ -136,17 +148,23 @@ fn test_expr_package_regex() {
     assert!(expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
     assert!(expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_b, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
     assert!(!expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_c, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
 }
 
@@ -162,17 +180,23 @@ fn test_expr_binary_id_glob() {
     assert!(expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
     assert!(expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_b, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
     assert!(!expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_c, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
 }
 
@@ -192,39 +216,53 @@ fn test_expr_deps() {
     assert!(expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
     assert!(expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_b, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
     assert!(expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_c, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
     assert!(expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_d, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
 
     // e-g are not deps of d
     assert!(!expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_e, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
     assert!(!expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_f, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
     assert!(!expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_g, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
 }
 
@@ -244,39 +282,53 @@ fn test_expr_rdeps() {
     assert!(!expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
     assert!(!expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_b, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
     assert!(!expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_c, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
 
     // d-g are rdeps of d
     assert!(expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_d, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
     assert!(expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_e, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
     assert!(expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_f, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
     assert!(expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_g, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
 }
 
@@ -305,6 +357,317 @@ fn test_expr_with_no_matching_packages() {
     assert_error(&errors);
 }
 
+#[test]
+fn test_expr_changed_outside_git() {
+    // The fixture's workspace root doesn't exist, so git can't be run there.
+    let graph = load_graph();
+    for input in ["changed(origin/main)", "affected(origin/main)"] {
+        let errors = FilteringExpr::parse(input.to_owned(), &graph).unwrap_err();
+        assert_eq!(errors.errors.len(), 1, "for input {input}");
+        assert!(
+            matches!(
+                errors.errors[0],
+                ParseSingleError::GitDiffError { span, .. } if span == (input.len() - 12, 11).into()
+            ),
+            "for input {input}: {:?}",
+            errors.errors[0],
+        );
+    }
+}
+
+#[test]
+fn test_expr_named_filterset() {
+    let graph = load_graph();
+    // Definitions may refer to filtersets defined after them.
+    let filtersets = NamedFiltersets::new(
+        [
+            ("lib-a".to_owned(), "set(pkg-a) & kind(lib)".to_owned()),
+            ("pkg-a".to_owned(), "package(crate_a)".to_owned()),
+        ],
+        &graph,
+    )
+    .unwrap();
+    let expr = FilteringExpr::parse_with_filtersets(
+        "set(lib-a) | test(parse)".to_owned(),
+        &graph,
+        &filtersets,
+    )
+    .unwrap();
+
+    let pid_a = mk_pid('a');
+    let pid_b = mk_pid('b');
+    assert!(expr.matches_test(&TestQuery {
+        binary_query: binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Target)
+            .to_query(),
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
+    }));
+    assert!(!expr.matches_test(&TestQuery {
+        binary_query: binary_query(&graph, &pid_a, "test", "my-binary", BuildPlatform::Target)
+            .to_query(),
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
+    }));
+    assert!(!expr.matches_test(&TestQuery {
+        binary_query: binary_query(&graph, &pid_b, "lib", "my-binary", BuildPlatform::Target)
+            .to_query(),
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
+    }));
+    assert!(expr.matches_test(&TestQuery {
+        binary_query: binary_query(&graph, &pid_b, "lib", "my-binary", BuildPlatform::Target)
+            .to_query(),
+        test_name: "test_parse",
+        ignored: false,
+        test_group: None,
+    }));
+}
+
+#[test]
+fn test_expr_unknown_filterset() {
+    let graph = load_graph();
+    let errors = FilteringExpr::parse("set(missing)".to_owned(), &graph).unwrap_err();
+    assert_eq!(
+        errors.errors,
+        vec![ParseSingleError::UnknownFilterset((4, 7).into())]
+    );
+
+    let errors = NamedFiltersets::new(
+        [
+            ("ok".to_owned(), "all()".to_owned()),
+            ("bad".to_owned(), "set(ok) | set(missing)".to_owned()),
+        ],
+        &graph,
+    )
+    .unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].name, "bad");
+    assert_eq!(
+        errors[0].errors.errors,
+        vec![ParseSingleError::UnknownFilterset((14, 7).into())]
+    );
+}
+
+#[test]
+fn test_expr_named_filterset_cycle() {
+    let graph = load_graph();
+    let errors = NamedFiltersets::new(
+        [
+            ("a".to_owned(), "set(b)".to_owned()),
+            ("b".to_owned(), "kind(lib) & set(c)".to_owned()),
+            ("c".to_owned(), "set(a)".to_owned()),
+            ("self".to_owned(), "not set(self)".to_owned()),
+        ],
+        &graph,
+    )
+    .unwrap_err();
+    let errors: Vec<_> = errors
+        .iter()
+        .map(|error| (error.name.as_str(), error.errors.errors.clone()))
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            (
+                "c",
+                vec![ParseSingleError::FiltersetCycle {
+                    span: (4, 1).into(),
+                    cycle: "a -> b -> c -> a".to_owned(),
+                }]
+            ),
+            (
+                "self",
+                vec![ParseSingleError::FiltersetCycle {
+                    span: (8, 4).into(),
+                    cycle: "self -> self".to_owned(),
+                }]
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_expr_ignored() {
+    let graph = load_graph();
+    let expr = parse("ignored() & test(slow)", &graph);
+
+    let pid_a = mk_pid('a');
+    assert!(expr.matches_test(&TestQuery {
+        binary_query: binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Target)
+            .to_query(),
+        test_name: "test_slow",
+        ignored: true,
+        test_group: None,
+    }));
+    assert!(!expr.matches_test(&TestQuery {
+        binary_query: binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Target)
+            .to_query(),
+        test_name: "test_slow",
+        ignored: false,
+        test_group: None,
+    }));
+}
+
+#[test]
+fn test_expr_group() {
+    let graph = load_graph();
+    let expr = parse("group(serial-db)", &graph);
+
+    let pid_a = mk_pid('a');
+    let binary_query = binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Target);
+    assert!(expr.matches_test(&TestQuery {
+        binary_query: binary_query.to_query(),
+        test_name: "test_something",
+        ignored: false,
+        test_group: Some("serial-db"),
+    }));
+    assert!(!expr.matches_test(&TestQuery {
+        binary_query: binary_query.to_query(),
+        test_name: "test_something",
+        ignored: false,
+        test_group: Some("@global"),
+    }));
+    assert!(
+        !expr.matches_test(&TestQuery {
+            binary_query: binary_query.to_query(),
+            test_name: "test_something",
+            ignored: false,
+            test_group: None,
+        }),
+        "group() doesn't match tests whose group isn't known"
+    );
+    assert_eq!(
+        expr.matches_binary(&binary_query.to_query()),
+        None,
+        "group() can't be evaluated for binaries"
+    );
+}
+
+#[test]
+fn test_expr_explain() {
+    let graph = load_graph();
+    let pid_a = mk_pid('a');
+    let binary_query = binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Target);
+    let query = TestQuery {
+        binary_query: binary_query.to_query(),
+        test_name: "tests::test_foo",
+        ignored: false,
+        test_group: None,
+    };
+
+    let explain = |input: &str| {
+        let explanation = parse(input, &graph).explain_test(&query);
+        let deciding: Vec<_> = explanation
+            .deciding
+            .iter()
+            .map(|p| (p.predicate.as_str(), p.matches))
+            .collect();
+        (explanation.matches, format!("{deciding:?}"))
+    };
+
+    // A union that matches is decided by the first side that matches.
+    assert_eq!(
+        explain("test(bar) | test(foo) | test(tests)"),
+        (true, r#"[("test(foo)", true)]"#.to_owned())
+    );
+    // A union that doesn't match is decided by all of its sides.
+    assert_eq!(
+        explain("test(bar) or test(baz)"),
+        (
+            false,
+            r#"[("test(bar)", false), ("test(baz)", false)]"#.to_owned()
+        )
+    );
+    // An intersection that doesn't match is decided by the first side that doesn't match.
+    assert_eq!(
+        explain("test(foo) & (ignored() | test(bar))"),
+        (
+            false,
+            r#"[("ignored()", false), ("test(bar)", false)]"#.to_owned()
+        )
+    );
+    // A difference is decided by its right-hand side if the left-hand side matches.
+    assert_eq!(
+        explain("package(crate_a) - test(foo)"),
+        (false, r#"[("test(foo)", true)]"#.to_owned())
+    );
+    assert_eq!(
+        explain("not test(foo)"),
+        (false, r#"[("test(foo)", true)]"#.to_owned())
+    );
+
+    let explain_binary = |input: &str| {
+        let explanation = parse(input, &graph).explain_binary(&binary_query.to_query())?;
+        let deciding: Vec<_> = explanation
+            .deciding
+            .iter()
+            .map(|p| (p.predicate.as_str(), p.matches))
+            .collect();
+        Some((explanation.matches, format!("{deciding:?}")))
+    };
+
+    // Only binary-level predicates decide whether a binary matches.
+    assert_eq!(
+        explain_binary("test(foo) & package(crate_b)"),
+        Some((false, r#"[("package(crate_b)", false)]"#.to_owned()))
+    );
+    assert_eq!(
+        explain_binary("kind(bin) | (package(crate_b) & test(foo))"),
+        Some((
+            false,
+            r#"[("kind(bin)", false), ("package(crate_b)", false)]"#.to_owned()
+        ))
+    );
+    assert_eq!(
+        explain_binary("package(crate_a) - kind(bin)"),
+        Some((
+            true,
+            r#"[("package(crate_a)", true), ("kind(bin)", false)]"#.to_owned()
+        ))
+    );
+    // If the result depends on the tests in the binary, it isn't explained.
+    assert_eq!(explain_binary("package(crate_a) & test(foo)"), None);
+}
+
+#[test]
+fn test_expr_check_no_group() {
+    let graph = load_graph();
+    let filtersets = NamedFiltersets::new(
+        [
+            ("serial".to_owned(), "group(serial-db)".to_owned()),
+            ("slow".to_owned(), "test(slow)".to_owned()),
+        ],
+        &graph,
+    )
+    .unwrap();
+
+    let expr = FilteringExpr::parse_with_filtersets(
+        "set(slow) | ignored()".to_owned(),
+        &graph,
+        &filtersets,
+    )
+    .unwrap();
+    expr.check_no_group(&filtersets).unwrap();
+
+    let expr = FilteringExpr::parse_with_filtersets(
+        "group(a) | set(slow) | set(serial)".to_owned(),
+        &graph,
+        &filtersets,
+    )
+    .unwrap();
+    let errors = expr.check_no_group(&filtersets).unwrap_err();
+    assert_eq!(
+        errors.errors,
+        vec![
+            ParseSingleError::GroupNotAllowed((6, 1).into()),
+            ParseSingleError::GroupNotAllowed((27, 6).into()),
+        ]
+    );
+}
+
 #[test]
 fn test_expr_kind() {
     let graph = load_graph();
@@ -314,17 +677,23 @@ fn test_expr_kind() {
     assert!(expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
     assert!(!expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "test", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_parse"
+        test_name: "test_parse",
+        ignored: false,
+        test_group: None,
     }));
     assert!(!expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "lib2", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
 }
 
@@ -337,17 +706,23 @@ fn test_expr_binary() {
     assert!(expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
     assert!(!expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "test", "my-binary2", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_parse"
+        test_name: "test_parse",
+        ignored: false,
+        test_group: None,
     }));
     assert!(expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "lib2", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
 }
 
@@ -360,12 +735,16 @@ fn test_expr_platform() {
     assert!(expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Host)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
     assert!(!expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
 
     let expr = parse("platform(target)", &graph);
@@ -374,12 +753,16 @@ fn test_expr_platform() {
     assert!(expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
     assert!(!expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Host)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
 }
 
@@ -392,12 +775,16 @@ fn test_expr_kind_partial() {
     assert!(expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "test", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_something"
+        test_name: "test_something",
+        ignored: false,
+        test_group: None,
     }));
     assert!(!expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_parse"
+        test_name: "test_parse",
+        ignored: false,
+        test_group: None,
     }));
 }
 
@@ -412,17 +799,23 @@ fn test_expr_test() {
     assert!(expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_parse"
+        test_name: "test_parse",
+        ignored: false,
+        test_group: None,
     }));
     assert!(expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_b, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_parse"
+        test_name: "test_parse",
+        ignored: false,
+        test_group: None,
     }));
     assert!(!expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_run"
+        test_name: "test_run",
+        ignored: false,
+        test_group: None,
     }));
 }
 
@@ -435,12 +828,16 @@ fn test_expr_test_not() {
     assert!(!expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_parse"
+        test_name: "test_parse",
+        ignored: false,
+        test_group: None,
     }));
     assert!(expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_run"
+        test_name: "test_run",
+        ignored: false,
+        test_group: None,
     }));
 }
 
@@ -455,17 +852,23 @@ fn test_expr_test_union(input: &str) {
     assert!(expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_parse"
+        test_name: "test_parse",
+        ignored: false,
+        test_group: None,
     }));
     assert!(expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_run"
+        test_name: "test_run",
+        ignored: false,
+        test_group: None,
     }));
     assert!(!expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_build"
+        test_name: "test_build",
+        ignored: false,
+        test_group: None,
     }));
 }
 
@@ -479,17 +882,23 @@ fn test_expr_test_difference(input: &str) {
     assert!(expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_parse"
+        test_name: "test_parse",
+        ignored: false,
+        test_group: None,
     }));
     assert!(expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_parse_set"
+        test_name: "test_parse_set",
+        ignored: false,
+        test_group: None,
     }));
     assert!(!expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_parse_expr"
+        test_name: "test_parse_expr",
+        ignored: false,
+        test_group: None,
     }));
 }
 
@@ -503,17 +912,23 @@ fn test_expr_test_intersect(input: &str) {
     assert!(!expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_parse"
+        test_name: "test_parse",
+        ignored: false,
+        test_group: None,
     }));
     assert!(!expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_expr"
+        test_name: "test_expr",
+        ignored: false,
+        test_group: None,
     }));
     assert!(expr.matches_test(&TestQuery {
         binary_query: binary_query(&graph, &pid_a, "lib", "my-binary", BuildPlatform::Target)
             .to_query(),
-        test_name: "test_parse_expr"
+        test_name: "test_parse_expr",
+        ignored: false,
+        test_group: None,
     }));
 }
 
@@ -7,6 +7,7 @@
 //! Implemented so far:
 //! * ✅ Listing tests with [`TestListSummary`]
 //! * ✅ Semantic exit codes with [`NextestExitCode`]
+//! * ✅ Streaming run events with [`TestEventSummary`]
 //!
 //! # Examples
 //!
@@ -41,8 +42,10 @@
 
 mod errors;
 mod exit_codes;
+mod run_events;
 mod test_list;
 
 pub use errors::*;
 pub use exit_codes::*;
+pub use run_events::*;
 pub use test_list::*;
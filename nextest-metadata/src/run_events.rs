@@ -0,0 +1,681 @@
+// Copyright (c) The nextest Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::{MismatchReason, RustBinaryId};
+use serde::{Deserialize, Serialize};
+
+/// The current version of the run event format.
+///
+/// This is bumped whenever a backwards-incompatible change is made to [`TestEventSummary`] or any
+/// of the types within it. Adding new fields or event kinds is not considered a breaking change.
+pub const RUN_EVENTS_FORMAT_VERSION: u32 = 1;
+
+/// A single event produced by `cargo nextest run --message-format nextest-json`.
+///
+/// Events are written to standard output as newline-delimited JSON, one event per line.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TestEventSummary {
+    /// The version of the format this event was produced with.
+    ///
+    /// Currently, this is always [`RUN_EVENTS_FORMAT_VERSION`].
+    pub format_version: u32,
+
+    /// The amount of time elapsed since the start of the test run, in seconds.
+    pub elapsed_secs: f64,
+
+    /// The kind of event this is.
+    pub kind: TestEventKindSummary,
+}
+
+impl TestEventSummary {
+    /// Parse a single line of output from `cargo nextest run --message-format nextest-json`.
+    pub fn parse_json(json: impl AsRef<str>) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json.as_ref())
+    }
+}
+
+/// The kind of a [`TestEventSummary`].
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", tag = "type")]
+#[non_exhaustive]
+pub enum TestEventKindSummary {
+    /// The test run started.
+    #[serde(rename_all = "kebab-case")]
+    RunStarted {
+        /// The unique ID for this run.
+        run_id: String,
+
+        /// The number of tests that will be run.
+        test_count: usize,
+    },
+
+    /// A setup script started.
+    #[serde(rename_all = "kebab-case")]
+    SetupScriptStarted {
+        /// The setup script index.
+        index: usize,
+
+        /// The total number of setup scripts.
+        total: usize,
+
+        /// The script ID.
+        script_id: String,
+
+        /// Whether this is the script's setup command or its teardown command.
+        #[serde(default)]
+        phase: ScriptPhaseSummary,
+
+        /// The command to run.
+        command: String,
+
+        /// The arguments to the command.
+        args: Vec<String>,
+
+        /// True if some output from the setup script is being passed through.
+        no_capture: bool,
+    },
+
+    /// A setup script was slow.
+    #[serde(rename_all = "kebab-case")]
+    SetupScriptSlow {
+        /// The script ID.
+        script_id: String,
+
+        /// Whether this is the script's setup command or its teardown command.
+        #[serde(default)]
+        phase: ScriptPhaseSummary,
+
+        /// The command to run.
+        command: String,
+
+        /// The arguments to the command.
+        args: Vec<String>,
+
+        /// The amount of time elapsed since the start of execution, in seconds.
+        elapsed_secs: f64,
+
+        /// True if the script has hit its timeout and is about to be terminated.
+        will_terminate: bool,
+    },
+
+    /// A setup script completed execution.
+    #[serde(rename_all = "kebab-case")]
+    SetupScriptFinished {
+        /// The setup script index.
+        index: usize,
+
+        /// The total number of setup scripts.
+        total: usize,
+
+        /// The script ID.
+        script_id: String,
+
+        /// Whether this is the script's setup command or its teardown command.
+        #[serde(default)]
+        phase: ScriptPhaseSummary,
+
+        /// The command to run.
+        command: String,
+
+        /// The arguments to the command.
+        args: Vec<String>,
+
+        /// True if some output from the setup script was passed through.
+        no_capture: bool,
+
+        /// The execution status of the setup script.
+        run_status: SetupScriptExecuteStatusSummary,
+    },
+
+    /// One of the commands of a wrapper script was run around a test.
+    #[serde(rename_all = "kebab-case")]
+    WrapperScriptFinished {
+        /// The test the script was run around.
+        test: TestInstanceSummary,
+
+        /// The script ID.
+        script_id: String,
+
+        /// Whether the script's setup command was run before the test, or its teardown command
+        /// after it.
+        phase: ScriptPhaseSummary,
+
+        /// The command that was run.
+        command: String,
+
+        /// The arguments to the command.
+        args: Vec<String>,
+
+        /// The execution status of the command.
+        run_status: SetupScriptExecuteStatusSummary,
+    },
+
+    /// A test started running.
+    #[serde(rename_all = "kebab-case")]
+    TestStarted {
+        /// The test that was started.
+        test: TestInstanceSummary,
+
+        /// Current run statistics so far.
+        current_stats: RunStatsSummary,
+
+        /// The number of tests currently running, including this one.
+        running: usize,
+
+        /// The reason the run is being canceled, if it is.
+        cancel_state: Option<CancelReasonSummary>,
+    },
+
+    /// A test was slower than a configured soft timeout.
+    #[serde(rename_all = "kebab-case")]
+    TestSlow {
+        /// The test that was slow.
+        test: TestInstanceSummary,
+
+        /// Retry data.
+        retry_data: RetryDataSummary,
+
+        /// The amount of time that has elapsed since the beginning of the test, in seconds.
+        elapsed_secs: f64,
+
+        /// True if the test has hit its timeout and is about to be terminated.
+        will_terminate: bool,
+    },
+
+    /// A test wrote some output.
+    ///
+    /// This event is only produced if output is streamed while tests run.
+    #[serde(rename_all = "kebab-case")]
+    TestOutputChunk {
+        /// The test that wrote the output.
+        test: TestInstanceSummary,
+
+        /// Retry data.
+        retry_data: RetryDataSummary,
+
+        /// The stream the output was written to.
+        stream: TestOutputStreamSummary,
+
+        /// The output, converted to a string lossily. Chunks aren't split at line boundaries, but
+        /// characters are never split across chunks.
+        data: String,
+    },
+
+    /// A test attempt failed and will be retried in the future.
+    #[serde(rename_all = "kebab-case")]
+    TestAttemptFailedWillRetry {
+        /// The test that is being retried.
+        test: TestInstanceSummary,
+
+        /// The status of this attempt to run the test. Will never be success.
+        run_status: ExecuteStatusSummary,
+
+        /// The delay before the next attempt to run the test, in seconds.
+        delay_before_next_attempt_secs: f64,
+    },
+
+    /// A retry has started.
+    #[serde(rename_all = "kebab-case")]
+    TestRetryStarted {
+        /// The test that is being retried.
+        test: TestInstanceSummary,
+
+        /// Data related to retries.
+        retry_data: RetryDataSummary,
+    },
+
+    /// A test finished running.
+    #[serde(rename_all = "kebab-case")]
+    TestFinished {
+        /// The test that finished running.
+        test: TestInstanceSummary,
+
+        /// Whether this test is quarantined. Failures in quarantined tests don't cause the run to
+        /// fail.
+        quarantined: bool,
+
+        /// Information about all the attempts made to run this test, in order.
+        run_statuses: Vec<ExecuteStatusSummary>,
+
+        /// Current statistics for number of tests so far.
+        current_stats: RunStatsSummary,
+
+        /// The number of tests that are currently running, excluding this one.
+        running: usize,
+
+        /// The reason the run is being canceled, if it is.
+        cancel_state: Option<CancelReasonSummary>,
+    },
+
+    /// A test was skipped.
+    #[serde(rename_all = "kebab-case")]
+    TestSkipped {
+        /// The test that was skipped.
+        test: TestInstanceSummary,
+
+        /// The reason this test was skipped.
+        reason: MismatchReason,
+    },
+
+    /// A cancellation notice was received.
+    #[serde(rename_all = "kebab-case")]
+    RunBeginCancel {
+        /// The number of setup scripts still running.
+        setup_scripts_running: usize,
+
+        /// The number of tests still running.
+        running: usize,
+
+        /// The reason this run was canceled.
+        reason: CancelReasonSummary,
+    },
+
+    /// The run was paused.
+    #[serde(rename_all = "kebab-case")]
+    RunPaused {
+        /// The number of setup scripts running.
+        setup_scripts_running: usize,
+
+        /// The number of tests currently running.
+        running: usize,
+    },
+
+    /// The run was continued after being paused.
+    #[serde(rename_all = "kebab-case")]
+    RunContinued {
+        /// The number of setup scripts that will be started up again.
+        setup_scripts_running: usize,
+
+        /// The number of tests that will be started up again.
+        running: usize,
+    },
+
+    /// The test run finished.
+    #[serde(rename_all = "kebab-case")]
+    RunFinished {
+        /// The unique ID for this run.
+        run_id: String,
+
+        /// The time at which the run was started, in RFC 3339 format.
+        start_time: String,
+
+        /// The amount of time it took for the tests to run, in seconds.
+        elapsed_secs: f64,
+
+        /// Statistics for the run.
+        run_stats: RunStatsSummary,
+    },
+}
+
+/// Identifies a single test within a run.
+///
+/// Part of a [`TestEventSummary`].
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TestInstanceSummary {
+    /// The binary the test is in.
+    pub binary_id: RustBinaryId,
+
+    /// The name of the test.
+    pub name: String,
+}
+
+/// Information about retries for a test attempt.
+///
+/// Part of a [`TestEventSummary`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RetryDataSummary {
+    /// The current attempt, starting from 1.
+    pub attempt: usize,
+
+    /// The total number of attempts that will be made.
+    pub total_attempts: usize,
+}
+
+/// The status of a single attempt to run a test.
+///
+/// Part of a [`TestEventSummary`].
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ExecuteStatusSummary {
+    /// Retry-related data for this attempt.
+    pub retry_data: RetryDataSummary,
+
+    /// The result of this attempt.
+    pub result: ExecutionResultSummary,
+
+    /// The time at which this attempt started, in RFC 3339 format.
+    pub start_time: String,
+
+    /// The time it took for this attempt to run, in seconds.
+    pub time_taken_secs: f64,
+
+    /// The resources used by the test process, if known.
+    ///
+    /// This is only collected on Linux, and is `None` on other platforms.
+    #[serde(default)]
+    pub resource_usage: Option<ResourceUsageSummary>,
+
+    /// Whether this attempt was marked slow.
+    pub is_slow: bool,
+
+    /// The delay before this attempt was started, in seconds.
+    pub delay_before_start_secs: f64,
+
+    /// Standard output for this attempt, lossily converted to UTF-8.
+    pub stdout: String,
+
+    /// Standard error for this attempt, lossily converted to UTF-8.
+    pub stderr: String,
+}
+
+/// Resources used by a test process.
+///
+/// Part of an [`ExecuteStatusSummary`].
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ResourceUsageSummary {
+    /// The peak resident set size of the process, in bytes.
+    pub max_rss_bytes: u64,
+
+    /// The amount of CPU time spent in user mode, in seconds.
+    pub user_time_secs: f64,
+
+    /// The amount of CPU time spent in the kernel, in seconds.
+    pub system_time_secs: f64,
+}
+
+/// The status of a setup script that finished running.
+///
+/// Part of a [`TestEventSummary`].
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SetupScriptExecuteStatusSummary {
+    /// The result of the setup script.
+    pub result: ExecutionResultSummary,
+
+    /// The time at which the script started, in RFC 3339 format.
+    pub start_time: String,
+
+    /// The time it took for the script to run, in seconds.
+    pub time_taken_secs: f64,
+
+    /// Whether the script was marked slow.
+    pub is_slow: bool,
+
+    /// The number of environment variables set by the script.
+    pub env_count: usize,
+
+    /// Standard output for the script, lossily converted to UTF-8.
+    pub stdout: String,
+
+    /// Standard error for the script, lossily converted to UTF-8.
+    pub stderr: String,
+}
+
+/// The result of running a test or setup script.
+///
+/// Part of a [`TestEventSummary`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", tag = "status")]
+#[non_exhaustive]
+pub enum ExecutionResultSummary {
+    /// The test passed.
+    Pass,
+
+    /// The test passed but leaked handles.
+    Leak,
+
+    /// The test failed.
+    #[serde(rename_all = "kebab-case")]
+    Fail {
+        /// The abort status of the test, if any.
+        abort_status: Option<AbortStatusSummary>,
+
+        /// Whether the test leaked handles.
+        leaked: bool,
+    },
+
+    /// An error occurred while executing the test.
+    ExecFail,
+
+    /// The test was terminated due to a timeout.
+    Timeout,
+
+    /// The test failed because it exceeded one of its resource limits.
+    #[serde(rename_all = "kebab-case")]
+    ResourceExceeded {
+        /// The resource whose limit was exceeded.
+        resource: ResourceLimitKindSummary,
+    },
+}
+
+/// A standard stream that a test writes output to.
+///
+/// Part of a [`TestEventKindSummary::TestOutputChunk`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TestOutputStreamSummary {
+    /// Standard output.
+    Stdout,
+
+    /// Standard error.
+    Stderr,
+}
+
+/// Which command of a setup script is being run.
+///
+/// Part of the setup and wrapper script events in [`TestEventKindSummary`].
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScriptPhaseSummary {
+    /// The setup command, run before any tests (or before each test, for wrapper scripts).
+    #[default]
+    Setup,
+
+    /// The teardown command, run once tests have finished (or after each test, for wrapper
+    /// scripts).
+    Teardown,
+}
+
+/// A resource that a test can be limited in using.
+///
+/// Part of an [`ExecutionResultSummary`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+pub enum ResourceLimitKindSummary {
+    /// The CPU time limit.
+    CpuTime,
+}
+
+/// Information about a test that was aborted by the operating system.
+///
+/// Part of an [`ExecutionResultSummary`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", tag = "kind")]
+#[non_exhaustive]
+pub enum AbortStatusSummary {
+    /// The test was aborted by a Unix signal.
+    UnixSignal {
+        /// The signal number.
+        signal: i32,
+    },
+
+    /// The test was aborted with a Windows NTSTATUS code.
+    WindowsNtStatus {
+        /// The NTSTATUS code.
+        code: i32,
+    },
+}
+
+/// Statistics for a test run.
+///
+/// Part of a [`TestEventSummary`]. Counters missing from the input (e.g. ones added in newer
+/// versions of nextest) are deserialized as 0.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct RunStatsSummary {
+    /// The total number of tests that were expected to be run at the beginning.
+    pub initial_run_count: usize,
+
+    /// The total number of tests that were actually run.
+    pub finished_count: usize,
+
+    /// The total number of setup scripts that were expected to be run at the beginning.
+    pub setup_scripts_initial_count: usize,
+
+    /// The total number of setup scripts that were run.
+    pub setup_scripts_finished_count: usize,
+
+    /// The number of setup scripts that passed.
+    pub setup_scripts_passed: usize,
+
+    /// The number of setup scripts that failed.
+    pub setup_scripts_failed: usize,
+
+    /// The number of setup scripts that encountered an execution failure.
+    pub setup_scripts_exec_failed: usize,
+
+    /// The number of setup scripts that timed out.
+    pub setup_scripts_timed_out: usize,
+
+    /// The total number of teardown commands that were run. These aren't counted in the
+    /// `setup-scripts-*` fields.
+    pub teardown_scripts_finished_count: usize,
+
+    /// The number of teardown commands that passed.
+    pub teardown_scripts_passed: usize,
+
+    /// The number of teardown commands that failed.
+    pub teardown_scripts_failed: usize,
+
+    /// The number of teardown commands that encountered an execution failure.
+    pub teardown_scripts_exec_failed: usize,
+
+    /// The number of teardown commands that timed out.
+    pub teardown_scripts_timed_out: usize,
+
+    /// The number of tests that passed, including flaky and leaky tests.
+    pub passed: usize,
+
+    /// The number of slow tests that passed.
+    pub passed_slow: usize,
+
+    /// The number of tests that passed on retry.
+    pub flaky: usize,
+
+    /// The number of tests that failed.
+    pub failed: usize,
+
+    /// The number of failed tests that were slow.
+    pub failed_slow: usize,
+
+    /// The number of tests that timed out.
+    pub timed_out: usize,
+
+    /// The number of tests that passed but leaked handles.
+    pub leaky: usize,
+
+    /// The number of tests that encountered an execution failure.
+    pub exec_failed: usize,
+
+    /// The number of quarantined tests that failed, timed out or encountered an execution
+    /// failure. These tests aren't counted in `failed`, `timed_out` or `exec_failed`.
+    pub quarantined: usize,
+
+    /// The number of tests that were skipped.
+    pub skipped: usize,
+}
+
+/// The reason a test run is being canceled.
+///
+/// Part of a [`TestEventSummary`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+pub enum CancelReasonSummary {
+    /// A setup script failed.
+    SetupScriptFailure,
+
+    /// A test failed and fail-fast is enabled.
+    TestFailure,
+
+    /// An error occurred while reporting results.
+    ReportError,
+
+    /// Cancellation was requested by the caller, for example by `cargo nextest watch` after
+    /// source files changed.
+    Requested,
+
+    /// A termination signal was received.
+    Signal,
+
+    /// An interrupt was received.
+    Interrupt,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_roundtrip() {
+        let event = TestEventSummary {
+            format_version: RUN_EVENTS_FORMAT_VERSION,
+            elapsed_secs: 1.5,
+            kind: TestEventKindSummary::TestFinished {
+                test: TestInstanceSummary {
+                    binary_id: RustBinaryId::new("my-package::my-test"),
+                    name: "tests::foo".to_owned(),
+                },
+                quarantined: false,
+                run_statuses: vec![ExecuteStatusSummary {
+                    retry_data: RetryDataSummary {
+                        attempt: 1,
+                        total_attempts: 1,
+                    },
+                    result: ExecutionResultSummary::Fail {
+                        abort_status: Some(AbortStatusSummary::UnixSignal { signal: 6 }),
+                        leaked: false,
+                    },
+                    start_time: "2023-12-01T00:00:00+00:00".to_owned(),
+                    time_taken_secs: 0.25,
+                    resource_usage: Some(ResourceUsageSummary {
+                        max_rss_bytes: 4 << 20,
+                        user_time_secs: 0.125,
+                        system_time_secs: 0.0625,
+                    }),
+                    is_slow: false,
+                    delay_before_start_secs: 0.0,
+                    stdout: "out".to_owned(),
+                    stderr: "err".to_owned(),
+                }],
+                current_stats: RunStatsSummary {
+                    initial_run_count: 1,
+                    finished_count: 1,
+                    failed: 1,
+                    ..RunStatsSummary::default()
+                },
+                running: 0,
+                cancel_state: None,
+            },
+        };
+
+        let json = serde_json::to_string(&event).expect("event serialized");
+        assert!(
+            !json.contains('\n'),
+            "serialized event is a single line: {json}"
+        );
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["kind"]["type"], "test-finished");
+        let result = &value["kind"]["run-statuses"][0]["result"];
+        assert_eq!(result["status"], "fail");
+        assert_eq!(result["abort-status"]["kind"], "unix-signal");
+
+        let parsed = TestEventSummary::parse_json(&json).expect("event deserialized");
+        assert_eq!(parsed, event, "event round-tripped");
+    }
+}
@@ -12,6 +12,7 @@ use std::{
     fmt::{self, Write as _},
     path::PathBuf,
     process::Command,
+    time::Duration,
 };
 use target_spec::summaries::PlatformSummary;
 
@@ -135,6 +136,69 @@ pub struct TestListSummary {
     /// A map of Rust test suites to the test binaries within them, keyed by a unique identifier
     /// for each test suite.
     pub rust_suites: BTreeMap<RustBinaryId, RustTestSuiteSummary>,
+
+    /// The expected duration of each test, based on the durations recorded for it in previous
+    /// runs, keyed by binary ID and then by test name.
+    ///
+    /// Tests for which no durations have been recorded are not present. Serialized as a number of
+    /// seconds.
+    ///
+    /// Added in cargo-nextest 0.9.65.
+    #[serde(
+        rename = "expected-durations-secs",
+        default,
+        skip_serializing_if = "BTreeMap::is_empty",
+        with = "duration_secs_map"
+    )]
+    pub expected_durations: BTreeMap<RustBinaryId, BTreeMap<String, Duration>>,
+}
+
+mod duration_secs_map {
+    use crate::RustBinaryId;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::{collections::BTreeMap, time::Duration};
+
+    pub(super) fn serialize<S>(
+        durations: &BTreeMap<RustBinaryId, BTreeMap<String, Duration>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let secs: BTreeMap<_, BTreeMap<_, _>> = durations
+            .iter()
+            .map(|(binary_id, durations)| {
+                let durations = durations
+                    .iter()
+                    .map(|(test_name, duration)| (test_name, duration.as_secs_f64()))
+                    .collect();
+                (binary_id, durations)
+            })
+            .collect();
+        secs.serialize(serializer)
+    }
+
+    pub(super) fn deserialize<'de, D>(
+        deserializer: D,
+    ) -> Result<BTreeMap<RustBinaryId, BTreeMap<String, Duration>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let secs = BTreeMap::<RustBinaryId, BTreeMap<String, f64>>::deserialize(deserializer)?;
+        secs.into_iter()
+            .map(|(binary_id, durations)| {
+                let durations = durations
+                    .into_iter()
+                    .map(|(test_name, secs)| {
+                        Duration::try_from_secs_f64(secs)
+                            .map(|duration| (test_name, duration))
+                            .map_err(serde::de::Error::custom)
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok((binary_id, durations))
+            })
+            .collect()
+    }
 }
 
 impl TestListSummary {
@@ -144,6 +208,7 @@ impl TestListSummary {
             rust_build_meta,
             test_count: 0,
             rust_suites: BTreeMap::new(),
+            expected_durations: BTreeMap::new(),
         }
     }
     /// Parse JSON output from `cargo nextest list --message-format json`.
@@ -672,6 +737,9 @@ pub enum MismatchReason {
 
     /// This test is in a different partition.
     Partition,
+
+    /// This test did not fail in the previous run, and `--rerun-failed` was specified.
+    RerunFailed,
 }
 
 impl fmt::Display for MismatchReason {
@@ -683,10 +751,114 @@ impl fmt::Display for MismatchReason {
                 write!(f, "does not match the provided expression filters")
             }
             MismatchReason::Partition => write!(f, "is in a different partition"),
+            MismatchReason::RerunFailed => write!(f, "did not fail in the previous run"),
+        }
+    }
+}
+
+/// Serializable explanation of why each test in a test list matched the provided filters or not.
+///
+/// This is the output of `cargo nextest show filter --message-format json`.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+pub struct FilterExplanationSummary {
+    /// The filter expressions that were explained, in the order they were provided.
+    ///
+    /// Each test has one [`FilterExpressionExplanationSummary`] per expression, in this order.
+    pub expressions: Vec<String>,
+
+    /// The number of tests that match the provided filters.
+    pub match_count: usize,
+
+    /// Explanations for each test suite, keyed by binary ID.
+    pub rust_suites: BTreeMap<RustBinaryId, RustTestSuiteFilterExplanationSummary>,
+}
+
+impl FilterExplanationSummary {
+    /// Creates a new `FilterExplanationSummary` for the given expressions, with no test suites.
+    pub fn new(expressions: Vec<String>) -> Self {
+        Self {
+            expressions,
+            match_count: 0,
+            rust_suites: BTreeMap::new(),
         }
     }
 }
 
+/// Serializable explanation of how filters apply to a suite of tests within a Rust test binary.
+///
+/// Part of a [`FilterExplanationSummary`].
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RustTestSuiteFilterExplanationSummary {
+    /// Status of this test suite.
+    ///
+    /// If this is [`SKIPPED`](RustTestSuiteStatusSummary::SKIPPED), the binary didn't match the
+    /// filter expressions and `test_cases` is empty.
+    pub status: RustTestSuiteStatusSummary,
+
+    /// If the binary was skipped, explanations for why it didn't match each filter expression, in
+    /// the same order as [`FilterExplanationSummary::expressions`]. These are in terms of the
+    /// binary-level predicates such as `package()` and `kind()`.
+    ///
+    /// Empty if the binary wasn't skipped, or if it wasn't skipped because of the filter
+    /// expressions.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub expressions: Vec<FilterExpressionExplanationSummary>,
+
+    /// Explanations for each test case within this test suite.
+    #[serde(rename = "testcases")]
+    pub test_cases: BTreeMap<String, RustTestCaseFilterExplanationSummary>,
+}
+
+/// Serializable explanation of how filters apply to an individual test case.
+///
+/// Part of a [`RustTestSuiteFilterExplanationSummary`].
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RustTestCaseFilterExplanationSummary {
+    /// Whether the test matches all the provided filters.
+    ///
+    /// This is the same as [`RustTestCaseSummary::filter_match`].
+    pub filter_match: FilterMatch,
+
+    /// Explanations for each filter expression, in the same order as
+    /// [`FilterExplanationSummary::expressions`].
+    pub expressions: Vec<FilterExpressionExplanationSummary>,
+}
+
+/// Serializable explanation of how a single filter expression applies to a test case or binary.
+///
+/// Part of a [`RustTestCaseFilterExplanationSummary`] or a
+/// [`RustTestSuiteFilterExplanationSummary`].
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct FilterExpressionExplanationSummary {
+    /// True if the test or binary matches this expression.
+    pub matches: bool,
+
+    /// The predicates within the expression that decided the result, in the order they appear.
+    pub deciding: Vec<DecidingPredicateSummary>,
+}
+
+/// Serializable information about a predicate that decided whether a test matched a filter
+/// expression.
+///
+/// Part of a [`FilterExpressionExplanationSummary`].
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct DecidingPredicateSummary {
+    /// The predicate, for example `test(foo)`.
+    pub predicate: String,
+
+    /// True if the predicate itself matched the test or binary.
+    ///
+    /// This can differ from [`FilterExpressionExplanationSummary::matches`], for example if the
+    /// predicate is negated with `not`.
+    pub matches: bool,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -730,6 +902,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_expected_durations_serialization() {
+        let mut summary = TestListSummary::new(RustBuildMetaSummary {
+            target_directory: "/foo".into(),
+            base_output_directories: BTreeSet::new(),
+            non_test_binaries: BTreeMap::new(),
+            build_script_out_dirs: BTreeMap::new(),
+            linked_paths: BTreeSet::new(),
+            target_platform: None,
+            target_platforms: vec![],
+        });
+
+        // Empty expected durations are not serialized.
+        let json = serde_json::to_value(&summary).expect("summary serialized");
+        assert!(json.get("expected-durations-secs").is_none());
+
+        summary.expected_durations.insert(
+            RustBinaryId::new("foo"),
+            BTreeMap::from([("test_bar".to_owned(), Duration::from_millis(1500))]),
+        );
+        let json = serde_json::to_value(&summary).expect("summary serialized");
+        assert_eq!(
+            json["expected-durations-secs"],
+            serde_json::json!({ "foo": { "test_bar": 1.5 } }),
+        );
+
+        let deserialized: TestListSummary =
+            serde_json::from_value(json).expect("summary deserialized");
+        assert_eq!(deserialized, summary, "summary round-tripped");
+    }
+
     #[test]
     fn test_binary_id_ord() {
         let empty = RustBinaryId::new("");
@@ -0,0 +1,255 @@
+// Copyright (c) The nextest Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
+use globset::{Glob, GlobBuilder, GlobMatcher};
+use serde::Deserialize;
+use std::fmt;
+
+/// Glob patterns for files to collect when a test fails.
+///
+/// Returned by [`TestSettings::collect_artifacts`](super::TestSettings::collect_artifacts).
+/// Relative patterns are resolved against the test's working directory.
+#[derive(Clone, Debug, Default)]
+pub struct ArtifactGlobs {
+    globs: Vec<ArtifactGlob>,
+}
+
+impl ArtifactGlobs {
+    /// Returns true if no patterns are set.
+    pub fn is_empty(&self) -> bool {
+        self.globs.is_empty()
+    }
+
+    /// Returns the patterns as they were specified in the configuration.
+    pub fn patterns(&self) -> impl Iterator<Item = &str> + '_ {
+        self.globs.iter().map(|glob| glob.matcher.glob().glob())
+    }
+
+    /// Finds files that match these patterns.
+    ///
+    /// Returns pairs of the path to each file, and the path it should be copied to relative to the
+    /// artifact directory.
+    pub(crate) fn find(&self, cwd: &Utf8Path) -> Vec<(Utf8PathBuf, Utf8PathBuf)> {
+        let mut found: Vec<(Utf8PathBuf, Utf8PathBuf)> = Vec::new();
+        for glob in &self.globs {
+            walk(&cwd.join(&glob.base), &mut |path| {
+                // Relative patterns are matched against the path relative to the working
+                // directory, and absolute patterns against the whole path.
+                let (to_match, relative) = if glob.base.is_absolute() {
+                    let Ok(mut relative) = path.strip_prefix(&glob.base) else {
+                        return;
+                    };
+                    if relative.as_str().is_empty() {
+                        // The pattern is a literal path to this file.
+                        let Some(file_name) = path.file_name() else {
+                            return;
+                        };
+                        relative = Utf8Path::new(file_name);
+                    }
+                    (path, relative)
+                } else {
+                    let Ok(relative) = path.strip_prefix(cwd) else {
+                        return;
+                    };
+                    (relative, relative)
+                };
+                if glob.matcher.is_match(to_match)
+                    && !found.iter().any(|(source, _)| source == path)
+                {
+                    found.push((path.to_owned(), relative.to_owned()));
+                }
+            });
+        }
+        found
+    }
+}
+
+impl<'de> Deserialize<'de> for ArtifactGlobs {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let patterns = Vec::<String>::deserialize(deserializer)?;
+        let globs = patterns
+            .iter()
+            .map(|pattern| ArtifactGlob::new(pattern).map_err(serde::de::Error::custom))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { globs })
+    }
+}
+
+#[derive(Clone, Debug)]
+struct ArtifactGlob {
+    // The longest leading part of the pattern without any glob metacharacters. Only this directory
+    // is searched for matching files.
+    base: Utf8PathBuf,
+    matcher: GlobMatcher,
+}
+
+impl ArtifactGlob {
+    fn new(pattern: &str) -> Result<Self, ArtifactGlobError> {
+        // Files are copied to the same path relative to the artifact directory, so a `..` could
+        // write them outside of it.
+        if Utf8Path::new(pattern)
+            .components()
+            .any(|component| component == Utf8Component::ParentDir)
+        {
+            return Err(ArtifactGlobError {
+                pattern: pattern.to_owned(),
+                reason: "patterns can't contain `..`".to_owned(),
+            });
+        }
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|error| ArtifactGlobError {
+                pattern: pattern.to_owned(),
+                reason: error.kind().to_string(),
+            })?;
+        Ok(Self {
+            base: glob_base(&glob),
+            matcher: glob.compile_matcher(),
+        })
+    }
+}
+
+#[derive(Debug)]
+struct ArtifactGlobError {
+    pattern: String,
+    reason: String,
+}
+
+impl fmt::Display for ArtifactGlobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid artifact pattern `{}`: {}",
+            self.pattern, self.reason
+        )
+    }
+}
+
+fn glob_base(glob: &Glob) -> Utf8PathBuf {
+    let mut base = Utf8PathBuf::new();
+    for component in Utf8Path::new(glob.glob()).components() {
+        if let Utf8Component::Normal(s) = component {
+            if s.contains(['*', '?', '[', '{']) {
+                break;
+            }
+        }
+        base.push(component);
+    }
+    base
+}
+
+/// Calls `f` on every file under `path`, or on `path` itself if it's a file.
+///
+/// Symlinks to directories aren't followed, and Cargo target directories are skipped unless `path`
+/// is within one.
+fn walk(root: &Utf8Path, f: &mut impl FnMut(&Utf8Path)) {
+    let mut stack = vec![root.to_owned()];
+    while let Some(path) = stack.pop() {
+        let Ok(metadata) = path.symlink_metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            // Cargo marks its target directories with this file.
+            if path != root && path.join("CACHEDIR.TAG").exists() {
+                continue;
+            }
+            if let Ok(entries) = path.read_dir_utf8() {
+                stack.extend(entries.flatten().map(|entry| entry.into_path()));
+            }
+        } else {
+            f(&path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use camino_tempfile::tempdir;
+    use test_case::test_case;
+
+    #[test_case("*.log", ""; "no base")]
+    #[test_case("logs/**/*.log", "logs"; "relative base")]
+    #[test_case("/var/log/app-*.log", "/var/log"; "absolute base")]
+    #[test_case("core", "core"; "literal")]
+    fn test_glob_base(pattern: &str, expected: &str) {
+        let glob = ArtifactGlob::new(pattern).expect("valid pattern");
+        assert_eq!(glob.base, expected);
+    }
+
+    #[test]
+    fn test_find() {
+        let dir = tempdir().unwrap();
+        for file in [
+            "a.log",
+            "b.txt",
+            "logs/c.log",
+            "logs/nested/d.log",
+            "target/CACHEDIR.TAG",
+            "target/e.log",
+        ] {
+            let path = dir.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+
+        let globs = ArtifactGlobs {
+            globs: vec![
+                ArtifactGlob::new("*.log").unwrap(),
+                ArtifactGlob::new("logs/**/*.log").unwrap(),
+                ArtifactGlob::new("**/*.log").unwrap(),
+            ],
+        };
+        let mut found: Vec<_> = globs
+            .find(dir.path())
+            .into_iter()
+            .map(|(source, dest)| {
+                assert_eq!(source, dir.path().join(&dest));
+                dest
+            })
+            .collect();
+        found.sort();
+        assert_eq!(found, ["a.log", "logs/c.log", "logs/nested/d.log"]);
+    }
+
+    #[test]
+    fn test_find_absolute_literal() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("logs/app.log");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "").unwrap();
+
+        let globs = ArtifactGlobs {
+            globs: vec![ArtifactGlob::new(path.as_str()).unwrap()],
+        };
+        let cwd = tempdir().unwrap();
+        assert_eq!(
+            globs.find(cwd.path()),
+            [(path, Utf8PathBuf::from("app.log"))]
+        );
+    }
+
+    #[test_case("logs/[a", "invalid artifact pattern `logs/[a`"; "invalid glob")]
+    #[test_case(
+        "../logs/*.log",
+        "invalid artifact pattern `../logs/*.log`: patterns can't contain `..`";
+        "parent dir"
+    )]
+    #[test_case(
+        "/var/log/../app.log",
+        "invalid artifact pattern `/var/log/../app.log`: patterns can't contain `..`";
+        "absolute parent dir"
+    )]
+    fn test_invalid_pattern(pattern: &str, expected: &str) {
+        let error = ArtifactGlob::new(pattern).unwrap_err();
+        assert!(
+            error.to_string().starts_with(expected),
+            "unexpected error: {error}"
+        );
+    }
+}
@@ -2,15 +2,16 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 use super::{
-    CompiledByProfile, CompiledData, ConfigExperimental, CustomTestGroup, DeserializedOverride,
-    DeserializedProfileScriptConfig, NextestVersionDeserialize, RetryPolicy, ScriptConfig,
-    ScriptId, SettingSource, SetupScripts, SlowTimeout, TestGroup, TestGroupConfig, TestSettings,
-    TestThreads, ThreadsRequired, ToolConfigFile,
+    check_script_dependencies, CompiledByProfile, CompiledData, CompiledProfileScripts,
+    ConfigExperimental, CustomTestGroup, DeserializedOverride, DeserializedProfileScriptConfig,
+    NextestVersionDeserialize, RetryPolicy, ScriptConfig, ScriptId, SettingSource, SetupScripts,
+    SlowTimeout, TestGroup, TestGroupAssignments, TestGroupConfig, TestSettings, TestThreads,
+    ThreadsRequired, ToolConfigFile,
 };
 use crate::{
     errors::{
-        provided_by_tool, ConfigParseError, ConfigParseErrorKind, ProfileNotFound,
-        UnknownConfigScriptError, UnknownTestGroupError,
+        provided_by_tool, ConfigParseError, ConfigParseErrorKind, ProfileInheritanceError,
+        ProfileNotFound, UnknownConfigScriptError, UnknownTestGroupError,
     },
     list::TestList,
     platform::BuildPlatforms,
@@ -20,11 +21,11 @@ use camino::{Utf8Path, Utf8PathBuf};
 use config::{builder::DefaultState, Config, ConfigBuilder, File, FileFormat, FileSourceFile};
 use guppy::graph::PackageGraph;
 use indexmap::IndexMap;
-use nextest_filtering::TestQuery;
+use nextest_filtering::{NamedFiltersets, TestQuery};
 use once_cell::sync::Lazy;
 use serde::Deserialize;
 use std::{
-    collections::{BTreeMap, BTreeSet, HashMap},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     time::Duration,
 };
 
@@ -55,6 +56,7 @@ pub struct NextestConfig {
     workspace_root: Utf8PathBuf,
     inner: NextestConfigImpl,
     compiled: CompiledByProfile,
+    filtersets: NamedFiltersets,
 }
 
 impl NextestConfig {
@@ -141,7 +143,7 @@ impl NextestConfig {
     {
         let workspace_root = workspace_root.into();
         let tool_config_files_rev = tool_config_files.into_iter().rev();
-        let (inner, compiled) = Self::read_from_sources(
+        let (inner, compiled, filtersets) = Self::read_from_sources(
             graph,
             &workspace_root,
             config_file,
@@ -153,6 +155,7 @@ impl NextestConfig {
             workspace_root,
             inner,
             compiled,
+            filtersets,
         })
     }
 
@@ -184,8 +187,9 @@ impl NextestConfig {
         Self {
             workspace_root: workspace_root.into(),
             inner: deserialized.into_config_impl(),
-            // The default config does not (cannot) have overrides.
+            // The default config does not (cannot) have overrides or filtersets.
             compiled: CompiledByProfile::default(),
+            filtersets: NamedFiltersets::default(),
         }
     }
 
@@ -198,6 +202,12 @@ impl NextestConfig {
         self.make_profile(name.as_ref())
     }
 
+    /// Returns the named filtersets defined in the `[filters]` section, which expressions can
+    /// refer to through `set(name)`.
+    pub fn filtersets(&self) -> &NamedFiltersets {
+        &self.filtersets
+    }
+
     // ---
     // Helper methods
     // ---
@@ -209,7 +219,7 @@ impl NextestConfig {
         tool_config_files_rev: impl Iterator<Item = &'a ToolConfigFile>,
         experimental: &BTreeSet<ConfigExperimental>,
         unknown_callback: &mut impl FnMut(&Utf8Path, Option<&str>, &BTreeSet<String>),
-    ) -> Result<(NextestConfigImpl, CompiledByProfile), ConfigParseError> {
+    ) -> Result<(NextestConfigImpl, CompiledByProfile, NamedFiltersets), ConfigParseError> {
         // First, get the default config.
         let mut composite_builder = Self::make_default_config();
 
@@ -219,6 +229,8 @@ impl NextestConfig {
 
         let mut known_groups = BTreeSet::new();
         let mut known_scripts = BTreeSet::new();
+        let mut known_filters = BTreeMap::new();
+        let mut filtersets = NamedFiltersets::default();
 
         // Next, merge in tool configs.
         for ToolConfigFile { config_file, tool } in tool_config_files_rev {
@@ -234,6 +246,8 @@ impl NextestConfig {
                 unknown_callback,
                 &mut known_groups,
                 &mut known_scripts,
+                &mut known_filters,
+                &mut filtersets,
             )?;
 
             // This is the final, composite builder used at the end.
@@ -261,6 +275,8 @@ impl NextestConfig {
             unknown_callback,
             &mut known_groups,
             &mut known_scripts,
+            &mut known_filters,
+            &mut filtersets,
         )?;
 
         composite_builder = composite_builder.add_source(source);
@@ -268,7 +284,7 @@ impl NextestConfig {
         // The unknown set is ignored here because any values in it have already been reported in
         // deserialize_individual_config.
         let (config, _unknown) = Self::build_and_deserialize_config(&composite_builder)
-            .map_err(|kind| ConfigParseError::new(config_file, None, kind))?;
+            .map_err(|kind| ConfigParseError::new(&config_file, None, kind))?;
 
         // Reverse all the compiled data at the end.
         compiled.default.reverse();
@@ -276,7 +292,28 @@ impl NextestConfig {
             data.reverse();
         }
 
-        Ok((config.into_config_impl(), compiled))
+        // Profiles can inherit from profiles defined in other config files, so inheritance is
+        // checked against the final config.
+        check_profile_inheritance(&config.profiles).map_err(|err| {
+            ConfigParseError::new(
+                &config_file,
+                None,
+                ConfigParseErrorKind::ProfileInheritance(err),
+            )
+        })?;
+
+        // Dependencies between scripts can cross config files, so they're checked against the
+        // final config.
+        let config = config.into_config_impl();
+        check_script_dependencies(&config.scripts).map_err(|err| {
+            ConfigParseError::new(
+                config_file,
+                None,
+                ConfigParseErrorKind::ScriptDependencies(err),
+            )
+        })?;
+
+        Ok((config, compiled, filtersets))
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -291,6 +328,8 @@ impl NextestConfig {
         unknown_callback: &mut impl FnMut(&Utf8Path, Option<&str>, &BTreeSet<String>),
         known_groups: &mut BTreeSet<CustomTestGroup>,
         known_scripts: &mut BTreeSet<ScriptId>,
+        known_filters: &mut BTreeMap<String, String>,
+        filtersets: &mut NamedFiltersets,
     ) -> Result<(), ConfigParseError> {
         // Try building default builder + this file to get good error attribution and handle
         // overrides additively.
@@ -388,8 +427,24 @@ impl NextestConfig {
             }
         }
 
+        // Filtersets defined by this file can refer to ones defined by earlier files, and replace
+        // them if they have the same name.
+        known_filters.extend(
+            this_config
+                .filters
+                .iter()
+                .map(|(name, filter)| (name.clone(), filter.clone())),
+        );
+        *filtersets = NamedFiltersets::new(known_filters.clone(), graph).map_err(|errors| {
+            ConfigParseError::new(
+                config_file,
+                tool,
+                ConfigParseErrorKind::FiltersetParseErrors(errors),
+            )
+        })?;
+
         // Compile the overrides for this file.
-        let this_compiled = CompiledByProfile::new(graph, &this_config)
+        let this_compiled = CompiledByProfile::new(graph, &this_config, filtersets)
             .map_err(|kind| ConfigParseError::new(config_file, tool, kind))?;
 
         // Check that all overrides specify known test groups.
@@ -506,25 +561,26 @@ impl NextestConfig {
         &self,
         name: &str,
     ) -> Result<NextestProfile<'_, PreBuildPlatform>, ProfileNotFound> {
-        let custom_profile = self.inner.get_profile(name)?;
+        let custom_profiles = self.inner.get_profile_chain(name)?;
 
         // The profile was found: construct the NextestProfile.
         let mut store_dir = self.workspace_root.join(&self.inner.store.dir);
         store_dir.push(name);
 
-        // Grab the compiled data as well.
-        let compiled_data = self
-            .compiled
-            .other
-            .get(name)
-            .cloned()
-            .unwrap_or_default()
+        // Grab the compiled data as well. Overrides and scripts for this profile take precedence
+        // over those for the profiles it inherits from, which in turn take precedence over those
+        // for the default profile.
+        let compiled_data = custom_profiles
+            .iter()
+            .fold(CompiledData::default(), |compiled_data, (name, _)| {
+                compiled_data.chain(self.compiled.other.get(*name).cloned().unwrap_or_default())
+            })
             .chain(self.compiled.default.clone());
-
         Ok(NextestProfile {
+            name: name.to_owned(),
             store_dir,
             default_profile: &self.inner.default_profile,
-            custom_profile,
+            custom_profiles,
             test_groups: &self.inner.test_groups,
             scripts: &self.inner.scripts,
             compiled_data,
@@ -556,7 +612,7 @@ impl NextestConfig {
 pub struct PreBuildPlatform {}
 
 /// The state of nextest profiles after build platforms have been applied.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct FinalConfig {
     // Evaluation result for host_spec on the host platform.
     pub(super) host_eval: bool,
@@ -573,9 +629,12 @@ pub struct FinalConfig {
 /// Returned by [`NextestConfig::profile`].
 #[derive(Clone, Debug)]
 pub struct NextestProfile<'cfg, State = FinalConfig> {
+    name: String,
     store_dir: Utf8PathBuf,
     default_profile: &'cfg DefaultProfileImpl,
-    custom_profile: Option<&'cfg CustomProfileImpl>,
+    // The profile followed by the profiles it inherits from, along with their names. The default
+    // profile is not included.
+    custom_profiles: Vec<(&'cfg str, &'cfg CustomProfileImpl)>,
     test_groups: &'cfg BTreeMap<CustomTestGroup, TestGroupConfig>,
     // This is ordered because the scripts are used in the order they're defined.
     scripts: &'cfg IndexMap<ScriptId, ScriptConfig>,
@@ -583,6 +642,11 @@ pub struct NextestProfile<'cfg, State = FinalConfig> {
 }
 
 impl<'cfg, State> NextestProfile<'cfg, State> {
+    /// Returns the name of the profile.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
     /// Returns the absolute profile-specific store directory.
     pub fn store_dir(&self) -> &Utf8Path {
         &self.store_dir
@@ -600,7 +664,7 @@ impl<'cfg, State> NextestProfile<'cfg, State> {
 
     #[allow(dead_code)]
     pub(super) fn custom_profile(&self) -> Option<&'cfg CustomProfileImpl> {
-        self.custom_profile
+        self.custom_profiles.first().map(|(_, profile)| *profile)
     }
 }
 
@@ -612,9 +676,10 @@ impl<'cfg> NextestProfile<'cfg, PreBuildPlatform> {
     pub fn apply_build_platforms(self, build_platforms: &BuildPlatforms) -> NextestProfile<'cfg> {
         let compiled_data = self.compiled_data.apply_build_platforms(build_platforms);
         NextestProfile {
+            name: self.name,
             store_dir: self.store_dir,
             default_profile: self.default_profile,
-            custom_profile: self.custom_profile,
+            custom_profiles: self.custom_profiles,
             scripts: self.scripts,
             test_groups: self.test_groups,
             compiled_data,
@@ -625,75 +690,142 @@ impl<'cfg> NextestProfile<'cfg, PreBuildPlatform> {
 impl<'cfg> NextestProfile<'cfg, FinalConfig> {
     /// Returns the retry count for this profile.
     pub fn retries(&self) -> RetryPolicy {
-        self.custom_profile
-            .and_then(|profile| profile.retries)
-            .unwrap_or(self.default_profile.retries)
+        self.retries_with_profile().0
+    }
+
+    pub(super) fn retries_with_profile(&self) -> (RetryPolicy, &str) {
+        self.setting_with_profile(|profile| profile.retries, self.default_profile.retries)
     }
 
     /// Returns the number of threads to run against for this profile.
     pub fn test_threads(&self) -> TestThreads {
-        self.custom_profile
-            .and_then(|profile| profile.test_threads)
+        self.custom_profiles
+            .iter()
+            .find_map(|(_, profile)| profile.test_threads)
             .unwrap_or(self.default_profile.test_threads)
     }
 
     /// Returns the number of threads required for each test.
     pub fn threads_required(&self) -> ThreadsRequired {
-        self.custom_profile
-            .and_then(|profile| profile.threads_required)
-            .unwrap_or(self.default_profile.threads_required)
+        self.threads_required_with_profile().0
+    }
+
+    pub(super) fn threads_required_with_profile(&self) -> (ThreadsRequired, &str) {
+        self.setting_with_profile(
+            |profile| profile.threads_required,
+            self.default_profile.threads_required,
+        )
     }
 
     /// Returns the time after which tests are treated as slow for this profile.
     pub fn slow_timeout(&self) -> SlowTimeout {
-        self.custom_profile
-            .and_then(|profile| profile.slow_timeout)
-            .unwrap_or(self.default_profile.slow_timeout)
+        self.slow_timeout_with_profile().0
+    }
+
+    pub(super) fn slow_timeout_with_profile(&self) -> (SlowTimeout, &str) {
+        self.setting_with_profile(
+            |profile| profile.slow_timeout,
+            self.default_profile.slow_timeout,
+        )
     }
 
     /// Returns the time after which a child process that hasn't closed its handles is marked as
     /// leaky.
     pub fn leak_timeout(&self) -> Duration {
-        self.custom_profile
-            .and_then(|profile| profile.leak_timeout)
-            .unwrap_or(self.default_profile.leak_timeout)
+        self.leak_timeout_with_profile().0
+    }
+
+    pub(super) fn leak_timeout_with_profile(&self) -> (Duration, &str) {
+        self.setting_with_profile(
+            |profile| profile.leak_timeout,
+            self.default_profile.leak_timeout,
+        )
     }
 
     /// Returns the test status level.
     pub fn status_level(&self) -> StatusLevel {
-        self.custom_profile
-            .and_then(|profile| profile.status_level)
+        self.custom_profiles
+            .iter()
+            .find_map(|(_, profile)| profile.status_level)
             .unwrap_or(self.default_profile.status_level)
     }
 
     /// Returns the test status level at the end of the run.
     pub fn final_status_level(&self) -> FinalStatusLevel {
-        self.custom_profile
-            .and_then(|profile| profile.final_status_level)
+        self.custom_profiles
+            .iter()
+            .find_map(|(_, profile)| profile.final_status_level)
             .unwrap_or(self.default_profile.final_status_level)
     }
 
     /// Returns the failure output config for this profile.
     pub fn failure_output(&self) -> TestOutputDisplay {
-        self.custom_profile
-            .and_then(|profile| profile.failure_output)
-            .unwrap_or(self.default_profile.failure_output)
+        self.failure_output_with_profile().0
+    }
+
+    pub(super) fn failure_output_with_profile(&self) -> (TestOutputDisplay, &str) {
+        self.setting_with_profile(
+            |profile| profile.failure_output,
+            self.default_profile.failure_output,
+        )
     }
 
     /// Returns the failure output config for this profile.
     pub fn success_output(&self) -> TestOutputDisplay {
-        self.custom_profile
-            .and_then(|profile| profile.success_output)
-            .unwrap_or(self.default_profile.success_output)
+        self.success_output_with_profile().0
+    }
+
+    pub(super) fn success_output_with_profile(&self) -> (TestOutputDisplay, &str) {
+        self.setting_with_profile(
+            |profile| profile.success_output,
+            self.default_profile.success_output,
+        )
     }
 
     /// Returns the fail-fast config for this profile.
     pub fn fail_fast(&self) -> bool {
-        self.custom_profile
-            .and_then(|profile| profile.fail_fast)
+        self.custom_profiles
+            .iter()
+            .find_map(|(_, profile)| profile.fail_fast)
             .unwrap_or(self.default_profile.fail_fast)
     }
 
+    /// Returns true if setup scripts should be started in parallel, rather than one at a time in
+    /// the order they're listed.
+    pub fn setup_scripts_parallel(&self) -> bool {
+        self.custom_profiles
+            .iter()
+            .find_map(|(_, profile)| profile.setup_scripts_parallel)
+            .unwrap_or(self.default_profile.setup_scripts_parallel)
+    }
+
+    pub(super) fn junit_store_success_output_with_profile(&self) -> (bool, &str) {
+        self.setting_with_profile(
+            |profile| profile.junit.store_success_output,
+            self.default_profile.junit.store_success_output,
+        )
+    }
+
+    pub(super) fn junit_store_failure_output_with_profile(&self) -> (bool, &str) {
+        self.setting_with_profile(
+            |profile| profile.junit.store_failure_output,
+            self.default_profile.junit.store_failure_output,
+        )
+    }
+
+    /// Returns a setting from the first custom profile in the inheritance chain that sets it, or
+    /// `default` otherwise, along with the name of the profile it's taken from.
+    fn setting_with_profile<T>(
+        &self,
+        custom: impl Fn(&'cfg CustomProfileImpl) -> Option<T>,
+        default: T,
+    ) -> (T, &str) {
+        self.custom_profiles
+            .iter()
+            .find_map(|(name, profile)| Some((custom(profile)?, *name)))
+            .unwrap_or((default, NextestConfig::DEFAULT_PROFILE))
+    }
+
     /// Returns the list of setup scripts.
     pub fn setup_scripts(&self, test_list: &TestList<'_>) -> SetupScripts<'_> {
         SetupScripts::new(self, test_list)
@@ -704,6 +836,13 @@ impl<'cfg> NextestProfile<'cfg, FinalConfig> {
         TestSettings::new(self, query)
     }
 
+    /// Returns the test groups that this profile's overrides assign tests to.
+    ///
+    /// Used to evaluate `group()` predicates in filter expressions.
+    pub fn test_group_assignments(&self) -> TestGroupAssignments {
+        TestGroupAssignments::new(&self.compiled_data.overrides)
+    }
+
     /// Returns override settings for individual tests, with sources attached.
     pub(crate) fn settings_with_source_for(
         &self,
@@ -712,28 +851,46 @@ impl<'cfg> NextestProfile<'cfg, FinalConfig> {
         TestSettings::new(self, query)
     }
 
+    /// Returns the setup scripts enabled for an individual test, along with the rules that enabled
+    /// them.
+    ///
+    /// Scripts are returned in the order they're defined in, and a script enabled by several rules
+    /// is returned once for each rule.
+    pub(crate) fn scripts_with_source_for(
+        &self,
+        query: &TestQuery<'_>,
+    ) -> Vec<(&'cfg ScriptId, &CompiledProfileScripts<FinalConfig>)> {
+        let mut scripts = Vec::new();
+        for (script_id, _) in self.scripts {
+            for rule in &self.compiled_data.scripts {
+                if rule.setup().contains(script_id) && rule.is_enabled(query) {
+                    scripts.push((script_id, rule));
+                }
+            }
+        }
+        scripts
+    }
+
     /// Returns the JUnit configuration for this profile.
     pub fn junit(&self) -> Option<NextestJunitConfig<'cfg>> {
-        let path = self
-            .custom_profile
-            .map(|profile| &profile.junit.path)
-            .unwrap_or(&self.default_profile.junit.path)
-            .as_deref();
+        // Custom profiles don't inherit the JUnit path from the default profile.
+        let path = if self.custom_profiles.is_empty() {
+            self.default_profile.junit.path.as_deref()
+        } else {
+            self.custom_profiles
+                .iter()
+                .find_map(|(_, profile)| profile.junit.path.as_deref())
+        };
 
         path.map(|path| {
             let path = self.store_dir.join(path);
             let report_name = self
-                .custom_profile
-                .and_then(|profile| profile.junit.report_name.as_deref())
+                .custom_profiles
+                .iter()
+                .find_map(|(_, profile)| profile.junit.report_name.as_deref())
                 .unwrap_or(&self.default_profile.junit.report_name);
-            let store_success_output = self
-                .custom_profile
-                .and_then(|profile| profile.junit.store_success_output)
-                .unwrap_or(self.default_profile.junit.store_success_output);
-            let store_failure_output = self
-                .custom_profile
-                .and_then(|profile| profile.junit.store_failure_output)
-                .unwrap_or(self.default_profile.junit.store_failure_output);
+            let store_success_output = self.junit_store_success_output_with_profile().0;
+            let store_failure_output = self.junit_store_failure_output_with_profile().0;
             NextestJunitConfig {
                 path,
                 report_name,
@@ -780,21 +937,39 @@ pub(super) struct NextestConfigImpl {
     store: StoreConfigImpl,
     test_groups: BTreeMap<CustomTestGroup, TestGroupConfig>,
     scripts: IndexMap<ScriptId, ScriptConfig>,
+    filters: BTreeMap<String, String>,
     default_profile: DefaultProfileImpl,
     other_profiles: HashMap<String, CustomProfileImpl>,
 }
 
 impl NextestConfigImpl {
-    fn get_profile(&self, profile: &str) -> Result<Option<&CustomProfileImpl>, ProfileNotFound> {
-        let custom_profile = match profile {
+    /// Returns the given profile followed by the profiles it inherits from, along with their
+    /// names. The default profile is not included.
+    fn get_profile_chain(
+        &self,
+        profile: &str,
+    ) -> Result<Vec<(&str, &CustomProfileImpl)>, ProfileNotFound> {
+        let mut chain = Vec::new();
+        let mut next = match profile {
             NextestConfig::DEFAULT_PROFILE => None,
             other => Some(
                 self.other_profiles
-                    .get(other)
+                    .get_key_value(other)
+                    .map(|(name, custom_profile)| (name.as_str(), custom_profile))
                     .ok_or_else(|| ProfileNotFound::new(profile, self.all_profiles()))?,
             ),
         };
-        Ok(custom_profile)
+        while let Some((name, custom_profile)) = next {
+            chain.push((name, custom_profile));
+            // Inheritance was checked to be known and acyclic while parsing the config. Profiles
+            // that inherit from the default profile end the chain here.
+            next = custom_profile.inherits.as_deref().and_then(|inherits| {
+                self.other_profiles
+                    .get_key_value(inherits)
+                    .map(|(name, custom_profile)| (name.as_str(), custom_profile))
+            });
+        }
+        Ok(chain)
     }
 
     fn all_profiles(&self) -> impl Iterator<Item = &str> {
@@ -834,6 +1009,8 @@ struct NextestConfigDeserialize {
     test_groups: BTreeMap<CustomTestGroup, TestGroupConfig>,
     #[serde(default, rename = "script")]
     scripts: IndexMap<ScriptId, ScriptConfig>,
+    #[serde(default)]
+    filters: BTreeMap<String, String>,
     #[serde(rename = "profile")]
     profiles: HashMap<String, CustomProfileImpl>,
 }
@@ -851,6 +1028,7 @@ impl NextestConfigDeserialize {
             default_profile,
             test_groups: self.test_groups,
             scripts: self.scripts,
+            filters: self.filters,
             other_profiles: self.profiles,
         }
     }
@@ -872,6 +1050,7 @@ pub(super) struct DefaultProfileImpl {
     failure_output: TestOutputDisplay,
     success_output: TestOutputDisplay,
     fail_fast: bool,
+    setup_scripts_parallel: bool,
     slow_timeout: SlowTimeout,
     leak_timeout: Duration,
     overrides: Vec<DeserializedOverride>,
@@ -902,6 +1081,9 @@ impl DefaultProfileImpl {
                 .success_output
                 .expect("success-output present in default profile"),
             fail_fast: p.fail_fast.expect("fail-fast present in default profile"),
+            setup_scripts_parallel: p
+                .setup_scripts_parallel
+                .expect("setup-scripts-parallel present in default profile"),
             slow_timeout: p
                 .slow_timeout
                 .expect("slow-timeout present in default profile"),
@@ -948,6 +1130,8 @@ struct DefaultJunitImpl {
 #[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub(super) struct CustomProfileImpl {
+    #[serde(default)]
+    inherits: Option<String>,
     #[serde(default, deserialize_with = "super::deserialize_retry_policy")]
     retries: Option<RetryPolicy>,
     #[serde(default)]
@@ -964,6 +1148,8 @@ pub(super) struct CustomProfileImpl {
     success_output: Option<TestOutputDisplay>,
     #[serde(default)]
     fail_fast: Option<bool>,
+    #[serde(default)]
+    setup_scripts_parallel: Option<bool>,
     #[serde(default, deserialize_with = "super::deserialize_slow_timeout")]
     slow_timeout: Option<SlowTimeout>,
     #[serde(default, with = "humantime_serde::option")]
@@ -991,6 +1177,59 @@ impl CustomProfileImpl {
     }
 }
 
+/// Checks that the `inherits` keys of `profiles` only refer to known profiles, and don't form a
+/// cycle.
+fn check_profile_inheritance(
+    profiles: &HashMap<String, CustomProfileImpl>,
+) -> Result<(), ProfileInheritanceError> {
+    // Sort profiles by name so errors are reported deterministically.
+    let mut names: Vec<_> = profiles.keys().map(|name| name.as_str()).collect();
+    names.sort_unstable();
+
+    for &name in &names {
+        let Some(inherits) = &profiles[name].inherits else {
+            continue;
+        };
+        if name == NextestConfig::DEFAULT_PROFILE {
+            return Err(ProfileInheritanceError::Default {
+                inherits: inherits.clone(),
+            });
+        }
+        if !profiles.contains_key(inherits) {
+            return Err(ProfileInheritanceError::Unknown {
+                profile: name.to_owned(),
+                inherits: inherits.clone(),
+            });
+        }
+    }
+
+    // Each profile inherits from at most one other profile, so following the chain from each
+    // profile finds every cycle.
+    let mut done = HashSet::new();
+    for &name in &names {
+        let mut path = Vec::new();
+        let mut next = Some(name);
+        while let Some(name) = next {
+            if done.contains(name) {
+                break;
+            }
+            if let Some(pos) = path.iter().position(|n| *n == name) {
+                let cycle = path[pos..]
+                    .iter()
+                    .chain(std::iter::once(&name))
+                    .map(|n| (*n).to_owned())
+                    .collect();
+                return Err(ProfileInheritanceError::Cycle { cycle });
+            }
+            path.push(name);
+            next = profiles[name].inherits.as_deref();
+        }
+        done.extend(path);
+    }
+
+    Ok(())
+}
+
 #[derive(Clone, Debug, Default, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 struct JunitImpl {
@@ -1009,6 +1248,10 @@ mod tests {
     use super::*;
     use crate::config::test_helpers::*;
     use camino_tempfile::tempdir;
+    use display_error_chain::DisplayErrorChain;
+    use guppy::graph::cargo::BuildPlatform;
+    use indoc::indoc;
+    use test_case::test_case;
 
     #[test]
     fn default_config_is_valid() {
@@ -1104,4 +1347,172 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn profile_inheritance() {
+        let config_contents = indoc! {r#"
+            [profile.default]
+            retries = 1
+
+            [profile.ci]
+            retries = 2
+            fail-fast = false
+            setup-scripts-parallel = true
+
+            [profile.ci.junit]
+            path = "junit.xml"
+
+            [[profile.ci.overrides]]
+            filter = 'test(test_foo)'
+            retries = 5
+
+            [profile.ci-nightly]
+            inherits = "ci"
+            fail-fast = true
+
+            [[profile.ci-nightly.overrides]]
+            filter = 'test(test_bar)'
+            retries = 10
+
+            [profile.other]
+            inherits = "default"
+        "#};
+
+        let workspace_dir = tempdir().unwrap();
+
+        let graph = temp_workspace(workspace_dir.path(), config_contents);
+        let package_id = graph.workspace().iter().next().unwrap().id();
+
+        let config = NextestConfig::from_sources(
+            graph.workspace().root(),
+            &graph,
+            None,
+            &[][..],
+            &Default::default(),
+        )
+        .expect("config is valid");
+
+        let binary_query =
+            binary_query(&graph, package_id, "lib", "my-binary", BuildPlatform::Host);
+        let query_for = |test_name| TestQuery {
+            binary_query: binary_query.to_query(),
+            test_name,
+            ignored: false,
+            test_group: None,
+        };
+
+        let profile = config
+            .profile("ci-nightly")
+            .expect("valid profile name")
+            .apply_build_platforms(&build_platforms());
+        // Settings not set on this profile are inherited from the parent profile.
+        assert_eq!(profile.retries(), RetryPolicy::new_without_delay(2));
+        // ... and are attributed to the profile they're inherited from.
+        assert_eq!(
+            profile.retries_with_profile(),
+            (RetryPolicy::new_without_delay(2), "ci")
+        );
+        assert_eq!(profile.leak_timeout_with_profile().1, "default");
+        let (_, source) = profile
+            .settings_with_source_for(&query_for("test_baz"))
+            .retries_with_source();
+        assert!(
+            matches!(source, SettingSource::Profile("ci")),
+            "unexpected source {source:?}"
+        );
+        assert!(profile.setup_scripts_parallel());
+        // Settings set on this profile take precedence over the parent profile.
+        assert!(profile.fail_fast());
+        let junit = profile.junit().expect("JUnit is inherited");
+        assert_eq!(junit.path(), profile.store_dir().join("junit.xml"));
+        // Overrides from both this profile and the parent profile apply.
+        assert_eq!(
+            profile.settings_for(&query_for("test_foo")).retries(),
+            RetryPolicy::new_without_delay(5)
+        );
+        assert_eq!(
+            profile.settings_for(&query_for("test_bar")).retries(),
+            RetryPolicy::new_without_delay(10)
+        );
+
+        // Inheriting from the default profile is the same as not inheriting at all.
+        let profile = config
+            .profile("other")
+            .expect("valid profile name")
+            .apply_build_platforms(&build_platforms());
+        assert_eq!(
+            profile.retries_with_profile(),
+            (RetryPolicy::new_without_delay(1), "default")
+        );
+        assert!(profile.junit().is_none(), "JUnit is not configured");
+        assert!(
+            !profile.setup_scripts_parallel(),
+            "setup scripts run one at a time by default"
+        );
+    }
+
+    #[test_case(
+        indoc! {r#"
+            [profile.default]
+            inherits = "ci"
+
+            [profile.ci]
+        "#},
+        "the default profile cannot inherit from another profile (inherits `ci`)"
+
+        ; "default inherits"
+    )]
+    #[test_case(
+        indoc! {r#"
+            [profile.ci]
+            inherits = "foo"
+        "#},
+        "profile `ci` inherits from unknown profile `foo`"
+
+        ; "unknown profile"
+    )]
+    #[test_case(
+        indoc! {r#"
+            [profile.a]
+            inherits = "b"
+
+            [profile.b]
+            inherits = "c"
+
+            [profile.c]
+            inherits = "a"
+        "#},
+        "profiles inherit from each other in a cycle: a -> b -> c -> a"
+
+        ; "inheritance cycle"
+    )]
+    #[test_case(
+        indoc! {r#"
+            [profile.a]
+            inherits = "a"
+        "#},
+        "profiles inherit from each other in a cycle: a -> a"
+
+        ; "inherits from itself"
+    )]
+    fn profile_inheritance_invalid(config_contents: &str, message: &str) {
+        let workspace_dir = tempdir().unwrap();
+
+        let graph = temp_workspace(workspace_dir.path(), config_contents);
+
+        let nextest_config_error = NextestConfig::from_sources(
+            graph.workspace().root(),
+            &graph,
+            None,
+            &[][..],
+            &Default::default(),
+        )
+        .expect_err("config is invalid");
+        let actual_message = DisplayErrorChain::new(nextest_config_error).to_string();
+
+        assert!(
+            actual_message.contains(message),
+            "nextest config error `{actual_message}` contains message `{message}`"
+        );
+    }
 }
@@ -3,29 +3,37 @@
 
 //! Configuration support for nextest.
 
+mod artifacts;
 mod config_impl;
 mod identifier;
 mod nextest_version;
+mod output_limits;
 mod overrides;
+mod resource_limits;
 mod retry_policy;
 mod scripts;
 mod slow_timeout;
 mod test_group;
 mod test_threads;
 mod threads_required;
+mod timeout_diagnostics;
 mod tool_config;
 
+pub use artifacts::*;
 pub use config_impl::*;
 pub use identifier::*;
 pub use nextest_version::*;
+pub use output_limits::*;
 pub use overrides::*;
+pub use resource_limits::*;
 pub use retry_policy::*;
 pub(super) use scripts::*;
 pub use slow_timeout::*;
 pub use test_group::*;
 pub use test_threads::*;
 pub use threads_required::*;
+pub use timeout_diagnostics::*;
 pub use tool_config::*;
 
 #[cfg(test)]
-mod test_helpers;
+pub(crate) mod test_helpers;
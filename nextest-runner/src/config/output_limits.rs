@@ -0,0 +1,156 @@
+// Copyright (c) The nextest Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use serde::{de::IntoDeserializer, Deserialize};
+use std::fmt;
+
+/// The maximum amount of output captured from a test, for each of its output streams and in total.
+///
+/// Returned by [`TestSettings::max_output_size`](super::TestSettings::max_output_size). What
+/// happens to output past the limit is controlled by [`OutputOverflow`].
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct MaxOutputSize {
+    /// The maximum number of bytes captured from standard output.
+    #[serde(default, deserialize_with = "super::deserialize_byte_size")]
+    pub stdout: Option<u64>,
+
+    /// The maximum number of bytes captured from standard error.
+    #[serde(default, deserialize_with = "super::deserialize_byte_size")]
+    pub stderr: Option<u64>,
+
+    /// The maximum number of bytes captured from standard output and standard error combined.
+    #[serde(default, deserialize_with = "super::deserialize_byte_size")]
+    pub total: Option<u64>,
+}
+
+impl MaxOutputSize {
+    /// Returns true if output isn't limited.
+    pub fn is_empty(&self) -> bool {
+        self.stdout.is_none() && self.stderr.is_none() && self.total.is_none()
+    }
+}
+
+/// What happens to test output past [`MaxOutputSize`].
+///
+/// Either way, the beginning and the end of the output are kept, and output is dropped from the
+/// middle.
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputOverflow {
+    /// Output past the limit is discarded.
+    #[default]
+    Drop,
+
+    /// The full output of a stream that goes past the limit is written to a file in the store
+    /// directory.
+    Spill,
+}
+
+pub(super) fn deserialize_max_output_size<'de, D>(
+    deserializer: D,
+) -> Result<Option<MaxOutputSize>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    struct V;
+
+    impl<'de2> serde::de::Visitor<'de2> for V {
+        type Value = Option<MaxOutputSize>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            write!(
+                formatter,
+                "a table ({{ stdout = \"10MB\", stderr = \"1MB\", total = \"10MB\" }}), a string (\"10MB\") \
+                 or a positive number of bytes"
+            )
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where
+            E: serde::de::Error,
+        {
+            let size = super::deserialize_byte_size(v.into_deserializer())?;
+            Ok(Some(MaxOutputSize {
+                stdout: size,
+                stderr: size,
+                total: None,
+            }))
+        }
+
+        // Note that TOML uses i64, not u64.
+        fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+        where
+            E: serde::de::Error,
+        {
+            let size = super::deserialize_byte_size(v.into_deserializer())?;
+            Ok(Some(MaxOutputSize {
+                stdout: size,
+                stderr: size,
+                total: None,
+            }))
+        }
+
+        fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+        where
+            A: serde::de::MapAccess<'de2>,
+        {
+            MaxOutputSize::deserialize(serde::de::value::MapAccessDeserializer::new(map)).map(Some)
+        }
+    }
+
+    deserializer.deserialize_any(V)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[derive(Debug, Deserialize)]
+    struct Wrapper {
+        #[serde(deserialize_with = "deserialize_max_output_size")]
+        max_output_size: Option<MaxOutputSize>,
+    }
+
+    #[test_case(
+        "max_output_size = \"10MB\"",
+        Some(MaxOutputSize { stdout: Some(10_000_000), stderr: Some(10_000_000), total: None })
+
+        ; "string applies to both streams"
+    )]
+    #[test_case(
+        "max_output_size = 4096",
+        Some(MaxOutputSize { stdout: Some(4096), stderr: Some(4096), total: None })
+
+        ; "number of bytes"
+    )]
+    #[test_case(
+        "max_output_size = { stdout = \"1MiB\", stderr = 100 }",
+        Some(MaxOutputSize { stdout: Some(1 << 20), stderr: Some(100), total: None })
+
+        ; "table with both streams"
+    )]
+    #[test_case(
+        "max_output_size = { stderr = \"1MB\" }",
+        Some(MaxOutputSize { stdout: None, stderr: Some(1_000_000), total: None })
+
+        ; "table with one stream"
+    )]
+    #[test_case(
+        "max_output_size = { stdout = \"1MB\", total = \"2MB\" }",
+        Some(MaxOutputSize { stdout: Some(1_000_000), stderr: None, total: Some(2_000_000) })
+
+        ; "table with total"
+    )]
+    #[test_case("max_output_size = \"0MB\"", None; "zero is invalid")]
+    #[test_case("max_output_size = -1", None; "negative is invalid")]
+    #[test_case("max_output_size = { stdin = \"1MB\" }", None; "unknown stream is invalid")]
+    fn test_deserialize_max_output_size(input: &str, expected: Option<MaxOutputSize>) {
+        let actual = toml::from_str::<Wrapper>(input);
+        match expected {
+            Some(expected) => assert_eq!(actual.unwrap().max_output_size, Some(expected)),
+            None => assert!(actual.is_err(), "expected error, got {actual:?}"),
+        }
+    }
+}
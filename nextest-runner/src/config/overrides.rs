@@ -2,16 +2,20 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 use super::{
-    CompiledProfileScripts, DeserializedProfileScriptConfig, NextestConfigImpl, NextestProfile,
+    CompiledProfileScripts, DeserializedProfileScriptConfig, NextestConfig, NextestConfigImpl,
+    NextestProfile,
 };
 use crate::{
-    config::{FinalConfig, PreBuildPlatform, RetryPolicy, SlowTimeout, TestGroup, ThreadsRequired},
+    config::{
+        ArtifactGlobs, FinalConfig, MaxOutputSize, OutputOverflow, PreBuildPlatform,
+        ResourceLimits, RetryPolicy, SlowTimeout, TestGroup, ThreadsRequired, TimeoutDiagnostics,
+    },
     errors::{ConfigParseCompiledDataError, ConfigParseErrorKind},
     platform::BuildPlatforms,
     reporter::TestOutputDisplay,
 };
 use guppy::graph::{cargo::BuildPlatform, PackageGraph};
-use nextest_filtering::{FilteringExpr, TestQuery};
+use nextest_filtering::{FilteringExpr, NamedFiltersets, TestQuery};
 use serde::{Deserialize, Deserializer};
 use smol_str::SmolStr;
 use std::{collections::HashMap, time::Duration};
@@ -34,15 +38,25 @@ pub struct TestSettings<Source = ()> {
     failure_output: (TestOutputDisplay, Source),
     junit_store_success_output: (bool, Source),
     junit_store_failure_output: (bool, Source),
+    quarantined: (bool, Source),
+    memory_limit: (Option<u64>, Source),
+    cpu_time_limit: (Option<Duration>, Source),
+    max_open_files: (Option<u64>, Source),
+    isolation: (bool, Source),
+    collect_artifacts: (ArtifactGlobs, Source),
+    collect_core_dumps: (bool, Source),
+    max_output_size: (MaxOutputSize, Source),
+    output_overflow: (OutputOverflow, Source),
+    timeout_diagnostics: (Option<TimeoutDiagnostics>, Source),
 }
 
 pub(crate) trait TrackSource<'p>: Sized {
-    fn track_profile<T>(value: T) -> (T, Self);
+    fn track_profile<T>(value: T, profile_name: &'p str) -> (T, Self);
     fn track_override<T>(value: T, source: &'p CompiledOverride<FinalConfig>) -> (T, Self);
 }
 
 impl<'p> TrackSource<'p> for () {
-    fn track_profile<T>(value: T) -> (T, Self) {
+    fn track_profile<T>(value: T, _profile_name: &'p str) -> (T, Self) {
         (value, ())
     }
 
@@ -53,13 +67,14 @@ impl<'p> TrackSource<'p> for () {
 
 #[derive(Copy, Clone, Debug)]
 pub(crate) enum SettingSource<'p> {
-    Profile,
+    /// The setting is taken from the profile with this name.
+    Profile(&'p str),
     Override(&'p CompiledOverride<FinalConfig>),
 }
 
 impl<'p> TrackSource<'p> for SettingSource<'p> {
-    fn track_profile<T>(value: T) -> (T, Self) {
-        (value, SettingSource::Profile)
+    fn track_profile<T>(value: T, profile_name: &'p str) -> (T, Self) {
+        (value, SettingSource::Profile(profile_name))
     }
 
     fn track_override<T>(value: T, source: &'p CompiledOverride<FinalConfig>) -> (T, Self) {
@@ -112,6 +127,74 @@ impl TestSettings {
     pub fn junit_store_failure_output(&self) -> bool {
         self.junit_store_failure_output.0
     }
+
+    /// Returns whether this test is quarantined.
+    ///
+    /// Quarantined tests are run and reported as usual, but their failures don't cause the run to
+    /// fail.
+    pub fn quarantined(&self) -> bool {
+        self.quarantined.0
+    }
+
+    /// Returns the memory limit for this test, in bytes, if any.
+    pub fn memory_limit(&self) -> Option<u64> {
+        self.memory_limit.0
+    }
+
+    /// Returns the CPU time limit for this test, if any.
+    pub fn cpu_time_limit(&self) -> Option<Duration> {
+        self.cpu_time_limit.0
+    }
+
+    /// Returns the maximum number of files this test can have open, if any.
+    pub fn max_open_files(&self) -> Option<u64> {
+        self.max_open_files.0
+    }
+
+    /// Returns whether this test is run in an isolated sandbox.
+    ///
+    /// Isolated tests don't have network access, get a private `/tmp`, and can't write to the
+    /// workspace outside of the target directory. Isolation is only supported on Linux.
+    pub fn isolation(&self) -> bool {
+        self.isolation.0
+    }
+
+    /// Returns the patterns for files to collect if this test fails.
+    pub fn collect_artifacts(&self) -> &ArtifactGlobs {
+        &self.collect_artifacts.0
+    }
+
+    /// Returns whether core dumps are collected if this test crashes.
+    ///
+    /// Core dumps are currently only collected on Linux and macOS.
+    pub fn collect_core_dumps(&self) -> bool {
+        self.collect_core_dumps.0
+    }
+
+    /// Returns the maximum amount of output captured from this test.
+    pub fn max_output_size(&self) -> MaxOutputSize {
+        self.max_output_size.0
+    }
+
+    /// Returns what happens to output from this test past its maximum output size.
+    pub fn output_overflow(&self) -> OutputOverflow {
+        self.output_overflow.0
+    }
+
+    /// Returns the command run to diagnose this test before it's terminated for timing out, if
+    /// any.
+    pub fn timeout_diagnostics(&self) -> Option<&TimeoutDiagnostics> {
+        self.timeout_diagnostics.0.as_ref()
+    }
+
+    /// Returns the resource limits that apply to this test.
+    pub fn resource_limits(&self) -> ResourceLimits {
+        ResourceLimits {
+            memory: self.memory_limit.0,
+            cpu_time: self.cpu_time_limit.0,
+            open_files: self.max_open_files.0,
+        }
+    }
 }
 
 #[allow(dead_code)]
@@ -132,26 +215,21 @@ impl<Source: Copy> TestSettings<Source> {
         let mut failure_output = None;
         let mut junit_store_success_output = None;
         let mut junit_store_failure_output = None;
+        let mut quarantined = None;
+        let mut memory_limit = None;
+        let mut cpu_time_limit = None;
+        let mut max_open_files = None;
+        let mut isolation = None;
+        let mut collect_artifacts = None;
+        let mut collect_core_dumps = None;
+        let mut max_output_size = None;
+        let mut output_overflow = None;
+        let mut timeout_diagnostics = None;
 
         for override_ in &profile.compiled_data.overrides {
-            if !override_.state.host_eval {
+            if !override_matches(&override_.state, override_.data.expr.as_ref(), query) {
                 continue;
             }
-            if query.binary_query.platform == BuildPlatform::Host && !override_.state.host_test_eval
-            {
-                continue;
-            }
-            if query.binary_query.platform == BuildPlatform::Target && !override_.state.target_eval
-            {
-                continue;
-            }
-
-            if let Some(expr) = &override_.data.expr {
-                if !expr.matches_test(query) {
-                    continue;
-                }
-                // If no expression is present, it's equivalent to "all()".
-            }
             if threads_required.is_none() {
                 if let Some(t) = override_.data.threads_required {
                     threads_required = Some(Source::track_override(t, override_));
@@ -197,29 +275,109 @@ impl<Source: Copy> TestSettings<Source> {
                     junit_store_failure_output = Some(Source::track_override(f, override_));
                 }
             }
+            if quarantined.is_none() {
+                if let Some(q) = override_.data.quarantine {
+                    quarantined = Some(Source::track_override(q, override_));
+                }
+            }
+            if memory_limit.is_none() {
+                if let Some(m) = override_.data.memory_limit {
+                    memory_limit = Some(Source::track_override(Some(m), override_));
+                }
+            }
+            if cpu_time_limit.is_none() {
+                if let Some(c) = override_.data.cpu_time_limit {
+                    cpu_time_limit = Some(Source::track_override(Some(c), override_));
+                }
+            }
+            if max_open_files.is_none() {
+                if let Some(m) = override_.data.max_open_files {
+                    max_open_files = Some(Source::track_override(Some(m), override_));
+                }
+            }
+            if isolation.is_none() {
+                if let Some(i) = override_.data.isolation {
+                    isolation = Some(Source::track_override(i, override_));
+                }
+            }
+            if collect_artifacts.is_none() {
+                if let Some(c) = &override_.data.collect_artifacts {
+                    collect_artifacts = Some(Source::track_override(c.clone(), override_));
+                }
+            }
+            if collect_core_dumps.is_none() {
+                if let Some(c) = override_.data.collect_core_dumps {
+                    collect_core_dumps = Some(Source::track_override(c, override_));
+                }
+            }
+            if max_output_size.is_none() {
+                if let Some(m) = override_.data.max_output_size {
+                    max_output_size = Some(Source::track_override(m, override_));
+                }
+            }
+            if output_overflow.is_none() {
+                if let Some(o) = override_.data.output_overflow {
+                    output_overflow = Some(Source::track_override(o, override_));
+                }
+            }
+            if timeout_diagnostics.is_none() {
+                if let Some(t) = &override_.data.timeout_diagnostics {
+                    timeout_diagnostics = Some(Source::track_override(Some(t.clone()), override_));
+                }
+            }
         }
 
         // If no overrides were found, use the profile defaults.
-        let threads_required =
-            threads_required.unwrap_or_else(|| Source::track_profile(profile.threads_required()));
-        let retries = retries.unwrap_or_else(|| Source::track_profile(profile.retries()));
+        fn track_profile<'p, T, Source: TrackSource<'p>>(
+            (value, profile_name): (T, &'p str),
+        ) -> (T, Source) {
+            Source::track_profile(value, profile_name)
+        }
+        // Settings that can only be set through overrides come from the built-in default profile.
+        fn track_default<'p, T, Source: TrackSource<'p>>(value: T) -> (T, Source) {
+            Source::track_profile(value, NextestConfig::DEFAULT_PROFILE)
+        }
+        let threads_required = threads_required
+            .unwrap_or_else(|| track_profile(profile.threads_required_with_profile()));
+        let retries = retries.unwrap_or_else(|| track_profile(profile.retries_with_profile()));
         let slow_timeout =
-            slow_timeout.unwrap_or_else(|| Source::track_profile(profile.slow_timeout()));
+            slow_timeout.unwrap_or_else(|| track_profile(profile.slow_timeout_with_profile()));
         let leak_timeout =
-            leak_timeout.unwrap_or_else(|| Source::track_profile(profile.leak_timeout()));
-        let test_group = test_group.unwrap_or_else(|| Source::track_profile(TestGroup::Global));
+            leak_timeout.unwrap_or_else(|| track_profile(profile.leak_timeout_with_profile()));
+        let test_group = test_group.unwrap_or_else(|| track_default(TestGroup::Global));
         let success_output =
-            success_output.unwrap_or_else(|| Source::track_profile(profile.success_output()));
+            success_output.unwrap_or_else(|| track_profile(profile.success_output_with_profile()));
         let failure_output =
-            failure_output.unwrap_or_else(|| Source::track_profile(profile.failure_output()));
+            failure_output.unwrap_or_else(|| track_profile(profile.failure_output_with_profile()));
         let junit_store_success_output = junit_store_success_output.unwrap_or_else(|| {
             // If the profile doesn't have JUnit enabled, success output can just be false.
-            Source::track_profile(profile.junit().map_or(false, |j| j.store_success_output()))
+            let (store, profile_name) = profile.junit_store_success_output_with_profile();
+            Source::track_profile(store && profile.junit().is_some(), profile_name)
         });
         let junit_store_failure_output = junit_store_failure_output.unwrap_or_else(|| {
             // If the profile doesn't have JUnit enabled, failure output can just be false.
-            Source::track_profile(profile.junit().map_or(false, |j| j.store_failure_output()))
+            let (store, profile_name) = profile.junit_store_failure_output_with_profile();
+            Source::track_profile(store && profile.junit().is_some(), profile_name)
         });
+        // Quarantine can only be set through overrides.
+        let quarantined = quarantined.unwrap_or_else(|| track_default(false));
+        // Resource limits can only be set through overrides.
+        let memory_limit = memory_limit.unwrap_or_else(|| track_default(None));
+        let cpu_time_limit = cpu_time_limit.unwrap_or_else(|| track_default(None));
+        let max_open_files = max_open_files.unwrap_or_else(|| track_default(None));
+        // Isolation can only be turned on through overrides.
+        let isolation = isolation.unwrap_or_else(|| track_default(false));
+        // Artifacts can only be collected through overrides.
+        let collect_artifacts =
+            collect_artifacts.unwrap_or_else(|| track_default(ArtifactGlobs::default()));
+        let collect_core_dumps = collect_core_dumps.unwrap_or_else(|| track_default(false));
+        // Output is only limited through overrides.
+        let max_output_size =
+            max_output_size.unwrap_or_else(|| track_default(MaxOutputSize::default()));
+        let output_overflow =
+            output_overflow.unwrap_or_else(|| track_default(OutputOverflow::default()));
+        // Timeout diagnostics can only be set through overrides.
+        let timeout_diagnostics = timeout_diagnostics.unwrap_or_else(|| track_default(None));
 
         TestSettings {
             threads_required,
@@ -231,6 +389,16 @@ impl<Source: Copy> TestSettings<Source> {
             failure_output,
             junit_store_success_output,
             junit_store_failure_output,
+            quarantined,
+            memory_limit,
+            cpu_time_limit,
+            max_open_files,
+            isolation,
+            collect_artifacts,
+            collect_core_dumps,
+            max_output_size,
+            output_overflow,
+            timeout_diagnostics,
         }
     }
 
@@ -258,6 +426,131 @@ impl<Source: Copy> TestSettings<Source> {
     pub(crate) fn test_group_with_source(&self) -> &(TestGroup, Source) {
         &self.test_group
     }
+
+    /// Returns the success output setting for this test, with the source attached.
+    pub(crate) fn success_output_with_source(&self) -> (TestOutputDisplay, Source) {
+        self.success_output
+    }
+
+    /// Returns the failure output setting for this test, with the source attached.
+    pub(crate) fn failure_output_with_source(&self) -> (TestOutputDisplay, Source) {
+        self.failure_output
+    }
+
+    /// Returns whether success output should be stored in JUnit, with the source attached.
+    pub(crate) fn junit_store_success_output_with_source(&self) -> (bool, Source) {
+        self.junit_store_success_output
+    }
+
+    /// Returns whether failure output should be stored in JUnit, with the source attached.
+    pub(crate) fn junit_store_failure_output_with_source(&self) -> (bool, Source) {
+        self.junit_store_failure_output
+    }
+
+    /// Returns whether this test is quarantined, with the source attached.
+    pub(crate) fn quarantined_with_source(&self) -> (bool, Source) {
+        self.quarantined
+    }
+
+    /// Returns the memory limit for this test, with the source attached.
+    pub(crate) fn memory_limit_with_source(&self) -> (Option<u64>, Source) {
+        self.memory_limit
+    }
+
+    /// Returns the CPU time limit for this test, with the source attached.
+    pub(crate) fn cpu_time_limit_with_source(&self) -> (Option<Duration>, Source) {
+        self.cpu_time_limit
+    }
+
+    /// Returns the maximum number of open files for this test, with the source attached.
+    pub(crate) fn max_open_files_with_source(&self) -> (Option<u64>, Source) {
+        self.max_open_files
+    }
+
+    /// Returns whether this test is run in an isolated sandbox, with the source attached.
+    pub(crate) fn isolation_with_source(&self) -> (bool, Source) {
+        self.isolation
+    }
+
+    /// Returns the patterns for files to collect if this test fails, with the source attached.
+    pub(crate) fn collect_artifacts_with_source(&self) -> &(ArtifactGlobs, Source) {
+        &self.collect_artifacts
+    }
+
+    /// Returns whether core dumps are collected if this test crashes, with the source attached.
+    pub(crate) fn collect_core_dumps_with_source(&self) -> (bool, Source) {
+        self.collect_core_dumps
+    }
+
+    /// Returns the maximum amount of output captured from this test, with the source attached.
+    pub(crate) fn max_output_size_with_source(&self) -> (MaxOutputSize, Source) {
+        self.max_output_size
+    }
+
+    /// Returns what happens to output past the maximum output size, with the source attached.
+    pub(crate) fn output_overflow_with_source(&self) -> (OutputOverflow, Source) {
+        self.output_overflow
+    }
+
+    /// Returns the command run to diagnose this test before it's terminated for timing out, with
+    /// the source attached.
+    pub(crate) fn timeout_diagnostics_with_source(&self) -> &(Option<TimeoutDiagnostics>, Source) {
+        &self.timeout_diagnostics
+    }
+}
+
+fn override_matches(
+    state: &FinalConfig,
+    expr: Option<&FilteringExpr>,
+    query: &TestQuery<'_>,
+) -> bool {
+    if !state.host_eval {
+        return false;
+    }
+    if query.binary_query.platform == BuildPlatform::Host && !state.host_test_eval {
+        return false;
+    }
+    if query.binary_query.platform == BuildPlatform::Target && !state.target_eval {
+        return false;
+    }
+
+    // If no expression is present, it's equivalent to "all()".
+    expr.map_or(true, |expr| expr.matches_test(query))
+}
+
+/// The test groups that a profile's overrides assign tests to.
+///
+/// Returned by [`NextestProfile::test_group_assignments`], and used to evaluate `group()`
+/// predicates in filter expressions.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TestGroupAssignments {
+    // Only the overrides that set a test group, in order of precedence.
+    overrides: Vec<(FinalConfig, Option<FilteringExpr>, TestGroup)>,
+}
+
+impl TestGroupAssignments {
+    pub(super) fn new(overrides: &[CompiledOverride<FinalConfig>]) -> Self {
+        let overrides = overrides
+            .iter()
+            .filter_map(|override_| {
+                let test_group = override_.data.test_group.clone()?;
+                Some((
+                    override_.state.clone(),
+                    override_.data.expr.clone(),
+                    test_group,
+                ))
+            })
+            .collect();
+        Self { overrides }
+    }
+
+    /// Returns the test group that the given test is assigned to.
+    pub fn group_for(&self, query: &TestQuery<'_>) -> &TestGroup {
+        self.overrides
+            .iter()
+            .find(|(state, expr, _)| override_matches(state, expr.as_ref(), query))
+            .map_or(&TestGroup::Global, |(_, _, test_group)| test_group)
+    }
 }
 
 #[derive(Clone, Debug, Default)]
@@ -270,10 +563,12 @@ impl CompiledByProfile {
     pub(super) fn new(
         graph: &PackageGraph,
         config: &NextestConfigImpl,
+        filtersets: &NamedFiltersets,
     ) -> Result<Self, ConfigParseErrorKind> {
         let mut errors = vec![];
         let default = CompiledData::new(
             graph,
+            filtersets,
             "default",
             config.default_profile().overrides(),
             config.default_profile().setup_scripts(),
@@ -286,6 +581,7 @@ impl CompiledByProfile {
                     profile_name.to_owned(),
                     CompiledData::new(
                         graph,
+                        filtersets,
                         profile_name,
                         profile.overrides(),
                         profile.scripts(),
@@ -312,6 +608,7 @@ pub(super) struct CompiledData<State> {
 impl CompiledData<PreBuildPlatform> {
     fn new(
         graph: &PackageGraph,
+        filtersets: &NamedFiltersets,
         profile_name: &str,
         overrides: &[DeserializedOverride],
         scripts: &[DeserializedProfileScriptConfig],
@@ -321,12 +618,14 @@ impl CompiledData<PreBuildPlatform> {
             .iter()
             .enumerate()
             .filter_map(|(index, source)| {
-                CompiledOverride::new(graph, profile_name, index, source, errors)
+                CompiledOverride::new(graph, filtersets, profile_name, index, source, errors)
             })
             .collect();
         let scripts = scripts
             .iter()
-            .filter_map(|source| CompiledProfileScripts::new(graph, profile_name, source, errors))
+            .filter_map(|source| {
+                CompiledProfileScripts::new(graph, filtersets, profile_name, source, errors)
+            })
             .collect();
         Self { overrides, scripts }
     }
@@ -405,11 +704,22 @@ pub(super) struct ProfileOverrideData {
     success_output: Option<TestOutputDisplay>,
     failure_output: Option<TestOutputDisplay>,
     junit: DeserializedJunitOutput,
+    quarantine: Option<bool>,
+    memory_limit: Option<u64>,
+    cpu_time_limit: Option<Duration>,
+    max_open_files: Option<u64>,
+    isolation: Option<bool>,
+    collect_artifacts: Option<ArtifactGlobs>,
+    collect_core_dumps: Option<bool>,
+    max_output_size: Option<MaxOutputSize>,
+    output_overflow: Option<OutputOverflow>,
+    timeout_diagnostics: Option<TimeoutDiagnostics>,
 }
 
 impl CompiledOverride<PreBuildPlatform> {
     fn new(
         graph: &PackageGraph,
+        filtersets: &NamedFiltersets,
         profile_name: &str,
         index: usize,
         source: &DeserializedOverride,
@@ -432,7 +742,12 @@ impl CompiledOverride<PreBuildPlatform> {
         let host_spec = MaybeTargetSpec::new(source.platform.host.as_deref());
         let target_spec = MaybeTargetSpec::new(source.platform.target.as_deref());
         let filter_expr = source.filter.as_ref().map_or(Ok(None), |filter| {
-            Some(FilteringExpr::parse(filter.clone(), graph)).transpose()
+            // Test groups are assigned by overrides, so they aren't known here.
+            Some(
+                FilteringExpr::parse_with_filtersets(filter.clone(), graph, filtersets)
+                    .and_then(|expr| expr.check_no_group(filtersets).map(|()| expr)),
+            )
+            .transpose()
         });
 
         match (host_spec, target_spec, filter_expr) {
@@ -454,6 +769,16 @@ impl CompiledOverride<PreBuildPlatform> {
                     success_output: source.success_output,
                     failure_output: source.failure_output,
                     junit: source.junit,
+                    quarantine: source.quarantine,
+                    memory_limit: source.memory_limit,
+                    cpu_time_limit: source.cpu_time_limit,
+                    max_open_files: source.max_open_files,
+                    isolation: source.isolation,
+                    collect_artifacts: source.collect_artifacts.clone(),
+                    collect_core_dumps: source.collect_core_dumps,
+                    max_output_size: source.max_output_size,
+                    output_overflow: source.output_overflow,
+                    timeout_diagnostics: source.timeout_diagnostics.clone(),
                 },
             }),
             (maybe_host_err, maybe_platform_err, maybe_parse_err) => {
@@ -566,6 +891,26 @@ pub(super) struct DeserializedOverride {
     failure_output: Option<TestOutputDisplay>,
     #[serde(default)]
     junit: DeserializedJunitOutput,
+    #[serde(default)]
+    quarantine: Option<bool>,
+    #[serde(default, deserialize_with = "super::deserialize_byte_size")]
+    memory_limit: Option<u64>,
+    #[serde(default, with = "humantime_serde::option")]
+    cpu_time_limit: Option<Duration>,
+    #[serde(default)]
+    max_open_files: Option<u64>,
+    #[serde(default)]
+    isolation: Option<bool>,
+    #[serde(default)]
+    collect_artifacts: Option<ArtifactGlobs>,
+    #[serde(default)]
+    collect_core_dumps: Option<bool>,
+    #[serde(default, deserialize_with = "super::deserialize_max_output_size")]
+    max_output_size: Option<MaxOutputSize>,
+    #[serde(default)]
+    output_overflow: Option<OutputOverflow>,
+    #[serde(default)]
+    timeout_diagnostics: Option<TimeoutDiagnostics>,
 }
 
 #[derive(Copy, Clone, Debug, Default, Deserialize)]
@@ -639,6 +984,7 @@ mod tests {
     use camino::Utf8Path;
     use camino_tempfile::tempdir;
     use indoc::indoc;
+    use nextest_filtering::errors::ParseSingleError;
     use std::num::NonZeroUsize;
     use test_case::test_case;
 
@@ -665,6 +1011,16 @@ mod tests {
             test-group = "my-group"
             failure-output = "final"
             junit = { store-failure-output = false }
+            quarantine = true
+            memory-limit = "512MiB"
+            cpu-time-limit = "30s"
+            max-open-files = 256
+            isolation = true
+            collect-artifacts = ["*.log", "snapshots/**/*.snap.new"]
+            collect-core-dumps = true
+            max-output-size = { stdout = "10MB", stderr = "1MiB" }
+            output-overflow = "spill"
+            timeout-diagnostics = "eu-stack -p {pid}"
 
             # Override 3
             [[profile.default.overrides]]
@@ -714,6 +1070,8 @@ mod tests {
         let query = TestQuery {
             binary_query: host_binary_query.to_query(),
             test_name: "test",
+            ignored: false,
+            test_group: None,
         };
         let overrides = profile.settings_for(&query);
 
@@ -736,7 +1094,41 @@ mod tests {
         {
             assert_eq!(overrides.junit_store_success_output(), false);
             assert_eq!(overrides.junit_store_failure_output(), false);
+            assert_eq!(overrides.quarantined(), true);
+            assert_eq!(overrides.isolation(), true);
+            assert_eq!(overrides.collect_core_dumps(), true);
         }
+        assert_eq!(
+            overrides.collect_artifacts().patterns().collect::<Vec<_>>(),
+            ["*.log", "snapshots/**/*.snap.new"],
+        );
+        assert_eq!(
+            overrides.max_output_size(),
+            MaxOutputSize {
+                stdout: Some(10_000_000),
+                stderr: Some(1 << 20),
+                total: None,
+            }
+        );
+        assert_eq!(overrides.output_overflow(), OutputOverflow::Spill);
+        assert_eq!(
+            overrides
+                .timeout_diagnostics()
+                .map(|diagnostics| diagnostics.command_line(42)),
+            Some(vec![
+                "eu-stack".to_owned(),
+                "-p".to_owned(),
+                "42".to_owned()
+            ]),
+        );
+        assert_eq!(
+            overrides.resource_limits(),
+            ResourceLimits {
+                memory: Some(512 << 20),
+                cpu_time: Some(Duration::from_secs(30)),
+                open_files: Some(256),
+            }
+        );
 
         // This query matches override 1 and 2.
         let target_binary_query = binary_query(
@@ -749,6 +1141,8 @@ mod tests {
         let query = TestQuery {
             binary_query: target_binary_query.to_query(),
             test_name: "test",
+            ignored: false,
+            test_group: None,
         };
         let overrides = profile.settings_for(&query);
 
@@ -782,20 +1176,48 @@ mod tests {
         {
             assert_eq!(overrides.junit_store_success_output(), true);
             assert_eq!(overrides.junit_store_failure_output(), false);
+            assert_eq!(overrides.quarantined(), true);
         }
 
         // This query matches override 3.
         let query = TestQuery {
             binary_query: target_binary_query.to_query(),
             test_name: "override3",
+            ignored: false,
+            test_group: None,
         };
         let overrides = profile.settings_for(&query);
         assert_eq!(overrides.retries(), RetryPolicy::new_without_delay(5));
+        assert!(!overrides.quarantined(), "quarantine is off by default");
+        assert!(
+            overrides.resource_limits().is_empty(),
+            "resource limits are unset by default"
+        );
+        assert!(!overrides.isolation(), "isolation is off by default");
+        assert!(
+            overrides.collect_artifacts().is_empty(),
+            "no artifacts are collected by default"
+        );
+        assert!(
+            !overrides.collect_core_dumps(),
+            "core dumps aren't collected by default"
+        );
+        assert!(
+            overrides.max_output_size().is_empty(),
+            "output is unlimited by default"
+        );
+        assert_eq!(overrides.output_overflow(), OutputOverflow::Drop);
+        assert!(
+            overrides.timeout_diagnostics().is_none(),
+            "no timeout diagnostics by default"
+        );
 
         // This query matches override 5.
         let query = TestQuery {
             binary_query: target_binary_query.to_query(),
             test_name: "override5",
+            ignored: false,
+            test_group: None,
         };
         let overrides = profile.settings_for(&query);
         assert_eq!(overrides.retries(), RetryPolicy::new_without_delay(8));
@@ -804,11 +1226,182 @@ mod tests {
         let query = TestQuery {
             binary_query: target_binary_query.to_query(),
             test_name: "no_match",
+            ignored: false,
+            test_group: None,
         };
         let overrides = profile.settings_for(&query);
         assert_eq!(overrides.retries(), RetryPolicy::new_without_delay(0));
     }
 
+    #[test]
+    fn test_overrides_filtersets() {
+        let config_contents = indoc! {r#"
+            [filters]
+            slow-tests = "test(slow) | set(very-slow)"
+            very-slow = "test(=very_slow)"
+
+            [[profile.default.overrides]]
+            filter = "set(slow-tests)"
+            retries = 3
+        "#};
+
+        let workspace_dir = tempdir().unwrap();
+
+        let graph = temp_workspace(workspace_dir.path(), config_contents);
+        let package_id = graph.workspace().iter().next().unwrap().id();
+
+        let config = NextestConfig::from_sources(
+            graph.workspace().root(),
+            &graph,
+            None,
+            &[][..],
+            &Default::default(),
+        )
+        .expect("config is valid");
+        assert_eq!(
+            config
+                .filtersets()
+                .iter()
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>(),
+            ["slow-tests", "very-slow"],
+        );
+
+        let profile = config
+            .profile("default")
+            .expect("valid profile name")
+            .apply_build_platforms(&build_platforms());
+        let binary_query = binary_query(
+            &graph,
+            package_id,
+            "lib",
+            "my-binary",
+            BuildPlatform::Target,
+        );
+        for (test_name, retries) in [("slow_test", 3), ("very_slow", 3), ("fast", 0)] {
+            let query = TestQuery {
+                binary_query: binary_query.to_query(),
+                test_name,
+                ignored: false,
+                test_group: None,
+            };
+            assert_eq!(
+                profile.settings_for(&query).retries(),
+                RetryPolicy::new_without_delay(retries),
+                "retries for {test_name}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_group_assignments() {
+        let config_contents = indoc! {r#"
+            [[profile.default.overrides]]
+            filter = "test(serial)"
+            retries = 2
+
+            [[profile.default.overrides]]
+            filter = "test(db)"
+            test-group = "serial-db"
+
+            [[profile.default.overrides]]
+            filter = "test(db) | test(serial)"
+            test-group = "serial"
+
+            [test-groups.serial-db]
+            max-threads = 1
+
+            [test-groups.serial]
+            max-threads = 1
+        "#};
+
+        let workspace_dir = tempdir().unwrap();
+
+        let graph = temp_workspace(workspace_dir.path(), config_contents);
+        let package_id = graph.workspace().iter().next().unwrap().id();
+
+        let config = NextestConfig::from_sources(
+            graph.workspace().root(),
+            &graph,
+            None,
+            &[][..],
+            &Default::default(),
+        )
+        .expect("config is valid");
+        let profile = config
+            .profile("default")
+            .expect("valid profile name")
+            .apply_build_platforms(&build_platforms());
+        let assignments = profile.test_group_assignments();
+
+        let binary_query = binary_query(
+            &graph,
+            package_id,
+            "lib",
+            "my-binary",
+            BuildPlatform::Target,
+        );
+        for (test_name, expected) in [
+            ("db_serial", test_group("serial-db")),
+            ("serial", test_group("serial")),
+            ("other", TestGroup::Global),
+        ] {
+            let query = TestQuery {
+                binary_query: binary_query.to_query(),
+                test_name,
+                ignored: false,
+                test_group: None,
+            };
+            assert_eq!(
+                assignments.group_for(&query),
+                &expected,
+                "test group for {test_name}"
+            );
+            assert_eq!(
+                profile.settings_for(&query).test_group(),
+                &expected,
+                "assignments match settings for {test_name}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_filtersets_invalid() {
+        let config_contents = indoc! {r#"
+            [filters]
+            a = "set(b)"
+            b = "set(a)"
+        "#};
+
+        let workspace_dir = tempdir().unwrap();
+        let graph = temp_workspace(workspace_dir.path(), config_contents);
+
+        let err = NextestConfig::from_sources(
+            graph.workspace().root(),
+            &graph,
+            None,
+            [],
+            &Default::default(),
+        )
+        .expect_err("config is invalid");
+        match err.kind() {
+            ConfigParseErrorKind::FiltersetParseErrors(errors) => {
+                assert_eq!(errors.len(), 1, "one filterset has errors");
+                assert_eq!(errors[0].name, "b");
+                assert_eq!(
+                    errors[0].errors.errors,
+                    [ParseSingleError::FiltersetCycle {
+                        span: (4, 1).into(),
+                        cycle: "a -> b -> a".to_owned(),
+                    }]
+                );
+            }
+            other => {
+                panic!("for config error {other:?}, expected ConfigParseErrorKind::FiltersetParseErrors");
+            }
+        }
+    }
+
     #[test_case(
         indoc! {r#"
             [[profile.default.overrides]]
@@ -868,6 +1461,38 @@ mod tests {
 
         ; "invalid filter expression"
     )]
+    #[test_case(
+        indoc! {r#"
+            [[profile.ci.overrides]]
+            filter = 'set(missing)'
+            retries = 2
+        "#},
+        "ci",
+        &[MietteJsonReport {
+            message: "unknown filterset".to_owned(),
+            labels: vec![
+                MietteJsonLabel { label: "no filterset with this name is defined".to_owned(), span: MietteJsonSpan { offset: 4, length: 7 } }
+            ]
+        }]
+
+        ; "unknown filterset"
+    )]
+    #[test_case(
+        indoc! {r#"
+            [[profile.ci.overrides]]
+            filter = 'group(serial) & ignored()'
+            retries = 2
+        "#},
+        "ci",
+        &[MietteJsonReport {
+            message: "`group()` is not allowed in this context".to_owned(),
+            labels: vec![
+                MietteJsonLabel { label: "test groups are not known here".to_owned(), span: MietteJsonSpan { offset: 6, length: 6 } }
+            ]
+        }]
+
+        ; "group predicate"
+    )]
     fn parse_overrides_invalid(
         config_contents: &str,
         faulty_profile: &str,
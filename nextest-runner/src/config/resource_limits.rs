@@ -0,0 +1,130 @@
+// Copyright (c) The nextest Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use serde::de::Unexpected;
+use std::{fmt, time::Duration};
+
+/// Limits on the resources a test process can use.
+///
+/// Returned by [`TestSettings::resource_limits`](super::TestSettings::resource_limits). Limits are
+/// currently only enforced on Unix platforms.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ResourceLimits {
+    /// The maximum size of the test's address space (not its resident memory), in bytes.
+    pub memory: Option<u64>,
+
+    /// The maximum amount of CPU time the test can use.
+    pub cpu_time: Option<Duration>,
+
+    /// The maximum number of files the test can have open at a time.
+    pub open_files: Option<u64>,
+}
+
+impl ResourceLimits {
+    /// Returns true if no limits are set.
+    pub fn is_empty(&self) -> bool {
+        self.memory.is_none() && self.cpu_time.is_none() && self.open_files.is_none()
+    }
+}
+
+/// A kind of resource limited by [`ResourceLimits`], that a test can be reported as having run
+/// into.
+///
+/// Running into the memory or open files limits makes allocations or opening files fail, which
+/// tests can handle in any number of ways, so tests that fail that way are reported as regular
+/// failures.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ResourceLimitKind {
+    /// The CPU time limit.
+    CpuTime,
+}
+
+impl fmt::Display for ResourceLimitKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CpuTime => write!(f, "CPU time"),
+        }
+    }
+}
+
+pub(super) fn deserialize_byte_size<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    struct V;
+
+    impl<'de2> serde::de::Visitor<'de2> for V {
+        type Value = Option<u64>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            write!(
+                formatter,
+                "a positive number of bytes or a string with a unit (\"512MB\", \"2GiB\")"
+            )
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where
+            E: serde::de::Error,
+        {
+            parse_byte_size(v)
+                .map(Some)
+                .ok_or_else(|| E::invalid_value(Unexpected::Str(v), &self))
+        }
+
+        // Note that TOML uses i64, not u64.
+        fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+        where
+            E: serde::de::Error,
+        {
+            if v > 0 {
+                Ok(Some(v as u64))
+            } else {
+                Err(E::invalid_value(Unexpected::Signed(v), &self))
+            }
+        }
+    }
+
+    deserializer.deserialize_any(V)
+}
+
+/// Parses a size like `512MB` or `2GiB` into a number of bytes.
+fn parse_byte_size(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let unit_start = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(unit_start);
+    let number: u64 = number.parse().ok()?;
+    let multiplier: u64 = match unit.trim_start().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1000,
+        "mb" => 1000 * 1000,
+        "gb" => 1000 * 1000 * 1000,
+        "tb" => 1000 * 1000 * 1000 * 1000,
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        "tib" => 1 << 40,
+        _ => return None,
+    };
+    number.checked_mul(multiplier).filter(|&bytes| bytes > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("1024", Some(1024); "no unit")]
+    #[test_case("100B", Some(100); "bytes")]
+    #[test_case("512MB", Some(512_000_000); "decimal unit")]
+    #[test_case("2GiB", Some(2 << 30); "binary unit")]
+    #[test_case(" 4 kib ", Some(4096); "whitespace and lowercase")]
+    #[test_case("0MB", None; "zero")]
+    #[test_case("MB", None; "no number")]
+    #[test_case("1.5GB", None; "fractional")]
+    #[test_case("10XB", None; "unknown unit")]
+    #[test_case("20000000TiB", None; "overflow")]
+    fn test_parse_byte_size(input: &str, expected: Option<u64>) {
+        assert_eq!(parse_byte_size(input), expected);
+    }
+}
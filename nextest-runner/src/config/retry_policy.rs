@@ -630,6 +630,8 @@ mod tests {
         let query = TestQuery {
             binary_query: binary_query.to_query(),
             test_name: "my_test",
+            ignored: false,
+            test_group: None,
         };
         let settings_for = config
             .profile("ci")
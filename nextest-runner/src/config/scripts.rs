@@ -9,7 +9,10 @@ use super::{
 };
 use crate::{
     double_spawn::{DoubleSpawnContext, DoubleSpawnInfo},
-    errors::{ConfigParseCompiledDataError, InvalidConfigScriptName, SetupScriptError},
+    errors::{
+        ConfigParseCompiledDataError, InvalidConfigScriptName, ScriptDependencyError,
+        SetupScriptError,
+    },
     list::TestList,
     platform::BuildPlatforms,
     test_command::{apply_ld_dyld_env, create_command, LocalExecuteContext},
@@ -18,7 +21,7 @@ use camino::Utf8Path;
 use camino_tempfile::Utf8TempPath;
 use guppy::graph::{cargo::BuildPlatform, PackageGraph};
 use indexmap::IndexMap;
-use nextest_filtering::{FilteringExpr, TestQuery};
+use nextest_filtering::{FilteringExpr, NamedFiltersets, TestQuery};
 use serde::{de::Error, Deserialize};
 use smol_str::SmolStr;
 use std::{
@@ -87,20 +90,17 @@ impl<'profile> SetupScripts<'profile> {
             }
         }
 
-        // Build up a map of enabled scripts along with their data, by script ID.
+        // Build up a map of enabled scripts along with their data, by script ID. Scripts are
+        // ordered so that each script comes after its dependencies, and dependencies are enabled
+        // even if no tests match them.
         let mut enabled_scripts = IndexMap::new();
-        for (script_id, config) in script_config {
+        for (script_id, _) in script_config {
             if enabled_ids.contains(script_id) {
-                let compiled = by_script_id
-                    .remove(script_id)
-                    .expect("script id must be present");
-                enabled_scripts.insert(
+                Self::enable_script(
                     script_id,
-                    SetupScript {
-                        id: script_id.clone(),
-                        config,
-                        compiled,
-                    },
+                    script_config,
+                    &mut by_script_id,
+                    &mut enabled_scripts,
                 );
             }
         }
@@ -108,6 +108,37 @@ impl<'profile> SetupScripts<'profile> {
         Self { enabled_scripts }
     }
 
+    fn enable_script(
+        script_id: &'profile ScriptId,
+        script_config: &'profile IndexMap<ScriptId, ScriptConfig>,
+        by_script_id: &mut HashMap<
+            &'profile ScriptId,
+            Vec<&'profile CompiledProfileScripts<FinalConfig>>,
+        >,
+        enabled_scripts: &mut IndexMap<&'profile ScriptId, SetupScript<'profile>>,
+    ) {
+        if enabled_scripts.contains_key(script_id) {
+            return;
+        }
+
+        // Dependencies were checked to be known and acyclic while parsing the config.
+        let config = &script_config[script_id];
+        for dependency in &config.depends_on {
+            Self::enable_script(dependency, script_config, by_script_id, enabled_scripts);
+        }
+
+        // A script enabled only as a dependency doesn't have any rules.
+        let compiled = by_script_id.remove(script_id).unwrap_or_default();
+        enabled_scripts.insert(
+            script_id,
+            SetupScript {
+                id: script_id.clone(),
+                config,
+                compiled,
+            },
+        );
+    }
+
     /// Returns the number of enabled setup scripts.
     #[inline]
     pub fn len(&self) -> usize {
@@ -144,9 +175,14 @@ pub(crate) struct SetupScript<'profile> {
 }
 
 impl<'profile> SetupScript<'profile> {
-    /// Turns self into a command that can be executed.
+    /// Turns the command for `phase` into a command that can be executed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `phase` is [`ScriptPhase::Teardown`] and the script has no teardown command.
     pub(crate) fn make_command(
         &self,
+        phase: ScriptPhase,
         double_spawn: &DoubleSpawnInfo,
         test_list: &TestList<'_>,
     ) -> Result<SetupScriptCommand, SetupScriptError> {
@@ -156,12 +192,8 @@ impl<'profile> SetupScript<'profile> {
             dylib_path: test_list.updated_dylib_path(),
             env: test_list.cargo_env(),
         };
-        SetupScriptCommand::new(
-            &lctx,
-            self.config.program().to_owned(),
-            self.config.args(),
-            test_list.workspace_root(),
-        )
+        let (program, args) = self.config.command_for(phase);
+        SetupScriptCommand::new(&lctx, program.to_owned(), args, test_list.workspace_root())
     }
 
     pub(crate) fn is_enabled(&self, test: &TestQuery<'_>) -> bool {
@@ -239,6 +271,7 @@ impl SetupScriptCommand {
 #[derive(Clone, Debug, Default)]
 pub(crate) struct SetupScriptExecuteData<'profile> {
     env_maps: Vec<(SetupScript<'profile>, SetupScriptEnvMap)>,
+    wrappers: Vec<SetupScript<'profile>>,
 }
 
 impl<'profile> SetupScriptExecuteData<'profile> {
@@ -250,19 +283,57 @@ impl<'profile> SetupScriptExecuteData<'profile> {
         self.env_maps.push((script, env_map));
     }
 
+    pub(crate) fn add_wrapper(&mut self, script: SetupScript<'profile>) {
+        self.wrappers.push(script);
+    }
+
     /// Applies the data from setup scripts to the given test instance.
-    pub(crate) fn apply(&self, test: &TestQuery<'_>, command: &mut Command) {
+    ///
+    /// `wrapper_env_maps` is the environment exported by the wrapper scripts run for this test, and
+    /// takes precedence over the environment exported by setup scripts.
+    pub(crate) fn apply(
+        &self,
+        test: &TestQuery<'_>,
+        wrapper_env_maps: &[SetupScriptEnvMap],
+        command: &mut Command,
+    ) {
         for (script, env_map) in &self.env_maps {
             if script.is_enabled(test) {
-                for (key, value) in env_map.env_map.iter() {
-                    command.env(key, value);
-                }
+                env_map.apply(command);
             }
         }
+        for env_map in wrapper_env_maps {
+            env_map.apply(command);
+        }
+    }
+
+    /// Returns the wrapper scripts enabled for the given test, in the order they should be run in.
+    pub(crate) fn wrappers_for<'a>(
+        &'a self,
+        test: &'a TestQuery<'_>,
+    ) -> impl Iterator<Item = &'a SetupScript<'profile>> {
+        self.wrappers
+            .iter()
+            .filter(move |script| script.is_enabled(test))
+    }
+
+    /// Returns the scripts that ran successfully and have a teardown command, in the order their
+    /// teardown commands should be run in.
+    ///
+    /// Teardown commands are run in the reverse order of setup, so that a script can rely on
+    /// everything set up before it still being around.
+    pub(crate) fn teardown_scripts(
+        &self,
+    ) -> impl Iterator<Item = (&SetupScript<'profile>, &SetupScriptEnvMap)> {
+        self.env_maps
+            .iter()
+            .rev()
+            .filter(|(script, _)| script.config.teardown.is_some())
+            .map(|(script, env_map)| (script, env_map))
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub(crate) struct SetupScriptEnvMap {
     env_map: BTreeMap<String, String>,
 }
@@ -316,6 +387,13 @@ impl SetupScriptEnvMap {
     pub(crate) fn len(&self) -> usize {
         self.env_map.len()
     }
+
+    /// Sets the environment variables in this map on `command`.
+    pub(crate) fn apply(&self, command: &mut Command) {
+        for (key, value) in &self.env_map {
+            command.env(key, value);
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -328,6 +406,7 @@ pub(crate) struct CompiledProfileScripts<State> {
 impl CompiledProfileScripts<PreBuildPlatform> {
     pub(super) fn new(
         graph: &PackageGraph,
+        filtersets: &NamedFiltersets,
         profile_name: &str,
         source: &DeserializedProfileScriptConfig,
         errors: &mut Vec<ConfigParseCompiledDataError>,
@@ -349,7 +428,12 @@ impl CompiledProfileScripts<PreBuildPlatform> {
         let host_spec = MaybeTargetSpec::new(source.platform.host.as_deref());
         let target_spec = MaybeTargetSpec::new(source.platform.target.as_deref());
         let filter_expr = source.filter.as_ref().map_or(Ok(None), |filter| {
-            Some(FilteringExpr::parse(filter.clone(), graph)).transpose()
+            // Test groups are assigned by overrides, so they aren't known here.
+            Some(
+                FilteringExpr::parse_with_filtersets(filter.clone(), graph, filtersets)
+                    .and_then(|expr| expr.check_no_group(filtersets).map(|()| expr)),
+            )
+            .transpose()
         });
 
         match (host_spec, target_spec, filter_expr) {
@@ -405,6 +489,21 @@ impl CompiledProfileScripts<PreBuildPlatform> {
 }
 
 impl CompiledProfileScripts<FinalConfig> {
+    /// Returns the scripts enabled by this rule.
+    pub(crate) fn setup(&self) -> &[ScriptId] {
+        &self.setup
+    }
+
+    /// Returns the target spec.
+    pub(crate) fn target_spec(&self) -> &MaybeTargetSpec {
+        &self.data.target_spec
+    }
+
+    /// Returns the filter expression, if any.
+    pub(crate) fn filter(&self) -> Option<&FilteringExpr> {
+        self.data.expr.as_ref()
+    }
+
     pub(super) fn is_enabled(&self, query: &TestQuery<'_>) -> bool {
         if !self.state.host_eval {
             return false;
@@ -493,6 +592,10 @@ pub(super) struct DeserializedProfileScriptConfig {
 #[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct ScriptConfig {
+    /// Whether this script runs once for the whole run, or around each test it's enabled for.
+    #[serde(default)]
+    pub kind: ScriptKind,
+
     /// The command to run. The first element is the program and the second element is a list
     /// of arguments.
     #[serde(deserialize_with = "deserialize_command")]
@@ -513,6 +616,14 @@ pub struct ScriptConfig {
     /// Whether to capture standard error for this command.
     #[serde(default)]
     pub capture_stderr: bool,
+
+    /// An optional command to run after tests have finished, to clean up after this script.
+    #[serde(default, deserialize_with = "deserialize_teardown")]
+    pub teardown: Option<(String, Vec<String>)>,
+
+    /// Setup scripts that must finish successfully before this one is started.
+    #[serde(default, deserialize_with = "deserialize_script_ids")]
+    pub depends_on: Vec<ScriptId>,
 }
 
 impl ScriptConfig {
@@ -528,11 +639,116 @@ impl ScriptConfig {
         &self.command.1
     }
 
+    /// Returns the name of the teardown program and its arguments, if any.
+    #[inline]
+    pub fn teardown(&self) -> Option<(&str, &[String])> {
+        self.teardown
+            .as_ref()
+            .map(|(program, args)| (program.as_str(), args.as_slice()))
+    }
+
     /// Returns true if at least some output isn't being captured.
     #[inline]
     pub fn no_capture(&self) -> bool {
         !(self.capture_stdout && self.capture_stderr)
     }
+
+    /// Returns the program and arguments to run for `phase`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `phase` is [`ScriptPhase::Teardown`] and there's no teardown command.
+    pub(crate) fn command_for(&self, phase: ScriptPhase) -> (&str, &[String]) {
+        match phase {
+            ScriptPhase::Setup => (self.program(), self.args()),
+            ScriptPhase::Teardown => self
+                .teardown()
+                .expect("teardown is only run for scripts with a teardown command"),
+        }
+    }
+}
+
+/// When a script is run, configured via the `kind` key.
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScriptKind {
+    /// The script's command is run once, before any tests, and its teardown command is run once
+    /// all tests have finished.
+    #[default]
+    Setup,
+
+    /// The script's command is run before each test it's enabled for, and its teardown command is
+    /// run after each such test.
+    Wrapper,
+}
+
+/// Which of its commands a setup script is running.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ScriptPhase {
+    /// The setup command, run before any tests (or before each test, for wrapper scripts).
+    Setup,
+
+    /// The teardown command, run once tests have finished (or after each test, for wrapper
+    /// scripts).
+    Teardown,
+}
+
+/// Checks that the `depends-on` lists of `scripts` only refer to known setup scripts, and don't
+/// form a cycle.
+pub(super) fn check_script_dependencies(
+    scripts: &IndexMap<ScriptId, ScriptConfig>,
+) -> Result<(), ScriptDependencyError> {
+    for (script_id, config) in scripts {
+        for dependency in &config.depends_on {
+            let Some(dependency_config) = scripts.get(dependency) else {
+                return Err(ScriptDependencyError::Unknown {
+                    script: script_id.clone(),
+                    dependency: dependency.clone(),
+                });
+            };
+            if config.kind != ScriptKind::Setup || dependency_config.kind != ScriptKind::Setup {
+                return Err(ScriptDependencyError::NotSetup {
+                    script: script_id.clone(),
+                    dependency: dependency.clone(),
+                });
+            }
+        }
+    }
+
+    // Look for cycles with a depth-first search, tracking the path from the root.
+    fn visit<'a>(
+        script_id: &'a ScriptId,
+        scripts: &'a IndexMap<ScriptId, ScriptConfig>,
+        path: &mut Vec<&'a ScriptId>,
+        done: &mut HashSet<&'a ScriptId>,
+    ) -> Result<(), ScriptDependencyError> {
+        if done.contains(script_id) {
+            return Ok(());
+        }
+        if let Some(pos) = path.iter().position(|id| *id == script_id) {
+            let cycle = path[pos..]
+                .iter()
+                .chain(std::iter::once(&script_id))
+                .map(|id| (*id).clone())
+                .collect();
+            return Err(ScriptDependencyError::Cycle { cycle });
+        }
+
+        path.push(script_id);
+        for dependency in &scripts[script_id].depends_on {
+            visit(dependency, scripts, path, done)?;
+        }
+        path.pop();
+        done.insert(script_id);
+        Ok(())
+    }
+
+    let mut done = HashSet::new();
+    for script_id in scripts.keys() {
+        visit(script_id, scripts, &mut Vec::new(), &mut done)?;
+    }
+
+    Ok(())
 }
 
 fn deserialize_script_ids<'de, D>(deserializer: D) -> Result<Vec<ScriptId>, D::Error>
@@ -570,7 +786,16 @@ where
     deserializer.deserialize_any(ScriptIdVisitor)
 }
 
-fn deserialize_command<'de, D>(deserializer: D) -> Result<(String, Vec<String>), D::Error>
+fn deserialize_teardown<'de, D>(deserializer: D) -> Result<Option<(String, Vec<String>)>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    deserialize_command(deserializer).map(Some)
+}
+
+pub(super) fn deserialize_command<'de, D>(
+    deserializer: D,
+) -> Result<(String, Vec<String>), D::Error>
 where
     D: serde::Deserializer<'de>,
 {
@@ -650,9 +875,11 @@ mod tests {
 
             [script.bar]
             command = ["cargo", "run", "-p", "bar"]
+            teardown = "cargo run -p bar -- --teardown"
             slow-timeout = { period = "60s", terminate-after = 2 }
 
             [script.baz]
+            kind = "wrapper"
             command = "baz"
             slow-timeout = "1s"
             leak-timeout = "1s"
@@ -715,6 +942,8 @@ mod tests {
         let query = TestQuery {
             binary_query: host_binary_query.to_query(),
             test_name: "script1",
+            ignored: false,
+            test_group: None,
         };
         let scripts = SetupScripts::new_with_queries(&profile, std::iter::once(query));
         assert_eq!(scripts.len(), 2, "two scripts should be enabled");
@@ -728,6 +957,42 @@ mod tests {
             "bar",
             "second script should be bar"
         );
+        assert_eq!(
+            scripts
+                .enabled_scripts
+                .get_index(0)
+                .unwrap()
+                .1
+                .config
+                .teardown(),
+            None,
+            "foo has no teardown command"
+        );
+        assert_eq!(
+            scripts
+                .enabled_scripts
+                .get_index(1)
+                .unwrap()
+                .1
+                .config
+                .teardown(),
+            Some((
+                "cargo",
+                &[
+                    "run".to_owned(),
+                    "-p".to_owned(),
+                    "bar".to_owned(),
+                    "--".to_owned(),
+                    "--teardown".to_owned()
+                ][..]
+            )),
+            "bar's teardown command should be parsed"
+        );
+        assert_eq!(
+            scripts.enabled_scripts.get_index(0).unwrap().1.config.kind,
+            ScriptKind::Setup,
+            "scripts are setup scripts by default"
+        );
 
         let target_binary_query = binary_query(
             &graph,
@@ -741,6 +1006,8 @@ mod tests {
         let query = TestQuery {
             binary_query: target_binary_query.to_query(),
             test_name: "script2",
+            ignored: false,
+            test_group: None,
         };
         let scripts = SetupScripts::new_with_queries(&profile, std::iter::once(query));
         assert_eq!(scripts.len(), 1, "one script should be enabled");
@@ -749,11 +1016,18 @@ mod tests {
             "baz",
             "first script should be baz"
         );
+        assert_eq!(
+            scripts.enabled_scripts.get_index(0).unwrap().1.config.kind,
+            ScriptKind::Wrapper,
+            "baz is a wrapper script"
+        );
 
         // This query matches the baz, foo and tool scripts (but note the order).
         let query = TestQuery {
             binary_query: target_binary_query.to_query(),
             test_name: "script3",
+            ignored: false,
+            test_group: None,
         };
         let scripts = SetupScripts::new_with_queries(&profile, std::iter::once(query));
         assert_eq!(scripts.len(), 3, "three scripts should be enabled");
@@ -774,6 +1048,69 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_scripts_depends_on() {
+        let config_contents = indoc! {r#"
+            [[profile.default.scripts]]
+            filter = "test(script1)"
+            setup = ["app", "cache"]
+
+            [script.app]
+            command = "app"
+            depends-on = ["db", "cache"]
+
+            [script.cache]
+            command = "cache"
+
+            [script.db]
+            command = "db"
+            depends-on = "network"
+
+            [script.network]
+            command = "network"
+
+            [script.unused]
+            command = "unused"
+        "#
+        };
+
+        let workspace_dir = tempdir().unwrap();
+        let graph = temp_workspace(workspace_dir.path(), config_contents);
+        let package_id = graph.workspace().iter().next().unwrap().id();
+
+        let nextest_config_result = NextestConfig::from_sources(
+            graph.workspace().root(),
+            &graph,
+            None,
+            &[][..],
+            &btreeset! { ConfigExperimental::SetupScripts },
+        )
+        .expect("config is valid");
+        let profile = nextest_config_result
+            .profile("default")
+            .expect("valid profile name")
+            .apply_build_platforms(&build_platforms());
+
+        let host_binary_query =
+            binary_query(&graph, package_id, "lib", "my-binary", BuildPlatform::Host);
+        let query = TestQuery {
+            binary_query: host_binary_query.to_query(),
+            test_name: "script1",
+            ignored: false,
+            test_group: None,
+        };
+        let scripts = SetupScripts::new_with_queries(&profile, std::iter::once(query));
+
+        // Dependencies are enabled even though no rules mention them, and each script comes after
+        // its dependencies.
+        let order: Vec<_> = scripts
+            .enabled_scripts
+            .keys()
+            .map(|script_id| script_id.as_str())
+            .collect();
+        assert_eq!(order, ["network", "db", "cache", "app"]);
+    }
+
     #[test_case(
         indoc! {r#"
             [script.foo]
@@ -800,6 +1137,68 @@ mod tests {
 
         ; "missing command"
     )]
+    #[test_case(
+        indoc! {r#"
+            [script.foo]
+            command = "my-command"
+            teardown = ""
+        "#},
+        "invalid value: string \"\", expected a Unix shell command or a list of arguments"
+
+        ; "empty teardown command"
+    )]
+    #[test_case(
+        indoc! {r#"
+            [script.foo]
+            kind = "around"
+            command = "my-command"
+        "#},
+        "script.foo.kind: enum ScriptKind does not have variant constructor around"
+
+        ; "unknown kind"
+    )]
+    #[test_case(
+        indoc! {r#"
+            [script.foo]
+            command = "my-command"
+            depends-on = "bar"
+        "#},
+        "script `foo` depends on unknown script `bar`"
+
+        ; "unknown dependency"
+    )]
+    #[test_case(
+        indoc! {r#"
+            [script.foo]
+            command = "my-command"
+            depends-on = "bar"
+
+            [script.bar]
+            kind = "wrapper"
+            command = "my-command"
+        "#},
+        "script `foo` depends on `bar`, but dependencies are only supported between scripts of kind `setup`"
+
+        ; "dependency on wrapper"
+    )]
+    #[test_case(
+        indoc! {r#"
+            [script.foo]
+            command = "my-command"
+            depends-on = "bar"
+
+            [script.bar]
+            command = "my-command"
+            depends-on = ["baz"]
+
+            [script.baz]
+            command = "my-command"
+            depends-on = ["bar"]
+        "#},
+        "scripts depend on each other in a cycle: bar -> baz -> bar"
+
+        ; "dependency cycle"
+    )]
     #[test_case(
         indoc! {r#"
             [script.foo]
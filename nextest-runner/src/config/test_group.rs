@@ -18,6 +18,14 @@ pub enum TestGroup {
 }
 
 impl TestGroup {
+    /// Returns the name of this test group, as matched by `group()` filter expressions.
+    pub fn as_str(&self) -> &str {
+        match self {
+            TestGroup::Global => "@global",
+            TestGroup::Custom(group) => group.as_str(),
+        }
+    }
+
     pub(crate) fn make_all_groups(
         custom_groups: impl IntoIterator<Item = CustomTestGroup>,
     ) -> impl Iterator<Item = Self> {
@@ -60,10 +68,7 @@ impl FromStr for TestGroup {
 
 impl fmt::Display for TestGroup {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            TestGroup::Global => write!(f, "@global"),
-            TestGroup::Custom(group) => write!(f, "{}", group.as_str()),
-        }
+        f.write_str(self.as_str())
     }
 }
 
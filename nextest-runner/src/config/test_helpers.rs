@@ -17,7 +17,7 @@ use serde::Deserialize;
 use std::{io::Write, path::PathBuf, process::Command};
 use target_spec::{Platform, TargetFeatures};
 
-pub(super) fn temp_workspace(temp_dir: &Utf8Path, config_contents: &str) -> PackageGraph {
+pub(crate) fn temp_workspace(temp_dir: &Utf8Path, config_contents: &str) -> PackageGraph {
     Command::new(cargo_path())
         .args(["init", "--lib", "--name=test-package", "--vcs=none"])
         .current_dir(temp_dir)
@@ -83,7 +83,7 @@ pub(super) fn binary_query<'a>(
     }
 }
 
-pub(super) fn build_platforms() -> BuildPlatforms {
+pub(crate) fn build_platforms() -> BuildPlatforms {
     BuildPlatforms::new_with_host(
         Platform::new("x86_64-unknown-linux-gnu", TargetFeatures::Unknown).unwrap(),
         Some(TargetTriple {
@@ -0,0 +1,79 @@
+// Copyright (c) The nextest Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use serde::Deserialize;
+
+/// A command run to find out where a test is stuck, just before it's terminated for timing out.
+///
+/// Returned by [`TestSettings::timeout_diagnostics`](super::TestSettings::timeout_diagnostics).
+/// `{pid}` in the command is replaced with the process ID of the test.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TimeoutDiagnostics {
+    program: String,
+    args: Vec<String>,
+}
+
+impl TimeoutDiagnostics {
+    /// Returns the program, as specified in the configuration.
+    pub fn program(&self) -> &str {
+        &self.program
+    }
+
+    /// Returns the arguments, as specified in the configuration.
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    /// Returns the program and arguments to run for the test process `pid`.
+    pub(crate) fn command_line(&self, pid: u32) -> Vec<String> {
+        let pid = pid.to_string();
+        std::iter::once(&self.program)
+            .chain(&self.args)
+            .map(|arg| arg.replace("{pid}", &pid))
+            .collect()
+    }
+}
+
+impl<'de> Deserialize<'de> for TimeoutDiagnostics {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let (program, args) = super::deserialize_command(deserializer)?;
+        Ok(Self { program, args })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[derive(Debug, Deserialize)]
+    struct Wrapper {
+        timeout_diagnostics: TimeoutDiagnostics,
+    }
+
+    #[test_case(
+        r#"timeout_diagnostics = "eu-stack -p {pid}""#,
+        &["eu-stack", "-p", "1234"]
+
+        ; "string"
+    )]
+    #[test_case(
+        r#"timeout_diagnostics = ["gdb", "-p", "{pid}", "-batch", "-ex", "thread apply all bt"]"#,
+        &["gdb", "-p", "1234", "-batch", "-ex", "thread apply all bt"]
+
+        ; "list"
+    )]
+    #[test_case(
+        r#"timeout_diagnostics = "sh -c 'cat /proc/{pid}/stack > stack-{pid}.txt'""#,
+        &["sh", "-c", "cat /proc/1234/stack > stack-1234.txt"]
+
+        ; "pid within an argument"
+    )]
+    fn test_command_line(input: &str, expected: &[&str]) {
+        let wrapper: Wrapper = toml::from_str(input).expect("valid command");
+        assert_eq!(wrapper.timeout_diagnostics.command_line(1234), expected);
+    }
+}
@@ -236,18 +236,26 @@ mod tests {
         let test_foo_query = TestQuery {
             binary_query: binary_query.to_query(),
             test_name: "test_foo",
+            ignored: false,
+            test_group: None,
         };
         let test_bar_query = TestQuery {
             binary_query: binary_query.to_query(),
             test_name: "test_bar",
+            ignored: false,
+            test_group: None,
         };
         let test_baz_query = TestQuery {
             binary_query: binary_query.to_query(),
             test_name: "test_baz",
+            ignored: false,
+            test_group: None,
         };
         let test_quux_query = TestQuery {
             binary_query: binary_query.to_query(),
             test_name: "test_quux",
+            ignored: false,
+            test_group: None,
         };
 
         assert_eq!(
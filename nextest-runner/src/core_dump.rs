@@ -0,0 +1,258 @@
+// Copyright (c) The nextest Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Collecting core dumps from tests that crash.
+//!
+//! Tests with the `collect-core-dumps` setting are run with core dumps enabled. If such a test
+//! dumps core, the core file is found through the system's core file pattern, and `gdb` or `lldb`
+//! (whichever is available) is used to get a backtrace of all threads from it. Core files are only
+//! collected if the pattern includes the pid, so that other processes' core files are never
+//! picked up.
+//!
+//! Core dumps are currently only collected on Linux and macOS.
+
+use camino::Utf8Path;
+use std::{process::Stdio, time::Duration};
+use tokio::process::Command;
+
+pub(crate) use imp::{enable, find_core_file};
+
+/// How long to wait for a debugger to print out a backtrace.
+const DEBUGGER_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Gets a backtrace of all threads from a core file, using gdb or lldb.
+///
+/// Returns `None` if neither debugger is available, or if the debugger failed.
+pub(crate) async fn backtrace(exe: &Utf8Path, core: &Utf8Path) -> Option<String> {
+    let mut gdb = Command::new("gdb");
+    gdb.args(["--batch", "--nx", "--quiet", "-ex", "thread apply all bt"])
+        .arg(exe)
+        .arg(core);
+    let mut lldb = Command::new("lldb");
+    lldb.args(["--batch", "--no-lldbinit", "--core"])
+        .arg(core)
+        .arg(exe)
+        .args(["--one-line", "thread backtrace all"]);
+
+    for mut debugger in [gdb, lldb] {
+        debugger
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true);
+        let program = debugger.as_std().get_program().to_owned();
+        let output = match debugger.spawn() {
+            Ok(child) => tokio::time::timeout(DEBUGGER_TIMEOUT, child.wait_with_output()).await,
+            Err(error) => {
+                log::debug!("error running {program:?}, trying the next debugger: {error}");
+                continue;
+            }
+        };
+        match output {
+            Ok(Ok(output)) if output.status.success() && !output.stdout.is_empty() => {
+                return Some(String::from_utf8_lossy(&output.stdout).into_owned());
+            }
+            Ok(Ok(output)) => {
+                log::debug!("{program:?} failed to get a backtrace: {}", output.status);
+            }
+            Ok(Err(error)) => log::debug!("error waiting for {program:?}: {error}"),
+            Err(_) => log::debug!("{program:?} timed out getting a backtrace"),
+        }
+        // The debugger was found, so don't try the next one.
+        return None;
+    }
+
+    None
+}
+
+#[cfg(unix)]
+mod imp {
+    use camino::{Utf8Path, Utf8PathBuf};
+    use globset::Glob;
+    use std::{
+        os::unix::process::{CommandExt, ExitStatusExt},
+        process::{Command, ExitStatus},
+        sync::Once,
+    };
+
+    /// Raises the core file size limit for `cmd` as far as it goes.
+    pub(crate) fn enable(cmd: &mut Command) {
+        // SAFETY: getrlimit and setrlimit are async-signal-safe, and nothing is allocated in the
+        // closure.
+        unsafe {
+            cmd.pre_exec(|| {
+                let mut rlimit: libc::rlimit = std::mem::zeroed();
+                if libc::getrlimit(libc::RLIMIT_CORE, &mut rlimit) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                // Only the soft limit can be raised without privileges.
+                rlimit.rlim_cur = rlimit.rlim_max;
+                if libc::setrlimit(libc::RLIMIT_CORE, &rlimit) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+    }
+
+    /// Returns the core file written out by the process `pid`, if it dumped core.
+    ///
+    /// `cwd` is the working directory of the process, which relative core file patterns are
+    /// resolved against.
+    pub(crate) fn find_core_file(
+        exit_status: ExitStatus,
+        pid: u32,
+        cwd: &Utf8Path,
+    ) -> Option<Utf8PathBuf> {
+        if !exit_status.core_dumped() {
+            return None;
+        }
+        let (pattern, uses_pid) = core_pattern()?;
+        let (mut glob, has_pid) = expand_core_pattern(&pattern, pid);
+        if uses_pid && !has_pid {
+            glob.push_str(&format!(".{pid}"));
+        } else if !has_pid {
+            // Without the pid, there's no way to tell this process's core file apart from ones
+            // written out by other tests or processes.
+            warn_cannot_collect(&format!(
+                "the core file pattern `{pattern}` doesn't include the pid (%p)"
+            ));
+            return None;
+        }
+        let glob = cwd.join(glob);
+
+        // Only the file name may contain wildcards, since searching through directories could be
+        // very slow.
+        let (dir, file_name) = (glob.parent()?, glob.file_name()?);
+        if dir.as_str().contains(['*', '?', '[', '{']) {
+            warn_cannot_collect(&format!(
+                "the core file pattern `{pattern}` has wildcards in its directory"
+            ));
+            return None;
+        }
+        let matcher = Glob::new(file_name).ok()?.compile_matcher();
+
+        // If several files match, the newest one is most likely the one for this process.
+        dir.read_dir_utf8()
+            .ok()?
+            .flatten()
+            .filter(|entry| matcher.is_match(entry.file_name()))
+            .filter_map(|entry| {
+                let modified = entry.metadata().ok()?.modified().ok()?;
+                Some((modified, entry.into_path()))
+            })
+            .max_by_key(|(modified, _)| *modified)
+            .map(|(_, path)| path)
+    }
+
+    /// Warns that core files can't be collected, because of `reason`.
+    ///
+    /// This is only done once, since the reason is the same for every test.
+    fn warn_cannot_collect(reason: &str) {
+        static WARN_ONCE: Once = Once::new();
+        WARN_ONCE.call_once(|| {
+            log::warn!(
+                "{reason}, so core dumps can't be collected for tests with \
+                 `collect-core-dumps = true` (see https://nexte.st/book/per-test-overrides#core-dumps)"
+            );
+        });
+    }
+
+    /// Returns the system's core file pattern, along with whether the pid is appended to it.
+    #[cfg(target_os = "linux")]
+    fn core_pattern() -> Option<(String, bool)> {
+        let pattern = match std::fs::read_to_string("/proc/sys/kernel/core_pattern") {
+            Ok(pattern) => pattern,
+            Err(error) => {
+                warn_cannot_collect(&format!("error reading the core file pattern: {error}"));
+                return None;
+            }
+        };
+        let pattern = pattern.trim_end();
+        if pattern.starts_with('|') {
+            // Core dumps are handled by a program like systemd-coredump or apport.
+            warn_cannot_collect(&format!("core dumps are piped to `{pattern}`"));
+            return None;
+        }
+        let uses_pid = std::fs::read_to_string("/proc/sys/kernel/core_uses_pid")
+            .is_ok_and(|uses_pid| uses_pid.trim() == "1");
+        let pattern = if pattern.is_empty() { "core" } else { pattern };
+        Some((pattern.to_owned(), uses_pid))
+    }
+
+    #[cfg(target_vendor = "apple")]
+    fn core_pattern() -> Option<(String, bool)> {
+        // This is the default for the kern.corefile sysctl.
+        Some(("/cores/core.%P".to_owned(), false))
+    }
+
+    #[cfg(not(any(target_os = "linux", target_vendor = "apple")))]
+    fn core_pattern() -> Option<(String, bool)> {
+        warn_cannot_collect("core files can only be found on Linux and macOS");
+        None
+    }
+
+    /// Turns a core file pattern into a glob. Specifiers other than the pid are turned into
+    /// wildcards.
+    ///
+    /// Also returns whether the pattern includes the pid.
+    fn expand_core_pattern(pattern: &str, pid: u32) -> (String, bool) {
+        let mut glob = String::new();
+        let mut has_pid = false;
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                glob.push_str(&globset::escape(c.encode_utf8(&mut [0; 4])));
+                continue;
+            }
+            match chars.next() {
+                Some('p' | 'P') => {
+                    glob.push_str(&pid.to_string());
+                    has_pid = true;
+                }
+                Some('%') => glob.push('%'),
+                // A trailing % is dropped by the kernel.
+                None => {}
+                Some(_) if glob.ends_with('*') => {}
+                Some(_) => glob.push('*'),
+            }
+        }
+        (glob, has_pid)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use test_case::test_case;
+
+        #[test_case("core", "core", false; "plain")]
+        #[test_case("core.%p", "core.1234", true; "pid")]
+        #[test_case("/var/crash/%e.%P.%t", "/var/crash/*.1234.*", true; "other specifiers")]
+        #[test_case("/var/crash/%e.%t", "/var/crash/*.*", false; "no pid")]
+        #[test_case("core-%e%s", "core-*", false; "adjacent specifiers")]
+        #[test_case("100%%-[core]", "100%-[[]core[]]", false; "escaped")]
+        #[test_case("core.%%p", "core.%p", false; "escaped pid")]
+        fn test_expand_core_pattern(pattern: &str, expected: &str, expected_has_pid: bool) {
+            assert_eq!(
+                expand_core_pattern(pattern, 1234),
+                (expected.to_owned(), expected_has_pid)
+            );
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod imp {
+    use camino::{Utf8Path, Utf8PathBuf};
+    use std::process::{Command, ExitStatus};
+
+    pub(crate) fn enable(_cmd: &mut Command) {}
+
+    pub(crate) fn find_core_file(
+        _exit_status: ExitStatus,
+        _pid: u32,
+        _cwd: &Utf8Path,
+    ) -> Option<Utf8PathBuf> {
+        None
+    }
+}
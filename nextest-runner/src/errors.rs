@@ -14,7 +14,7 @@ use crate::{
 use camino::{FromPathBufError, Utf8Path, Utf8PathBuf};
 use config::ConfigError;
 use itertools::Itertools;
-use nextest_filtering::errors::FilterExpressionParseErrors;
+use nextest_filtering::errors::{FilterExpressionParseErrors, NamedFiltersetParseErrors};
 use nextest_metadata::RustBinaryId;
 use smol_str::SmolStr;
 use std::{borrow::Cow, collections::BTreeSet, env::JoinPathsError, fmt, process::ExitStatus};
@@ -93,6 +93,9 @@ pub enum ConfigParseErrorKind {
     /// Errors occurred while parsing compiled data.
     #[error("error parsing compiled data (destructure this variant for more details)")]
     CompiledDataParseError(Vec<ConfigParseCompiledDataError>),
+    /// Errors occurred while parsing named filtersets.
+    #[error("error parsing named filtersets (destructure this variant for more details)")]
+    FiltersetParseErrors(Vec<NamedFiltersetParseErrors>),
     /// An invalid set of test groups was defined by the user.
     #[error("invalid test groups defined: {}\n(test groups cannot start with '@tool:' unless specified by a tool)", .0.iter().join(", "))]
     InvalidTestGroupsDefined(BTreeSet<CustomTestGroup>),
@@ -154,6 +157,13 @@ pub enum ConfigParseErrorKind {
         /// The feature that was not enabled.
         feature: ConfigExperimental,
     },
+    /// The dependencies between setup scripts were invalid.
+    #[error("invalid setup script dependencies")]
+    ScriptDependencies(#[source] ScriptDependencyError),
+
+    /// The profiles that profiles inherit from were invalid.
+    #[error("invalid profile inheritance")]
+    ProfileInheritance(#[source] ProfileInheritanceError),
 }
 
 /// An error that occurred while parsing config overrides or setup scripts.
@@ -216,14 +226,60 @@ impl ConfigParseCompiledDataError {
 #[derive(Debug, Error)]
 #[non_exhaustive]
 pub(crate) enum RunTestError {
+    #[error("error creating temporary directory for test")]
+    TmpDir(#[source] std::io::Error),
+
     #[error("error spawning test process")]
     Spawn(#[source] std::io::Error),
 
+    #[error(
+        "error spawning isolated test process \
+         (isolation requires unprivileged user namespaces to be enabled)"
+    )]
+    SpawnIsolated(#[source] std::io::Error),
+
+    #[error("error setting up isolation for test")]
+    Isolation(#[from] IsolationError),
+
     #[error("error waiting for setup script to exit")]
     Wait(#[source] std::io::Error),
 
     #[error("error collecting test output")]
     CollectOutput(#[from] CollectTestOutputError),
+
+    #[error("wrapper script `{script_id}` failed, so the test was not run")]
+    WrapperScriptSetup { script_id: ScriptId },
+
+    #[error("wrapper script `{script_id}` failed after the test was run")]
+    WrapperScriptTeardown { script_id: ScriptId },
+}
+
+/// An error that occurred while setting up an isolated sandbox for a test.
+///
+/// Internal error type.
+#[derive(Debug, Error)]
+pub(crate) enum IsolationError {
+    #[error(
+        "`{path}` is within /tmp, which is replaced with an empty directory for isolated tests"
+    )]
+    PathInTmp { path: Utf8PathBuf },
+
+    #[error("error reading mount flags for `{path}`")]
+    MountFlags {
+        path: Utf8PathBuf,
+        #[source]
+        error: std::io::Error,
+    },
+
+    #[error("error creating pipe to report isolation errors")]
+    Pipe(#[source] std::io::Error),
+
+    #[error("error {step} in isolated sandbox")]
+    Setup {
+        step: &'static str,
+        #[source]
+        error: std::io::Error,
+    },
 }
 
 /// An error that occurred while setting up or running a setup script.
@@ -300,6 +356,74 @@ pub struct UnknownTestGroupError {
     pub name: TestGroup,
 }
 
+/// An invalid `depends-on` list was specified for a setup script.
+///
+/// Part of [`ConfigParseErrorKind::ScriptDependencies`].
+#[derive(Clone, Debug, Eq, PartialEq, Error)]
+#[non_exhaustive]
+pub enum ScriptDependencyError {
+    /// A script depends on a script that isn't defined.
+    #[error("script `{script}` depends on unknown script `{dependency}`")]
+    Unknown {
+        /// The script with the dependency.
+        script: ScriptId,
+
+        /// The unknown script.
+        dependency: ScriptId,
+    },
+
+    /// A dependency was specified for or on a wrapper script.
+    #[error(
+        "script `{script}` depends on `{dependency}`, but dependencies are only supported \
+         between scripts of kind `setup`"
+    )]
+    NotSetup {
+        /// The script with the dependency.
+        script: ScriptId,
+
+        /// The script depended on.
+        dependency: ScriptId,
+    },
+
+    /// Scripts depend on each other in a cycle.
+    #[error("scripts depend on each other in a cycle: {}", .cycle.iter().join(" -> "))]
+    Cycle {
+        /// The scripts in the cycle, starting and ending with the same script.
+        cycle: Vec<ScriptId>,
+    },
+}
+
+/// An invalid `inherits` key was specified for a profile.
+///
+/// Part of [`ConfigParseErrorKind::ProfileInheritance`].
+#[derive(Clone, Debug, Eq, PartialEq, Error)]
+#[non_exhaustive]
+pub enum ProfileInheritanceError {
+    /// The default profile inherits from another profile.
+    #[error("the default profile cannot inherit from another profile (inherits `{inherits}`)")]
+    Default {
+        /// The profile that the default profile inherits from.
+        inherits: String,
+    },
+
+    /// A profile inherits from a profile that isn't defined.
+    #[error("profile `{profile}` inherits from unknown profile `{inherits}`")]
+    Unknown {
+        /// The profile with the `inherits` key.
+        profile: String,
+
+        /// The unknown profile.
+        inherits: String,
+    },
+
+    /// Profiles inherit from each other in a cycle.
+    #[error("profiles inherit from each other in a cycle: {}", .cycle.iter().join(" -> "))]
+    Cycle {
+        /// The profiles in the cycle, starting and ending with the same profile.
+        cycle: Vec<String>,
+    },
+}
+
 /// An unknown script was specified in the config.
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[non_exhaustive]
@@ -419,6 +543,26 @@ impl TestThreadsParseError {
     }
 }
 
+/// An error that occurs while parsing a [`StressCondition`](crate::runner::StressCondition) input.
+#[derive(Clone, Debug, Error)]
+#[error(
+    "stress condition `{input}` must be either a number of iterations or a duration \
+     (e.g. \"100\" or \"30s\"): {message}"
+)]
+pub struct StressConditionParseError {
+    input: String,
+    message: String,
+}
+
+impl StressConditionParseError {
+    pub(crate) fn new(input: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            input: input.into(),
+            message: message.into(),
+        }
+    }
+}
+
 /// An error that occurs while parsing a
 /// [`PartitionerBuilder`](crate::partition::PartitionerBuilder) input.
 #[derive(Clone, Debug, Error)]
@@ -775,6 +919,20 @@ pub enum TestRunnerBuildError {
     SignalHandlerSetupError(#[from] SignalHandlerSetupError),
 }
 
+/// An error that occurs while creating a
+/// [`WorkspaceWatcher`](crate::watch::WorkspaceWatcher).
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum WatcherBuildError {
+    /// An error occurred while creating the Tokio runtime.
+    #[error("error creating Tokio runtime")]
+    TokioRuntimeCreate(#[source] std::io::Error),
+
+    /// An error occurred while setting up signals.
+    #[error("error setting up signals")]
+    SignalHandlerSetupError(#[from] SignalHandlerSetupError),
+}
+
 /// Represents an unknown archive format.
 ///
 /// Returned by [`ArchiveFormat::autodetect`].
@@ -1002,6 +1160,48 @@ pub enum WriteEventError {
         #[source]
         error: quick_junit::SerializeError,
     },
+
+    /// An error occurred while recording the outcomes of the run.
+    #[error("error recording run outcomes")]
+    RunStore(#[from] RunStoreError),
+}
+
+/// An error that occurs while reading or writing data in the [run store](crate::run_store).
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum RunStoreError {
+    /// An error occurred while reading a run store file.
+    #[error("error reading `{path}`")]
+    Read {
+        /// The path that was read.
+        path: Utf8PathBuf,
+
+        /// The underlying IO error.
+        #[source]
+        error: std::io::Error,
+    },
+
+    /// An error occurred while parsing a run store file.
+    #[error("error parsing `{path}`")]
+    Parse {
+        /// The path that was parsed.
+        path: Utf8PathBuf,
+
+        /// The underlying JSON error.
+        #[source]
+        error: serde_json::Error,
+    },
+
+    /// An error occurred while writing a run store file.
+    #[error("error writing `{path}`")]
+    Write {
+        /// The path that was written to.
+        path: Utf8PathBuf,
+
+        /// The underlying IO error.
+        #[source]
+        error: std::io::Error,
+    },
 }
 
 /// An error occurred while constructing a [`CargoConfigs`](crate::cargo_config::CargoConfigs)
@@ -1207,6 +1407,20 @@ pub enum ShowTestGroupsError {
     },
 }
 
+/// An error occurred while showing settings for a test.
+#[derive(Debug, Error)]
+pub enum ShowTestSettingsError {
+    /// No tests with the given name were found.
+    #[error(
+        "no tests named `{name}` found\n\
+         (pass in the name of a test as shown by `cargo nextest list`)"
+    )]
+    NoMatchingTests {
+        /// The name that was passed in.
+        name: String,
+    },
+}
+
 #[cfg(feature = "self-update")]
 mod self_update_errors {
     use super::*;
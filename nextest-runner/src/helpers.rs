@@ -185,6 +185,24 @@ pub(crate) fn format_duration(duration: Duration) -> String {
     }
 }
 
+/// Formats a number of bytes with a binary unit, e.g. `12.5 MiB`.
+pub(crate) fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = UNITS[0];
+    for next_unit in &UNITS[1..] {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = next_unit;
+    }
+    format!("{value:.1} {unit}")
+}
+
 /// Extract the abort status from an exit status.
 pub(crate) fn extract_abort_status(exit_status: ExitStatus) -> Option<AbortStatus> {
     cfg_if::cfg_if! {
@@ -0,0 +1,481 @@
+// Copyright (c) The nextest Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Isolating tests from the network and the filesystem.
+//!
+//! Tests with the `isolation` setting are run in their own user, mount and network namespaces.
+//! Within them, only the loopback network interface is available, `/tmp` is an empty tmpfs, and
+//! the workspace is mounted read-only, apart from the target directory. Tests don't keep any
+//! capabilities within the namespaces, even if nextest is run as root, so they can't undo any of
+//! this.
+//!
+//! Isolation is only supported on Linux. On other platforms, a warning is printed the first time a
+//! test with isolation is run, and the test is run without it.
+
+pub(crate) use imp::isolate;
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use crate::errors::{IsolationError, RunTestError};
+    use camino::Utf8Path;
+    use libc::{c_int, c_ulong};
+    use std::{
+        ffi::CString,
+        fs::File,
+        io::{self, Read},
+        os::{
+            fd::{AsRawFd, FromRawFd, OwnedFd},
+            unix::process::CommandExt,
+        },
+        process::Command,
+    };
+
+    // Not defined by libc on musl.
+    const ST_RELATIME: c_ulong = 4096;
+
+    // From linux/securebits.h.
+    const SECBIT_NOROOT: c_int = 1 << 0;
+    const SECBIT_NOROOT_LOCKED: c_int = 1 << 1;
+
+    /// The step of setting up the sandbox that failed, as reported back to nextest.
+    #[derive(Clone, Copy)]
+    #[repr(u8)]
+    enum Step {
+        Unshare = 1,
+        MapIds,
+        Mount,
+        DropCapabilities,
+        Loopback,
+    }
+
+    impl Step {
+        fn from_u8(step: u8) -> Option<Self> {
+            [
+                Self::Unshare,
+                Self::MapIds,
+                Self::Mount,
+                Self::DropCapabilities,
+                Self::Loopback,
+            ]
+            .into_iter()
+            .find(|s| *s as u8 == step)
+        }
+
+        fn description(self) -> &'static str {
+            match self {
+                Self::Unshare => "creating namespaces",
+                Self::MapIds => "mapping user and group IDs",
+                Self::Mount => "mounting filesystems",
+                Self::DropCapabilities => "dropping capabilities",
+                Self::Loopback => "bringing up the loopback interface",
+            }
+        }
+    }
+
+    /// A command set up to be run in a sandbox by [`isolate`].
+    pub(crate) struct Sandbox {
+        // If setting up the sandbox fails, the step that failed is written here.
+        failed_step: File,
+    }
+
+    impl Sandbox {
+        /// Converts an error from spawning the command into a [`RunTestError`], pointing out
+        /// whether it happened while setting up the sandbox.
+        pub(crate) fn spawn_error(&self, error: io::Error) -> RunTestError {
+            let mut step = [0];
+            // The read end is nonblocking, so this only returns the step if one was written out
+            // before the command failed to spawn.
+            match (&self.failed_step).read(&mut step).ok().and_then(|n| {
+                (n == 1).then_some(())?;
+                Step::from_u8(step[0])
+            }) {
+                Some(Step::Unshare) => RunTestError::SpawnIsolated(error),
+                Some(step) => RunTestError::Isolation(IsolationError::Setup {
+                    step: step.description(),
+                    error,
+                }),
+                None => RunTestError::Spawn(error),
+            }
+        }
+    }
+
+    /// Sets up `cmd`, which runs `program`, to be run in an isolated sandbox.
+    pub(crate) fn isolate(
+        cmd: &mut Command,
+        program: &Utf8Path,
+        workspace_root: &Utf8Path,
+        target_dir: &Utf8Path,
+    ) -> Result<Sandbox, IsolationError> {
+        // A fresh /tmp would hide anything already in it, including the test binary.
+        for path in [program, workspace_root, target_dir] {
+            if path.starts_with("/tmp") {
+                return Err(IsolationError::PathInTmp {
+                    path: path.to_owned(),
+                });
+            }
+        }
+
+        // Map the current user and group to themselves in the new user namespace.
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+        let uid_map = format!("{uid} {uid} 1");
+        let gid_map = format!("{gid} {gid} 1");
+        let workspace_mount = BindMount::new(workspace_root, true)?;
+        // Tests can still write to the target directory, e.g. to CARGO_TARGET_TMPDIR.
+        let target_mount = if target_dir.starts_with(workspace_root) {
+            Some(BindMount::new(target_dir, false)?)
+        } else {
+            None
+        };
+
+        let (failed_step_read, failed_step_write) = pipe()?;
+
+        // SAFETY: everything in the closure is async-signal-safe, and nothing is allocated in it.
+        unsafe {
+            cmd.pre_exec(move || {
+                let res = (|| {
+                    check(libc::unshare(
+                        libc::CLONE_NEWUSER | libc::CLONE_NEWNS | libc::CLONE_NEWNET,
+                    ))
+                    .map_err(|error| (Step::Unshare, error))?;
+
+                    (|| {
+                        write_file(b"/proc/self/uid_map\0", uid_map.as_bytes())?;
+                        // Writing to gid_map is only allowed once setgroups is denied.
+                        write_file(b"/proc/self/setgroups\0", b"deny")?;
+                        write_file(b"/proc/self/gid_map\0", gid_map.as_bytes())
+                    })()
+                    .map_err(|error| (Step::MapIds, error))?;
+
+                    (|| {
+                        // Make sure none of the mounts below leak out of the namespace.
+                        mount(None, b"/\0", None, libc::MS_REC | libc::MS_PRIVATE)?;
+                        mount(
+                            Some(b"tmpfs\0"),
+                            b"/tmp\0",
+                            Some(b"tmpfs\0"),
+                            libc::MS_NOSUID | libc::MS_NODEV,
+                        )?;
+                        workspace_mount.mount()?;
+                        if let Some(target_mount) = &target_mount {
+                            target_mount.mount()?;
+                        }
+                        Ok(())
+                    })()
+                    .map_err(|error| (Step::Mount, error))?;
+
+                    bring_up_loopback().map_err(|error| (Step::Loopback, error))?;
+
+                    drop_capabilities().map_err(|error| (Step::DropCapabilities, error))
+                })();
+
+                res.map_err(|(step, error)| {
+                    libc::write(
+                        failed_step_write.as_raw_fd(),
+                        [step as u8].as_ptr().cast(),
+                        1,
+                    );
+                    error
+                })
+            })
+        };
+
+        Ok(Sandbox {
+            failed_step: File::from(failed_step_read),
+        })
+    }
+
+    /// Makes sure the test doesn't get any capabilities within the namespaces once it's executed.
+    ///
+    /// The process has all capabilities within its new user namespace until then, and if nextest
+    /// is run as root, it's mapped to root within the namespace, which would normally give the
+    /// test all capabilities again. That would let it undo the sandbox, for example by remounting
+    /// the workspace read-write.
+    fn drop_capabilities() -> io::Result<()> {
+        unsafe {
+            // Don't give root capabilities when it runs a program, and don't allow that to be
+            // changed back.
+            check(libc::prctl(
+                libc::PR_SET_SECUREBITS,
+                (SECBIT_NOROOT | SECBIT_NOROOT_LOCKED) as c_ulong,
+            ))?;
+            check(libc::prctl(
+                libc::PR_CAP_AMBIENT,
+                libc::PR_CAP_AMBIENT_CLEAR_ALL as c_ulong,
+                0 as c_ulong,
+                0 as c_ulong,
+                0 as c_ulong,
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Creates a pipe whose read end is nonblocking.
+    fn pipe() -> Result<(OwnedFd, OwnedFd), IsolationError> {
+        let mut fds = [0; 2];
+        check(unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) })
+            .and_then(|_| {
+                // SAFETY: pipe2 just returned these file descriptors.
+                let fds = unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+                check(unsafe { libc::fcntl(fds.0.as_raw_fd(), libc::F_SETFL, libc::O_NONBLOCK) })?;
+                Ok(fds)
+            })
+            .map_err(IsolationError::Pipe)
+    }
+
+    /// A directory that's bind-mounted onto itself, so that it can be made read-only or
+    /// read-write.
+    struct BindMount {
+        path: CString,
+        remount_flags: c_ulong,
+    }
+
+    impl BindMount {
+        fn new(path: &Utf8Path, read_only: bool) -> Result<Self, IsolationError> {
+            let c_path = CString::new(path.as_str()).expect("paths don't contain nul bytes");
+            let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+            if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+                return Err(IsolationError::MountFlags {
+                    path: path.to_owned(),
+                    error: io::Error::last_os_error(),
+                });
+            }
+
+            // Within a user namespace, flags inherited from the parent namespace are locked and
+            // must be preserved while remounting.
+            let mut remount_flags = libc::MS_BIND | libc::MS_REMOUNT;
+            for (st_flag, ms_flag) in [
+                (libc::ST_NOSUID, libc::MS_NOSUID),
+                (libc::ST_NODEV, libc::MS_NODEV),
+                (libc::ST_NOEXEC, libc::MS_NOEXEC),
+                (libc::ST_NOATIME, libc::MS_NOATIME),
+                (libc::ST_NODIRATIME, libc::MS_NODIRATIME),
+            ] {
+                if stat.f_flag & st_flag != 0 {
+                    remount_flags |= ms_flag;
+                }
+            }
+            // Remounting defaults to relatime, so strictatime must be asked for explicitly.
+            if stat.f_flag & (ST_RELATIME | libc::ST_NOATIME) == 0 {
+                remount_flags |= libc::MS_STRICTATIME;
+            }
+            if read_only {
+                remount_flags |= libc::MS_RDONLY;
+            }
+
+            Ok(Self {
+                path: c_path,
+                remount_flags,
+            })
+        }
+
+        fn mount(&self) -> io::Result<()> {
+            let path = self.path.as_bytes_with_nul();
+            mount(Some(path), path, None, libc::MS_BIND | libc::MS_REC)?;
+            mount(None, path, None, self.remount_flags)
+        }
+    }
+
+    /// Brings up the loopback interface, so that tests can still talk to servers on localhost.
+    fn bring_up_loopback() -> io::Result<()> {
+        let fd = check(unsafe {
+            libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0)
+        })?;
+        let mut req: libc::ifreq = unsafe { std::mem::zeroed() };
+        req.ifr_name[0] = b'l' as libc::c_char;
+        req.ifr_name[1] = b'o' as libc::c_char;
+
+        let res = unsafe {
+            check(libc::ioctl(fd, libc::SIOCGIFFLAGS as _, &mut req)).and_then(|_| {
+                req.ifr_ifru.ifru_flags |= libc::IFF_UP as libc::c_short;
+                check(libc::ioctl(fd, libc::SIOCSIFFLAGS as _, &req))
+            })
+        };
+        unsafe { libc::close(fd) };
+        res.map(|_| ())
+    }
+
+    fn write_file(path: &[u8], contents: &[u8]) -> io::Result<()> {
+        let fd =
+            check(unsafe { libc::open(path.as_ptr().cast(), libc::O_WRONLY | libc::O_CLOEXEC) })?;
+        let res = unsafe { libc::write(fd, contents.as_ptr().cast(), contents.len()) };
+        unsafe { libc::close(fd) };
+        if res < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn mount(
+        source: Option<&[u8]>,
+        target: &[u8],
+        fstype: Option<&[u8]>,
+        flags: c_ulong,
+    ) -> io::Result<()> {
+        let as_ptr = |s: Option<&[u8]>| s.map_or(std::ptr::null(), |s| s.as_ptr().cast());
+        check(unsafe {
+            libc::mount(
+                as_ptr(source),
+                target.as_ptr().cast(),
+                as_ptr(fstype),
+                flags,
+                std::ptr::null(),
+            )
+        })
+        .map(|_| ())
+    }
+
+    fn check(ret: c_int) -> io::Result<c_int> {
+        if ret == -1 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(ret)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::process::Output;
+
+        /// Runs `script` with bash in an isolated sandbox, or returns None if unprivileged user
+        /// namespaces aren't available.
+        fn run_isolated(script: &str, workspace_root: &Utf8Path) -> Option<Output> {
+            let mut cmd = Command::new("bash");
+            cmd.args(["-c", script]);
+            let sandbox = isolate(
+                &mut cmd,
+                Utf8Path::new("bash"),
+                workspace_root,
+                &workspace_root.join("target"),
+            )
+            .unwrap();
+            match cmd.output() {
+                Ok(output) => Some(output),
+                Err(error) => match sandbox.spawn_error(error) {
+                    RunTestError::SpawnIsolated(error) => {
+                        eprintln!("skipping test, unable to spawn isolated process: {error}");
+                        None
+                    }
+                    error => panic!("error spawning isolated process: {error:?}"),
+                },
+            }
+        }
+
+        #[test]
+        fn test_isolate() {
+            // The workspace can't be within /tmp, which is usually where the system temporary
+            // directory is.
+            let temp_dir = camino::Utf8PathBuf::try_from(std::env::temp_dir()).unwrap();
+            let temp_dir = if temp_dir.starts_with("/tmp") {
+                "/var/tmp".into()
+            } else {
+                temp_dir
+            };
+            let workspace = camino_tempfile::Builder::new()
+                .prefix("isolation-test-")
+                .tempdir_in(temp_dir)
+                .unwrap();
+            let workspace_root = workspace.path();
+            std::fs::create_dir(workspace_root.join("target")).unwrap();
+
+            let Some(output) = run_isolated(
+                &format!("touch {}", workspace_root.join("target/ok")),
+                workspace_root,
+            ) else {
+                return;
+            };
+            assert!(
+                output.status.success(),
+                "writing to the target directory succeeds: {output:?}"
+            );
+
+            let output = run_isolated(
+                &format!("touch {}", workspace_root.join("file")),
+                workspace_root,
+            )
+            .unwrap();
+            assert!(
+                !output.status.success(),
+                "writing to the workspace fails: {output:?}"
+            );
+            assert!(
+                !workspace_root.join("file").exists(),
+                "file wasn't written to the workspace"
+            );
+
+            // Even if nextest is run as root, the test can't remount the workspace read-write.
+            let output = run_isolated(
+                &format!(
+                    "mount -o remount,bind,rw {workspace_root} && touch {}",
+                    workspace_root.join("file")
+                ),
+                workspace_root,
+            )
+            .unwrap();
+            assert!(
+                !output.status.success(),
+                "remounting the workspace fails: {output:?}"
+            );
+            assert!(
+                !workspace_root.join("file").exists(),
+                "file wasn't written to the workspace after remounting"
+            );
+
+            // Failing to spawn the test isn't blamed on the sandbox.
+            let mut cmd = Command::new(workspace_root.join("target/does-not-exist"));
+            let sandbox = isolate(
+                &mut cmd,
+                &workspace_root.join("target/does-not-exist"),
+                workspace_root,
+                &workspace_root.join("target"),
+            )
+            .unwrap();
+            let error = cmd.output().expect_err("spawning a missing binary fails");
+            assert!(
+                matches!(sandbox.spawn_error(error), RunTestError::Spawn(_)),
+                "missing binary is reported as a spawn error"
+            );
+
+            let output = run_isolated("exec 3<>/dev/tcp/192.0.2.1/80", workspace_root).unwrap();
+            assert!(
+                !output.status.success(),
+                "connecting to a non-loopback address fails: {output:?}"
+            );
+            assert!(
+                String::from_utf8_lossy(&output.stderr).contains("Network is unreachable"),
+                "network is unreachable: {output:?}"
+            );
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod imp {
+    use crate::errors::{IsolationError, RunTestError};
+    use camino::Utf8Path;
+    use std::{io, process::Command, sync::Once};
+
+    pub(crate) struct Sandbox(());
+
+    impl Sandbox {
+        pub(crate) fn spawn_error(&self, error: io::Error) -> RunTestError {
+            RunTestError::Spawn(error)
+        }
+    }
+
+    pub(crate) fn isolate(
+        _cmd: &mut Command,
+        _program: &Utf8Path,
+        _workspace_root: &Utf8Path,
+        _target_dir: &Utf8Path,
+    ) -> Result<Sandbox, IsolationError> {
+        static WARN_ONCE: Once = Once::new();
+        WARN_ONCE.call_once(|| {
+            log::warn!(
+                "isolation is only supported on Linux, so tests with `isolation = true` \
+                 are run without it"
+            );
+        });
+        Ok(Sandbox(()))
+    }
+}
@@ -13,14 +13,17 @@ pub mod cargo_config;
 pub mod config;
 #[cfg(feature = "experimental-tokio-console")]
 pub mod console;
+mod core_dump;
 pub mod double_spawn;
 pub mod errors;
 mod helpers;
+mod isolation;
 pub mod list;
 pub mod partition;
 pub mod platform;
 pub mod reporter;
 pub mod reuse_build;
+pub mod run_store;
 pub mod runner;
 pub mod show_config;
 pub mod signal;
@@ -30,3 +33,4 @@ pub mod test_filter;
 mod time;
 #[cfg(feature = "self-update")]
 pub mod update;
+pub mod watch;
@@ -8,7 +8,9 @@ use crate::{
     errors::{CreateTestListError, FromMessagesError, WriteTestListError},
     helpers::{convert_build_platform, dylib_path, dylib_path_envvar, write_test_name},
     list::{BinaryList, OutputFormat, RustBuildMeta, Styles, TestListState},
+    partition::weighted_shards,
     reuse_build::PathMapper,
+    run_store::TestDurations,
     target_runner::{PlatformRunner, TargetRunner},
     test_command::{LocalExecuteContext, TestCommand},
     test_filter::TestFilterBuilder,
@@ -21,8 +23,9 @@ use guppy::{
 };
 use nextest_filtering::{BinaryQuery, TestQuery};
 use nextest_metadata::{
-    BuildPlatform, RustBinaryId, RustNonTestBinaryKind, RustTestBinaryKind, RustTestBinarySummary,
-    RustTestCaseSummary, RustTestSuiteStatusSummary, RustTestSuiteSummary, TestListSummary,
+    BuildPlatform, FilterMatch, MismatchReason, RustBinaryId, RustNonTestBinaryKind,
+    RustTestBinaryKind, RustTestBinarySummary, RustTestCaseSummary, RustTestSuiteStatusSummary,
+    RustTestSuiteSummary, TestListSummary,
 };
 use once_cell::sync::{Lazy, OnceCell};
 use owo_colors::OwoColorize;
@@ -33,6 +36,7 @@ use std::{
     io::Write,
     path::PathBuf,
     sync::Arc,
+    time::Duration,
 };
 use tokio::runtime::Runtime;
 
@@ -196,6 +200,8 @@ pub struct TestList<'g> {
     workspace_root: Utf8PathBuf,
     env: EnvironmentMap,
     updated_dylib_path: OsString,
+    // Set by apply_expected_durations.
+    expected_durations: BTreeMap<RustBinaryId, BTreeMap<String, Duration>>,
     // Computed on first access.
     skip_count: OnceCell<usize>,
 }
@@ -268,13 +274,14 @@ impl<'g> TestList<'g> {
             rust_build_meta,
             updated_dylib_path,
             test_count,
+            expected_durations: BTreeMap::new(),
             skip_count: OnceCell::new(),
         })
     }
 
     /// Creates a new test list with the given binary names and outputs.
     #[cfg(test)]
-    fn new_with_outputs(
+    pub(crate) fn new_with_outputs(
         test_bin_outputs: impl IntoIterator<
             Item = (RustTestArtifact<'g>, impl AsRef<str>, impl AsRef<str>),
         >,
@@ -313,15 +320,121 @@ impl<'g> TestList<'g> {
             rust_build_meta,
             updated_dylib_path,
             test_count,
+            expected_durations: BTreeMap::new(),
             skip_count: OnceCell::new(),
         })
     }
 
+    /// Creates a new test list with a single fake binary containing the given tests.
+    #[cfg(test)]
+    pub(crate) fn with_fake_tests(test_names: &[&str]) -> TestList<'static> {
+        let test_binary = RustTestArtifact {
+            binary_path: "/fake/binary".into(),
+            cwd: "/fake/cwd".into(),
+            package: tests::package_metadata(),
+            binary_name: "fake-binary".to_owned(),
+            binary_id: "fake-package::fake-binary".into(),
+            kind: RustTestBinaryKind::LIB,
+            non_test_binaries: BTreeSet::new(),
+            build_platform: BuildPlatform::Target,
+        };
+        let test_output: String = test_names
+            .iter()
+            .map(|name| format!("{name}: test\n"))
+            .collect();
+        let test_filter = TestFilterBuilder::any(crate::test_filter::RunIgnored::Default);
+        TestList::new_with_outputs(
+            [(test_binary, test_output, "")],
+            Utf8PathBuf::from("/fake/path"),
+            RustBuildMeta::new("/fake", None).map_paths(&PathMapper::noop()),
+            &test_filter,
+            EnvironmentMap::empty(),
+        )
+        .expect("valid output")
+    }
+
     /// Returns the total number of tests across all binaries.
     pub fn test_count(&self) -> usize {
         self.test_count
     }
 
+    /// Sets the expected duration of each test in this list from durations recorded in previous
+    /// runs.
+    ///
+    /// Expected durations are shown in serialized output, and are used by the runner to start the
+    /// longest tests first.
+    pub fn apply_expected_durations(&mut self, durations: &TestDurations) {
+        self.expected_durations = self
+            .rust_suites
+            .iter()
+            .filter_map(|(binary_id, suite)| {
+                let expected: BTreeMap<_, _> = suite
+                    .status
+                    .test_cases()
+                    .filter_map(|(test_name, _)| {
+                        let duration = durations.expected(binary_id, test_name)?;
+                        Some((test_name.to_owned(), duration))
+                    })
+                    .collect();
+                (!expected.is_empty()).then(|| (binary_id.clone(), expected))
+            })
+            .collect();
+    }
+
+    /// Returns the expected duration of the given test, as set by
+    /// [`Self::apply_expected_durations`].
+    pub fn expected_duration(&self, binary_id: &RustBinaryId, test_name: &str) -> Option<Duration> {
+        self.expected_durations
+            .get(binary_id)?
+            .get(test_name)
+            .copied()
+    }
+
+    /// Restricts this list to the tests in the given shard, assigning tests to shards based on
+    /// the given durations.
+    ///
+    /// This implements weighted partitioning. Only tests that match all other filters are assigned
+    /// to shards: the rest are marked as not matching with [`MismatchReason::Partition`].
+    ///
+    /// The assignment only depends on `durations`, not on the expected durations set by
+    /// [`Self::apply_expected_durations`], so every shard computes the same assignment as long as
+    /// they're all passed the same durations.
+    pub fn apply_weighted_partition(
+        &mut self,
+        durations: &TestDurations,
+        shard: u64,
+        total_shards: u64,
+    ) {
+        let (mut test_cases, expected_durations): (Vec<_>, Vec<_>) = self
+            .rust_suites
+            .iter_mut()
+            .filter_map(|(binary_id, suite)| match &mut suite.status {
+                RustTestSuiteStatus::Listed { test_cases } => Some(
+                    test_cases
+                        .iter_mut()
+                        .map(move |(test_name, test_case)| (binary_id, test_name, test_case)),
+                ),
+                RustTestSuiteStatus::Skipped => None,
+            })
+            .flatten()
+            .filter(|(_, _, test_case)| test_case.filter_match.is_match())
+            .map(|(binary_id, test_name, test_case)| {
+                (test_case, durations.expected(binary_id, test_name))
+            })
+            .unzip();
+        let shards = weighted_shards(&expected_durations, total_shards);
+
+        for (test_case, test_shard) in test_cases.iter_mut().zip(shards) {
+            if test_shard != shard - 1 {
+                test_case.filter_match = FilterMatch::Mismatch {
+                    reason: MismatchReason::Partition,
+                };
+            }
+        }
+        // The number of skipped tests has changed.
+        self.skip_count = OnceCell::new();
+    }
+
     /// Returns the Rust build-related metadata for this test list.
     pub fn rust_build_meta(&self) -> &RustBuildMeta<TestListState> {
         &self.rust_build_meta
@@ -390,6 +503,7 @@ impl<'g> TestList<'g> {
         let mut summary = TestListSummary::new(self.rust_build_meta.to_summary());
         summary.test_count = self.test_count;
         summary.rust_suites = rust_suites;
+        summary.expected_durations = self.expected_durations.clone();
         summary
     }
 
@@ -447,6 +561,7 @@ impl<'g> TestList<'g> {
             env: EnvironmentMap::empty(),
             updated_dylib_path: OsString::new(),
             rust_suites: BTreeMap::new(),
+            expected_durations: BTreeMap::new(),
             skip_count: OnceCell::new(),
         }
     }
@@ -701,6 +816,19 @@ pub struct RustTestSuite<'g> {
     pub status: RustTestSuiteStatus,
 }
 
+impl RustTestSuite<'_> {
+    /// Returns a [`BinaryQuery`] corresponding to this test suite.
+    pub fn to_binary_query(&self) -> BinaryQuery<'_> {
+        BinaryQuery {
+            package_id: self.package.id(),
+            binary_id: &self.binary_id,
+            kind: &self.kind,
+            binary_name: &self.binary_name,
+            platform: convert_build_platform(self.build_platform),
+        }
+    }
+}
+
 impl<'g> RustTestArtifact<'g> {
     /// Run this binary with and without --ignored and get the corresponding outputs.
     async fn exec(
@@ -904,14 +1032,20 @@ impl<'a> TestInstance<'a> {
                 platform: convert_build_platform(self.suite_info.build_platform),
             },
             test_name: self.name,
+            ignored: self.test_info.ignored,
+            test_group: None,
         }
     }
 
     /// Creates the command for this test instance.
+    ///
+    /// `tmpdir` is the scratch directory for this attempt, exposed to the test as
+    /// `NEXTEST_TEST_TMPDIR`.
     pub(crate) fn make_command(
         &self,
         ctx: &TestExecuteContext<'_>,
         test_list: &TestList<'_>,
+        tmpdir: &Utf8Path,
     ) -> TestCommand {
         let platform_runner = ctx
             .target_runner
@@ -941,14 +1075,16 @@ impl<'a> TestInstance<'a> {
             env: &test_list.env,
         };
 
-        TestCommand::new(
+        let mut cmd = TestCommand::new(
             &lctx,
             program,
             &args,
             &self.suite_info.cwd,
             &self.suite_info.package,
             &self.suite_info.non_test_binaries,
-        )
+        );
+        cmd.command_mut().env("NEXTEST_TEST_TMPDIR", tmpdir);
+        cmd
     }
 }
 
@@ -1250,6 +1386,88 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_weighted_partition_ignores_local_durations() {
+        let test_output = indoc! {"
+            tests::a: test
+            tests::b: test
+            tests::c: test
+            tests::d: test
+            tests::e: test
+        "};
+        let binary_id = RustBinaryId::new("fake-package::fake-binary");
+        let make_test_list = || {
+            let test_filter = TestFilterBuilder::new(
+                RunIgnored::Default,
+                None,
+                iter::empty::<String>(),
+                Vec::new(),
+            )
+            .unwrap();
+            let test_binary = RustTestArtifact {
+                binary_path: "/fake/binary".into(),
+                cwd: "/fake/cwd".into(),
+                package: package_metadata(),
+                binary_name: "fake-binary".to_owned(),
+                binary_id: binary_id.clone(),
+                kind: RustTestBinaryKind::LIB,
+                non_test_binaries: BTreeSet::new(),
+                build_platform: BuildPlatform::Target,
+            };
+            let rust_build_meta = RustBuildMeta::new("/fake", None).map_paths(&PathMapper::noop());
+            TestList::new_with_outputs(
+                [(test_binary, &test_output, &"")],
+                Utf8PathBuf::from("/fake/path"),
+                rust_build_meta,
+                &test_filter,
+                EnvironmentMap::empty(),
+            )
+            .expect("valid output")
+        };
+        let durations = |secs: &[(&str, u64)]| {
+            let mut durations = TestDurations::default();
+            for (test_name, secs) in secs {
+                durations.record(&binary_id, test_name, Duration::from_secs(*secs));
+            }
+            durations
+        };
+
+        // Two machines with different local histories, but the same partition durations.
+        let local_a = durations(&[("tests::a", 1), ("tests::b", 30), ("tests::e", 2)]);
+        let local_b = durations(&[("tests::c", 50), ("tests::d", 1)]);
+        let partition_durations = durations(&[
+            ("tests::a", 10),
+            ("tests::b", 1),
+            ("tests::c", 4),
+            ("tests::d", 5),
+            ("tests::e", 2),
+        ]);
+
+        let shard_tests = |local: &TestDurations, shard: u64| {
+            let mut test_list = make_test_list();
+            test_list.apply_expected_durations(local);
+            test_list.apply_weighted_partition(&partition_durations, shard, 2);
+            test_list
+                .iter_tests()
+                .filter(|instance| instance.test_info.filter_match.is_match())
+                .map(|instance| instance.name.to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        for shard in 1..=2 {
+            assert_eq!(
+                shard_tests(&local_a, shard),
+                shard_tests(&local_b, shard),
+                "shard {shard} is the same regardless of local durations"
+            );
+        }
+        assert_eq!(shard_tests(&local_a, 1), ["tests::a", "tests::b"]);
+        assert_eq!(
+            shard_tests(&local_a, 2),
+            ["tests::c", "tests::d", "tests::e"]
+        );
+    }
+
     static PACKAGE_GRAPH_FIXTURE: Lazy<PackageGraph> = Lazy::new(|| {
         static FIXTURE_JSON: &str = include_str!("../../../fixtures/cargo-metadata.json");
         let metadata = CargoMetadata::parse_json(FIXTURE_JSON).expect("fixture is valid JSON");
@@ -1259,7 +1477,7 @@ mod tests {
     });
 
     static PACKAGE_METADATA_ID: &str = "metadata-helper 0.1.0 (path+file:///Users/fakeuser/local/testcrates/metadata/metadata-helper)";
-    fn package_metadata() -> PackageMetadata<'static> {
+    pub(super) fn package_metadata() -> PackageMetadata<'static> {
         PACKAGE_GRAPH_FIXTURE
             .metadata(&PackageId::new(PACKAGE_METADATA_ID))
             .expect("package ID is valid")
@@ -3,15 +3,19 @@
 
 //! Support for partitioning test runs across several machines.
 //!
-//! At the moment this only supports simple hash-based and count-based sharding. In the future it
-//! could potentially be made smarter: e.g. using data to pick different sets of binaries and tests
-//! to run, with an aim to minimize total build and test times.
+//! This supports simple hash-based and count-based sharding, as well as weighted sharding which
+//! uses recorded test durations to give each shard roughly the same amount of work. In the future
+//! it could potentially be made smarter: e.g. using data to pick different sets of binaries and
+//! tests to run, with an aim to minimize total build and test times.
 
 use crate::errors::PartitionerBuilderParseError;
 use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
     fmt,
     hash::{Hash, Hasher},
     str::FromStr,
+    time::Duration,
 };
 use twox_hash::XxHash64;
 
@@ -39,6 +43,20 @@ pub enum PartitionerBuilder {
         /// The total number of shards.
         total_shards: u64,
     },
+
+    /// Partition based on expected test durations, such that each shard takes roughly the same
+    /// amount of time to run.
+    ///
+    /// This partitioner needs to know about every test across all binaries, so it is not applied
+    /// per-binary. Instead, it is applied to the entire list of tests by
+    /// [`TestList::apply_weighted_partition`](crate::list::TestList::apply_weighted_partition).
+    Weighted {
+        /// The shard this is in, counting up from 1.
+        shard: u64,
+
+        /// The total number of shards.
+        total_shards: u64,
+    },
 }
 
 /// Represents an individual partitioner, typically scoped to a test binary.
@@ -49,17 +67,21 @@ pub trait Partitioner: fmt::Debug {
 
 impl PartitionerBuilder {
     /// Creates a new `Partitioner` from this `PartitionerBuilder`.
-    pub fn build(&self) -> Box<dyn Partitioner> {
+    ///
+    /// Returns `None` for [`PartitionerBuilder::Weighted`], which can't be applied to a single
+    /// binary at a time.
+    pub fn build(&self) -> Option<Box<dyn Partitioner>> {
         // Note we don't use test_binary at the moment but might in the future.
         match self {
             PartitionerBuilder::Count {
                 shard,
                 total_shards,
-            } => Box::new(CountPartitioner::new(*shard, *total_shards)),
+            } => Some(Box::new(CountPartitioner::new(*shard, *total_shards))),
             PartitionerBuilder::Hash {
                 shard,
                 total_shards,
-            } => Box::new(HashPartitioner::new(*shard, *total_shards)),
+            } => Some(Box::new(HashPartitioner::new(*shard, *total_shards))),
+            PartitionerBuilder::Weighted { .. } => None,
         }
     }
 }
@@ -83,10 +105,19 @@ impl FromStr for PartitionerBuilder {
                 shard,
                 total_shards,
             })
+        } else if let Some(input) = s.strip_prefix("weighted:") {
+            let (shard, total_shards) = parse_shards(input, "weighted:M/N")?;
+
+            Ok(PartitionerBuilder::Weighted {
+                shard,
+                total_shards,
+            })
         } else {
             Err(PartitionerBuilderParseError::new(
                 None,
-                format!("partition input '{s}' must begin with \"hash:\" or \"count:\""),
+                format!(
+                    "partition input '{s}' must begin with \"hash:\", \"count:\" or \"weighted:\""
+                ),
             ))
         }
     }
@@ -184,6 +215,48 @@ impl Partitioner for HashPartitioner {
     }
 }
 
+/// Assigns tests to shards such that the total expected duration of each shard is roughly the same.
+///
+/// Returns the shard for each test, counting up from 0. Tests without an expected duration are
+/// assumed to take as long as the average of the tests with one (or, if no tests have one, all
+/// tests are assumed to take the same amount of time).
+///
+/// The assignment only depends on the order of `expected_durations` and the durations themselves,
+/// so the caller should pass in tests in a stable order: then, every shard computes the same
+/// assignment.
+pub(crate) fn weighted_shards(
+    expected_durations: &[Option<Duration>],
+    total_shards: u64,
+) -> Vec<u64> {
+    let (known_total, known_count) = expected_durations
+        .iter()
+        .flatten()
+        .fold((0u128, 0u128), |(total, count), duration| {
+            (total + duration.as_nanos(), count + 1)
+        });
+    let default_weight = known_total.checked_div(known_count).unwrap_or(1);
+    let weights: Vec<u128> = expected_durations
+        .iter()
+        .map(|duration| duration.map_or(default_weight, |duration| duration.as_nanos()))
+        .collect();
+
+    // Greedily assign the heaviest remaining test to the shard with the least total weight so far.
+    // Both sorts are stable with respect to the input order and shard numbers, so ties are broken
+    // deterministically.
+    let mut order: Vec<usize> = (0..weights.len()).collect();
+    order.sort_by_key(|&index| Reverse(weights[index]));
+
+    let mut shard_weights: BinaryHeap<Reverse<(u128, u64)>> =
+        (0..total_shards).map(|shard| Reverse((0, shard))).collect();
+    let mut shards = vec![0; weights.len()];
+    for index in order {
+        let Reverse((weight, shard)) = shard_weights.pop().expect("total_shards is at least 1");
+        shards[index] = shard;
+        shard_weights.push(Reverse((weight + weights[index], shard)));
+    }
+    shards
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -212,6 +285,13 @@ mod tests {
                     total_shards: 200,
                 },
             ),
+            (
+                "weighted:2/3",
+                PartitionerBuilder::Weighted {
+                    shard: 2,
+                    total_shards: 3,
+                },
+            ),
         ];
 
         let failures = vec![
@@ -225,6 +305,8 @@ mod tests {
             "hash:m/2",
             "hash:1/n",
             "hash:1/2/3",
+            "weighted:0/2",
+            "weighted:1",
         ];
 
         for (input, output) in successes {
@@ -242,4 +324,31 @@ mod tests {
                 .expect_err(&format!("expected input '{input}' to fail"));
         }
     }
+
+    #[test]
+    fn weighted_shards_balanced() {
+        let secs = |secs| Some(Duration::from_secs(secs));
+
+        // The heavy tests are spread out across shards, and the light tests fill in the gaps.
+        let durations = [secs(1), secs(10), secs(2), secs(9), secs(3), secs(5)];
+        let shards = weighted_shards(&durations, 2);
+        assert_eq!(shards, vec![1, 0, 0, 1, 0, 1]);
+        for shard in 0..2 {
+            let total: u64 = durations
+                .iter()
+                .zip(&shards)
+                .filter(|(_, s)| **s == shard)
+                .map(|(d, _)| d.unwrap().as_secs())
+                .sum();
+            assert_eq!(total, 15, "shard {shard} has half the total duration");
+        }
+
+        // Tests without durations are given the average duration (here, 4 seconds).
+        let shards = weighted_shards(&[secs(6), None, secs(2), None], 2);
+        assert_eq!(shards, vec![0, 1, 0, 1]);
+
+        // With no durations at all, tests are spread out evenly in order.
+        let shards = weighted_shards(&[None; 5], 3);
+        assert_eq!(shards, vec![0, 1, 2, 0, 1]);
+    }
 }
@@ -6,18 +6,20 @@
 //! The main structure in this module is [`TestReporter`].
 
 mod aggregator;
+mod structured;
 use crate::{
-    config::{NextestProfile, ScriptId},
+    config::{NextestProfile, ResourceLimitKind, ScriptId, ScriptPhase},
     errors::WriteEventError,
-    helpers::{plural, write_test_name},
+    helpers::{format_bytes, plural, write_test_name},
     list::{TestInstance, TestList},
-    reporter::aggregator::EventAggregator,
+    reporter::{aggregator::EventAggregator, structured::StructuredReporter},
     runner::{
         AbortStatus, ExecuteStatus, ExecutionDescription, ExecutionResult, ExecutionStatuses,
-        RetryData, RunStats, SetupScriptExecuteStatus,
+        RetryData, RunStats, SetupScriptExecuteStatus, TestOutputStream,
     },
 };
 pub use aggregator::heuristic_extract_description;
+use bytes::Bytes;
 use debug_ignore::DebugIgnore;
 use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
 use nextest_metadata::MismatchReason;
@@ -25,7 +27,8 @@ use owo_colors::{OwoColorize, Style};
 use serde::Deserialize;
 use std::{
     borrow::Cow,
-    cmp::Reverse,
+    cmp::{Ordering, Reverse},
+    collections::HashMap,
     fmt::{self, Write as _},
     io,
     io::{BufWriter, Write},
@@ -140,6 +143,21 @@ pub enum FinalStatusLevel {
     All,
 }
 
+/// The format in which events are written to standard output by the reporter.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum ReporterMessageFormat {
+    /// Only produce human-readable output on standard error.
+    #[default]
+    Human,
+
+    /// In addition to human-readable output on standard error, write each event to standard
+    /// output as newline-delimited JSON.
+    ///
+    /// The format of each line is described by
+    /// [`TestEventSummary`](nextest_metadata::TestEventSummary).
+    NextestJson,
+}
+
 /// Standard error destination for the reporter.
 ///
 /// This is usually a terminal, but can be an in-memory buffer for tests.
@@ -163,6 +181,8 @@ pub struct TestReporterBuilder {
     final_status_level: Option<FinalStatusLevel>,
     verbose: bool,
     hide_progress_bar: bool,
+    stream_output: bool,
+    message_format: ReporterMessageFormat,
 }
 
 impl TestReporterBuilder {
@@ -211,6 +231,21 @@ impl TestReporterBuilder {
         self.hide_progress_bar = hide_progress_bar;
         self
     }
+
+    /// Sets whether test output is displayed while tests run.
+    ///
+    /// In this mode, each line of output from [`TestEventKind::TestOutputChunk`] events is
+    /// displayed as soon as it's complete, prefixed with the binary ID and test name.
+    pub fn set_stream_output(&mut self, stream_output: bool) -> &mut Self {
+        self.stream_output = stream_output;
+        self
+    }
+
+    /// Sets the format in which events are additionally written to standard output.
+    pub fn set_message_format(&mut self, message_format: ReporterMessageFormat) -> &mut Self {
+        self.message_format = message_format;
+        self
+    }
 }
 
 impl TestReporterBuilder {
@@ -306,6 +341,13 @@ impl TestReporterBuilder {
             ReporterStderr::Buffer(buf) => ReporterStderrImpl::Buffer(buf),
         };
 
+        let structured_reporter = match self.message_format {
+            ReporterMessageFormat::Human => None,
+            ReporterMessageFormat::NextestJson => {
+                Some(StructuredReporter::new(Box::new(std::io::stdout())))
+            }
+        };
+
         TestReporter {
             inner: TestReporterImpl {
                 status_level,
@@ -313,12 +355,17 @@ impl TestReporterBuilder {
                 force_success_output,
                 force_failure_output,
                 no_capture: self.no_capture,
+                verbose: self.verbose,
+                stream_output: self.stream_output,
+                partial_lines: HashMap::new(),
                 binary_id_width,
                 styles,
                 cancel_status: None,
                 final_outputs: DebugIgnore(vec![]),
+                stress_tallies: vec![],
             },
             stderr,
+            structured_reporter,
             metadata_reporter: aggregator,
         }
     }
@@ -334,6 +381,7 @@ enum ReporterStderrImpl<'a> {
 pub struct TestReporter<'a> {
     inner: TestReporterImpl<'a>,
     stderr: ReporterStderrImpl<'a>,
+    structured_reporter: Option<StructuredReporter<'a>>,
     metadata_reporter: EventAggregator<'a>,
 }
 
@@ -383,6 +431,9 @@ impl<'a> TestReporter<'a> {
                     .map_err(WriteEventError::Io)?;
             }
         }
+        if let Some(structured_reporter) = &mut self.structured_reporter {
+            structured_reporter.write_event(&event)?;
+        }
         self.metadata_reporter.write_event(event)?;
         Ok(())
     }
@@ -530,6 +581,15 @@ fn write_summary_str(run_stats: &RunStats, styles: &Styles, out: &mut String) ->
         )?;
     }
 
+    if run_stats.quarantined > 0 {
+        write!(
+            out,
+            "{} {}, ",
+            run_stats.quarantined.style(styles.count),
+            "quarantined".style(styles.skip),
+        )?;
+    }
+
     write!(
         out,
         "{} {}",
@@ -540,12 +600,38 @@ fn write_summary_str(run_stats: &RunStats, styles: &Styles, out: &mut String) ->
     Ok(())
 }
 
+/// Pass/fail counts for a test run in stress mode.
+#[derive(Clone, Copy, Debug)]
+struct StressTally {
+    iterations: usize,
+    failed: usize,
+    time_taken: Duration,
+    quarantined: bool,
+}
+
+impl StressTally {
+    fn new(run_statuses: &ExecutionStatuses, quarantined: bool) -> Self {
+        Self {
+            iterations: run_statuses.len(),
+            failed: run_statuses.failed_count(),
+            time_taken: run_statuses.iter().map(|status| status.time_taken).sum(),
+            quarantined,
+        }
+    }
+
+    /// Compares failure rates, without going through floating point.
+    fn cmp_failure_rate(&self, other: &Self) -> Ordering {
+        (self.failed * other.iterations).cmp(&(other.failed * self.iterations))
+    }
+}
+
 #[derive(Debug)]
 enum FinalOutput {
     Skipped(MismatchReason),
     Executed {
         run_statuses: ExecutionStatuses,
         test_output_display: TestOutputDisplay,
+        quarantined: bool,
     },
 }
 
@@ -558,16 +644,24 @@ impl FinalOutput {
     }
 }
 
+/// The longest partial line of streamed output that's buffered before it's written out anyway.
+const MAX_PARTIAL_LINE_LEN: usize = 64 * 1024;
+
 struct TestReporterImpl<'a> {
     status_level: StatusLevel,
     final_status_level: FinalStatusLevel,
     force_success_output: Option<TestOutputDisplay>,
     force_failure_output: Option<TestOutputDisplay>,
     no_capture: bool,
+    verbose: bool,
+    stream_output: bool,
+    // Streamed output for each test that doesn't end in a newline yet.
+    partial_lines: HashMap<(&'a str, &'a str, TestOutputStream), Vec<u8>>,
     binary_id_width: usize,
     styles: Box<Styles>,
     cancel_status: Option<CancelReason>,
     final_outputs: DebugIgnore<Vec<(TestInstance<'a>, FinalOutput)>>,
+    stress_tallies: Vec<(TestInstance<'a>, StressTally)>,
 }
 
 impl<'a> TestReporterImpl<'a> {
@@ -603,11 +697,16 @@ impl<'a> TestReporterImpl<'a> {
                 index,
                 total,
                 script_id,
+                phase,
                 command,
                 args,
                 ..
             } => {
-                write!(writer, "{:>12} ", "SETUP".style(self.styles.pass))?;
+                write!(
+                    writer,
+                    "{:>12} ",
+                    script_phase_str(*phase).style(self.styles.pass)
+                )?;
                 // index + 1 so that it displays as e.g. "1/2" and "2/2".
                 write!(writer, "[{:>9}] ", format!("{}/{}", index + 1, total))?;
 
@@ -616,13 +715,18 @@ impl<'a> TestReporterImpl<'a> {
             }
             TestEventKind::SetupScriptSlow {
                 script_id,
+                phase,
                 command,
                 args,
                 elapsed,
                 will_terminate,
             } => {
                 if !*will_terminate && self.status_level >= StatusLevel::Slow {
-                    write!(writer, "{:>12} ", "SETUP SLOW".style(self.styles.skip))?;
+                    write!(
+                        writer,
+                        "{:>12} ",
+                        format!("{} SLOW", script_phase_str(*phase)).style(self.styles.skip)
+                    )?;
                 } else if *will_terminate {
                     write!(writer, "{:>12} ", "TERMINATING".style(self.styles.fail))?;
                 }
@@ -635,13 +739,14 @@ impl<'a> TestReporterImpl<'a> {
                 script_id,
                 index,
                 total,
+                phase,
                 command,
                 args,
                 run_status,
                 ..
             } => {
                 self.write_setup_script_status_line(
-                    script_id, *index, *total, command, args, run_status, writer,
+                    script_id, *index, *total, *phase, command, args, run_status, writer,
                 )?;
                 // Always display failing setup script output if it exists. We may change this in
                 // the future.
@@ -651,6 +756,43 @@ impl<'a> TestReporterImpl<'a> {
                     )?;
                 }
             }
+            TestEventKind::WrapperScriptFinished {
+                test_instance,
+                script_id,
+                phase,
+                command,
+                args,
+                run_status,
+            } => {
+                // Wrapper scripts run around every test they're enabled for, so only show them if
+                // they fail or in verbose mode.
+                let success = run_status.result.is_success();
+                if success && !self.verbose {
+                    return Ok(());
+                }
+
+                let status_str = if success {
+                    "WRAPPER PASS".style(self.styles.pass)
+                } else {
+                    "WRAPPER FAIL".style(self.styles.fail)
+                };
+                write!(writer, "{status_str:>12} ")?;
+                self.write_duration(run_status.time_taken, writer)?;
+                self.write_setup_script(script_id, command, args, writer)?;
+                let when = match phase {
+                    ScriptPhase::Setup => "before",
+                    ScriptPhase::Teardown => "after",
+                };
+                write!(writer, " ({when} ")?;
+                self.write_instance(*test_instance, writer)?;
+                writeln!(writer, ")")?;
+
+                if !success {
+                    self.write_setup_script_stdout_stderr(
+                        script_id, command, args, run_status, writer,
+                    )?;
+                }
+            }
             TestEventKind::TestStarted { test_instance, .. } => {
                 // In no-capture mode, print out a test start event.
                 if self.no_capture {
@@ -701,6 +843,16 @@ impl<'a> TestReporterImpl<'a> {
                 self.write_instance(*test_instance, writer)?;
                 writeln!(writer)?;
             }
+            TestEventKind::TestOutputChunk {
+                test_instance,
+                stream,
+                data,
+                ..
+            } => {
+                if self.stream_output {
+                    self.write_output_chunk(*test_instance, *stream, data, writer)?;
+                }
+            }
 
             TestEventKind::TestAttemptFailedWillRetry {
                 test_instance,
@@ -708,6 +860,7 @@ impl<'a> TestReporterImpl<'a> {
                 delay_before_next_attempt,
                 failure_output,
             } => {
+                self.flush_partial_lines(*test_instance, writer)?;
                 if self.status_level >= StatusLevel::Retry {
                     let try_status_string = format!(
                         "TRY {} {}",
@@ -778,18 +931,30 @@ impl<'a> TestReporterImpl<'a> {
                 test_instance,
                 success_output,
                 failure_output,
+                quarantined,
                 run_statuses,
                 ..
             } => {
+                self.flush_partial_lines(*test_instance, writer)?;
                 let describe = run_statuses.describe();
-                let last_status = run_statuses.last_status();
+                let last_status = describe.last_status();
                 let test_output_display = match last_status.result.is_success() {
                     true => self.success_output(*success_output),
                     false => self.failure_output(*failure_output),
                 };
+                let stress_tally = run_statuses
+                    .is_stress()
+                    .then(|| StressTally::new(run_statuses, *quarantined));
 
                 if self.status_level >= describe.status_level() {
-                    self.write_status_line(*test_instance, describe, writer)?;
+                    match &stress_tally {
+                        Some(tally) => {
+                            self.write_stress_status_line(*test_instance, tally, writer)?
+                        }
+                        None => {
+                            self.write_status_line(*test_instance, describe, *quarantined, writer)?
+                        }
+                    }
 
                     // If the test failed to execute, print its output and error status.
                     // (don't print out test failures after Ctrl-C)
@@ -810,9 +975,13 @@ impl<'a> TestReporterImpl<'a> {
                         FinalOutput::Executed {
                             run_statuses: run_statuses.clone(),
                             test_output_display,
+                            quarantined: *quarantined,
                         },
                     ));
                 }
+                if let Some(tally) = stress_tally {
+                    self.stress_tallies.push((*test_instance, tally));
+                }
             }
             TestEventKind::TestSkipped {
                 test_instance,
@@ -837,6 +1006,7 @@ impl<'a> TestReporterImpl<'a> {
                     CancelReason::SetupScriptFailure => "setup script failure",
                     CancelReason::TestFailure => "test failure",
                     CancelReason::ReportError => "error",
+                    CancelReason::Requested => "request",
                     CancelReason::Signal => "signal",
                     CancelReason::Interrupt => "interrupt",
                 };
@@ -972,6 +1142,10 @@ impl<'a> TestReporterImpl<'a> {
                 let _ = write_summary_str(run_stats, &self.styles, &mut summary_str);
                 writeln!(writer, " {tests_str} run: {summary_str}")?;
 
+                if !self.stress_tallies.is_empty() {
+                    self.write_stress_summary(writer)?;
+                }
+
                 // Don't print out final outputs if canceled due to Ctrl-C.
                 if self.cancel_status < Some(CancelReason::Signal) {
                     // Sort the final outputs for a friendlier experience.
@@ -993,19 +1167,34 @@ impl<'a> TestReporterImpl<'a> {
                             FinalOutput::Executed {
                                 run_statuses,
                                 test_output_display,
+                                quarantined,
                             } => {
-                                let last_status = run_statuses.last_status();
+                                let describe = run_statuses.describe();
+                                let last_status = describe.last_status();
 
                                 // Print out the final status line so that status lines are shown
-                                // for tests that e.g. failed due to signals.
-                                if self.final_status_level >= final_status_level
+                                // for tests that e.g. failed due to signals. (Failing tests in
+                                // stress mode have already been listed in the stress summary.)
+                                let listed_in_stress_summary =
+                                    run_statuses.is_stress() && run_statuses.failed_count() > 0;
+                                if (self.final_status_level >= final_status_level
+                                    && !listed_in_stress_summary)
                                     || test_output_display.is_final()
                                 {
-                                    self.write_final_status_line(
-                                        *test_instance,
-                                        run_statuses.describe(),
-                                        writer,
-                                    )?;
+                                    if run_statuses.is_stress() {
+                                        self.write_stress_status_line(
+                                            *test_instance,
+                                            &StressTally::new(run_statuses, *quarantined),
+                                            writer,
+                                        )?;
+                                    } else {
+                                        self.write_final_status_line(
+                                            *test_instance,
+                                            describe,
+                                            *quarantined,
+                                            writer,
+                                        )?;
+                                    }
                                 }
                                 if test_output_display.is_final() {
                                     self.write_stdout_stderr(
@@ -1046,24 +1235,34 @@ impl<'a> TestReporterImpl<'a> {
         script_id: &ScriptId,
         index: usize,
         total: usize,
+        phase: ScriptPhase,
         command: &str,
         args: &[String],
         status: &SetupScriptExecuteStatus,
         writer: &mut dyn Write,
     ) -> io::Result<()> {
+        let phase_str = script_phase_str(phase);
         match status.result {
             ExecutionResult::Pass => {
-                write!(writer, "{:>12} ", "SETUP PASS".style(self.styles.pass))?;
+                write!(
+                    writer,
+                    "{:>12} ",
+                    format!("{phase_str} PASS").style(self.styles.pass)
+                )?;
             }
             ExecutionResult::Leak => {
-                write!(writer, "{:>12} ", "SETUP LEAK".style(self.styles.skip))?;
+                write!(
+                    writer,
+                    "{:>12} ",
+                    format!("{phase_str} LEAK").style(self.styles.skip)
+                )?;
             }
             other => {
                 let status_str = short_status_str(other);
                 write!(
                     writer,
                     "{:>12} ",
-                    format!("SETUP {status_str}").style(self.styles.fail),
+                    format!("{phase_str} {status_str}").style(self.styles.fail),
                 )?;
             }
         }
@@ -1080,6 +1279,7 @@ impl<'a> TestReporterImpl<'a> {
         &self,
         test_instance: TestInstance<'a>,
         describe: ExecutionDescription<'_>,
+        quarantined: bool,
         writer: &mut dyn Write,
     ) -> io::Result<()> {
         let last_status = describe.last_status();
@@ -1099,6 +1299,10 @@ impl<'a> TestReporterImpl<'a> {
                     format!("TRY {} PASS", last_status.retry_data.attempt).style(self.styles.skip)
                 )?;
             }
+            ExecutionDescription::Failure { .. } if quarantined => {
+                // Use the skip color to indicate that this failure doesn't fail the run.
+                write!(writer, "{:>12} ", "QUARANTINED".style(self.styles.skip))?;
+            }
             ExecutionDescription::Failure { .. } => {
                 if last_status.retry_data.attempt == 1 {
                     write!(
@@ -1123,6 +1327,19 @@ impl<'a> TestReporterImpl<'a> {
 
         // Print the name of the test.
         self.write_instance(test_instance, writer)?;
+
+        // In verbose mode, also print out how many resources the test used.
+        if self.verbose {
+            if let Some(usage) = last_status.resource_usage {
+                write!(
+                    writer,
+                    " (max RSS: {}, user: {:.3}s, system: {:.3}s)",
+                    format_bytes(usage.max_rss),
+                    usage.user_time.as_secs_f64(),
+                    usage.system_time.as_secs_f64(),
+                )?;
+            }
+        }
         writeln!(writer)?;
 
         // On Windows, also print out the exception if available.
@@ -1138,10 +1355,77 @@ impl<'a> TestReporterImpl<'a> {
         Ok(())
     }
 
+    fn write_stress_status_line(
+        &self,
+        test_instance: TestInstance<'a>,
+        tally: &StressTally,
+        writer: &mut dyn Write,
+    ) -> io::Result<()> {
+        if tally.failed == 0 {
+            let status_str = format!("{}/{} PASS", tally.iterations, tally.iterations);
+            write!(writer, "{:>12} ", status_str.style(self.styles.pass))?;
+        } else {
+            let status_str = format!("{}/{} FAIL", tally.failed, tally.iterations);
+            // As with other quarantined failures, use the skip color.
+            let style = if tally.quarantined {
+                self.styles.skip
+            } else {
+                self.styles.fail
+            };
+            write!(writer, "{:>12} ", status_str.style(style))?;
+        }
+
+        // Next, print the total time taken across all iterations.
+        self.write_duration(tally.time_taken, writer)?;
+
+        // Print the name of the test.
+        self.write_instance(test_instance, writer)?;
+        writeln!(writer)
+    }
+
+    fn write_stress_summary(&mut self, writer: &mut dyn Write) -> io::Result<()> {
+        // Rank tests by failure rate, highest first.
+        self.stress_tallies
+            .sort_by(|(a_instance, a_tally), (b_instance, b_tally)| {
+                b_tally
+                    .cmp_failure_rate(a_tally)
+                    .then_with(|| a_instance.sort_key().cmp(&b_instance.sort_key()))
+            });
+
+        let failed_count = self
+            .stress_tallies
+            .iter()
+            .filter(|(_, tally)| tally.failed > 0)
+            .count();
+        let style = if failed_count > 0 {
+            self.styles.fail
+        } else {
+            self.styles.pass
+        };
+        writeln!(
+            writer,
+            "{:>12} {} of {} {} failed at least once",
+            "Stress".style(style),
+            failed_count.style(self.styles.count),
+            self.stress_tallies.len().style(self.styles.count),
+            plural::tests_str(self.stress_tallies.len()),
+        )?;
+
+        // Passing tests are listed along with other final outputs if requested.
+        for (test_instance, tally) in &self.stress_tallies {
+            if tally.failed > 0 {
+                self.write_stress_status_line(*test_instance, tally, writer)?;
+            }
+        }
+
+        Ok(())
+    }
+
     fn write_final_status_line(
         &self,
         test_instance: TestInstance<'a>,
         describe: ExecutionDescription<'_>,
+        quarantined: bool,
         writer: &mut dyn Write,
     ) -> io::Result<()> {
         let last_status = describe.last_status();
@@ -1174,6 +1458,10 @@ impl<'a> TestReporterImpl<'a> {
                     .style(self.styles.skip)
                 )?;
             }
+            ExecutionDescription::Failure { .. } if quarantined => {
+                // Use the skip color to indicate that this failure doesn't fail the run.
+                write!(writer, "{:>12} ", "QUARANTINED".style(self.styles.skip))?;
+            }
             ExecutionDescription::Failure { .. } => {
                 if last_status.retry_data.attempt == 1 {
                     write!(
@@ -1213,6 +1501,86 @@ impl<'a> TestReporterImpl<'a> {
         Ok(())
     }
 
+    /// Writes out complete lines of streamed output, prefixed with the test instance.
+    fn write_output_chunk(
+        &mut self,
+        instance: TestInstance<'a>,
+        stream: TestOutputStream,
+        data: &Bytes,
+        writer: &mut dyn Write,
+    ) -> io::Result<()> {
+        let key = (
+            instance.suite_info.binary_id.as_str(),
+            instance.name,
+            stream,
+        );
+        let buf = self.partial_lines.entry(key).or_default();
+        buf.extend_from_slice(data);
+        let lines = match buf.iter().rposition(|&b| b == b'\n') {
+            Some(last_newline) => {
+                let rest = buf.split_off(last_newline + 1);
+                std::mem::replace(buf, rest)
+            }
+            None => Vec::new(),
+        };
+        // Output that doesn't end a line for a long time is written out in pieces, so that it
+        // isn't buffered without limit.
+        let overlong = (buf.len() >= MAX_PARTIAL_LINE_LEN).then(|| {
+            let mut line = std::mem::take(buf);
+            line.push(b'\n');
+            line
+        });
+
+        for line in lines.split_inclusive(|&b| b == b'\n') {
+            self.write_output_line(instance, line, writer)?;
+        }
+        if let Some(line) = overlong {
+            self.write_output_line(instance, &line, writer)?;
+        }
+        Ok(())
+    }
+
+    /// Writes out any streamed output for a test that didn't end with a newline.
+    fn flush_partial_lines(
+        &mut self,
+        instance: TestInstance<'a>,
+        writer: &mut dyn Write,
+    ) -> io::Result<()> {
+        for stream in [TestOutputStream::Stdout, TestOutputStream::Stderr] {
+            let key = (
+                instance.suite_info.binary_id.as_str(),
+                instance.name,
+                stream,
+            );
+            if let Some(mut line) = self.partial_lines.remove(&key) {
+                if !line.is_empty() {
+                    line.push(b'\n');
+                    self.write_output_line(instance, &line, writer)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn write_output_line(
+        &self,
+        instance: TestInstance<'a>,
+        line: &[u8],
+        writer: &mut dyn Write,
+    ) -> io::Result<()> {
+        write!(
+            writer,
+            "[{} ",
+            instance
+                .suite_info
+                .binary_id
+                .style(self.styles.list_styles.binary_id)
+        )?;
+        write_test_name(instance.name, &self.styles.list_styles, &mut *writer)?;
+        write!(writer, "] ")?;
+        self.write_test_output(line, writer)
+    }
+
     fn write_instance(&self, instance: TestInstance<'a>, writer: &mut dyn Write) -> io::Result<()> {
         write!(
             writer,
@@ -1333,40 +1701,75 @@ impl<'a> TestReporterImpl<'a> {
         };
 
         if !run_status.stdout.is_empty() {
-            write!(writer, "\n{}", "--- ".style(header_style))?;
-            let out_len = self.write_attempt(run_status, header_style, writer)?;
-            // The width is to align test instances.
-            write!(
-                writer,
-                "{:width$}",
-                "STDOUT:".style(header_style),
-                width = (21 - out_len)
-            )?;
-            self.write_instance(*test_instance, writer)?;
-            writeln!(writer, "{}", " ---".style(header_style))?;
+            self.write_output_header("STDOUT", test_instance, run_status, header_style, writer)?;
 
             self.write_test_output(&run_status.stdout, writer)?;
         }
 
         if !run_status.stderr.is_empty() {
-            write!(writer, "\n{}", "--- ".style(header_style))?;
-            let out_len = self.write_attempt(run_status, header_style, writer)?;
-            // The width is to align test instances.
-            write!(
+            self.write_output_header("STDERR", test_instance, run_status, header_style, writer)?;
+
+            self.write_test_output(&run_status.stderr, writer)?;
+        }
+
+        if let Some(backtrace) = &run_status.backtrace {
+            self.write_output_header("BACKTRACE", test_instance, run_status, header_style, writer)?;
+
+            write!(writer, "{backtrace}")?;
+        }
+
+        if let Some(diagnostics) = &run_status.timeout_diagnostics {
+            self.write_output_header(
+                "DIAGNOSTICS",
+                test_instance,
+                run_status,
+                header_style,
                 writer,
-                "{:width$}",
-                "STDERR:".style(header_style),
-                width = (21 - out_len)
             )?;
-            self.write_instance(*test_instance, writer)?;
-            writeln!(writer, "{}", " ---".style(header_style))?;
 
-            self.write_test_output(&run_status.stderr, writer)?;
+            write!(writer, "{diagnostics}")?;
+        }
+
+        if let Some(tmpdir) = &run_status.tmpdir {
+            self.write_output_header("TMPDIR", test_instance, run_status, header_style, writer)?;
+
+            writeln!(writer, "{tmpdir}")?;
+        }
+
+        if !run_status.artifacts.is_empty() {
+            self.write_output_header("ARTIFACTS", test_instance, run_status, header_style, writer)?;
+
+            for artifact in &run_status.artifacts {
+                writeln!(writer, "{artifact}")?;
+            }
         }
 
         writeln!(writer)
     }
 
+    /// Writes out a `--- TRY n LABEL: <instance> ---` header for a section of a test attempt's
+    /// output.
+    fn write_output_header(
+        &self,
+        label: &str,
+        test_instance: &TestInstance<'a>,
+        run_status: &ExecuteStatus,
+        header_style: Style,
+        writer: &mut dyn Write,
+    ) -> io::Result<()> {
+        write!(writer, "\n{}", "--- ".style(header_style))?;
+        let out_len = self.write_attempt(run_status, header_style, writer)?;
+        // The width is to align test instances.
+        write!(
+            writer,
+            "{:width$}",
+            format!("{label}:").style(header_style),
+            width = (21 - out_len)
+        )?;
+        self.write_instance(*test_instance, writer)?;
+        writeln!(writer, "{}", " ---".style(header_style))
+    }
+
     fn write_test_output(&self, output: &[u8], writer: &mut dyn Write) -> io::Result<()> {
         if self.styles.is_colorized {
             const RESET_COLOR: &[u8] = b"\x1b[0m";
@@ -1456,6 +1859,16 @@ fn status_str(result: ExecutionResult) -> Cow<'static, str> {
         ExecutionResult::Pass => "PASS".into(),
         ExecutionResult::Leak => "LEAK".into(),
         ExecutionResult::Timeout => "TIMEOUT".into(),
+        ExecutionResult::ResourceExceeded { kind } => match kind {
+            ResourceLimitKind::CpuTime => "CPU LIMIT".into(),
+        },
+    }
+}
+
+fn script_phase_str(phase: ScriptPhase) -> &'static str {
+    match phase {
+        ScriptPhase::Setup => "SETUP",
+        ScriptPhase::Teardown => "TEARDOWN",
     }
 }
 
@@ -1487,6 +1900,7 @@ fn short_status_str(result: ExecutionResult) -> Cow<'static, str> {
         ExecutionResult::Pass => "PASS".into(),
         ExecutionResult::Leak => "LEAK".into(),
         ExecutionResult::Timeout => "TMT".into(),
+        ExecutionResult::ResourceExceeded { .. } => "LIMIT".into(),
     }
 }
 
@@ -1530,6 +1944,9 @@ pub enum TestEventKind<'a> {
         /// The script ID.
         script_id: ScriptId,
 
+        /// Whether the script's setup or teardown command is being run.
+        phase: ScriptPhase,
+
         /// The command to run.
         command: &'a str,
 
@@ -1545,6 +1962,9 @@ pub enum TestEventKind<'a> {
         /// The script ID.
         script_id: ScriptId,
 
+        /// Whether the script's setup or teardown command is being run.
+        phase: ScriptPhase,
+
         /// The command to run.
         command: &'a str,
 
@@ -1569,6 +1989,9 @@ pub enum TestEventKind<'a> {
         /// The script ID.
         script_id: ScriptId,
 
+        /// Whether the script's setup or teardown command was run.
+        phase: ScriptPhase,
+
         /// The command to run.
         command: &'a str,
 
@@ -1582,6 +2005,28 @@ pub enum TestEventKind<'a> {
         run_status: SetupScriptExecuteStatus,
     },
 
+    /// One of the commands of a wrapper script was run around a test.
+    WrapperScriptFinished {
+        /// The test instance the script was run around.
+        test_instance: TestInstance<'a>,
+
+        /// The script ID.
+        script_id: ScriptId,
+
+        /// Whether the script's setup command was run before the test, or its teardown command
+        /// after it.
+        phase: ScriptPhase,
+
+        /// The command that was run.
+        command: &'a str,
+
+        /// The arguments to the command.
+        args: &'a [String],
+
+        /// The execution status of the command.
+        run_status: SetupScriptExecuteStatus,
+    },
+
     // TODO: add events for BinaryStarted and BinaryFinished? May want a slightly different way to
     // do things, maybe a couple of reporter traits (one for the run as a whole and one for each
     // binary).
@@ -1615,6 +2060,25 @@ pub enum TestEventKind<'a> {
         will_terminate: bool,
     },
 
+    /// A test wrote some output.
+    ///
+    /// This event is only produced if output streaming is turned on through
+    /// [`TestRunnerBuilder::set_stream_output`](crate::runner::TestRunnerBuilder::set_stream_output).
+    /// Chunks aren't split at line boundaries.
+    TestOutputChunk {
+        /// The test instance that wrote the output.
+        test_instance: TestInstance<'a>,
+
+        /// Retry data.
+        retry_data: RetryData,
+
+        /// The stream the output was written to.
+        stream: TestOutputStream,
+
+        /// The output.
+        data: Bytes,
+    },
+
     /// A test attempt failed and will be retried in the future.
     ///
     /// This event does not occur on the final run of a failing test.
@@ -1658,6 +2122,10 @@ pub enum TestEventKind<'a> {
         /// Whether the JUnit report should store failure output for this test.
         junit_store_failure_output: bool,
 
+        /// Whether this test is quarantined. Failures in quarantined tests don't cause the run to
+        /// fail.
+        quarantined: bool,
+
         /// Information about all the runs for this test.
         run_statuses: ExecutionStatuses,
 
@@ -1739,6 +2207,10 @@ pub enum CancelReason {
     /// An error occurred while reporting results.
     ReportError,
 
+    /// Cancellation was requested through a
+    /// [`TestRunnerCancelHandle`](crate::runner::TestRunnerCancelHandle).
+    Requested,
+
     /// A termination signal (on Unix, SIGTERM or SIGHUP) was received.
     Signal,
 
@@ -1820,4 +2292,86 @@ mod tests {
             "status level is pass, overriding other settings"
         );
     }
+
+    #[test]
+    fn test_write_output_chunk() {
+        let test_list = TestList::with_fake_tests(&["tests::test_foo", "tests::test_bar"]);
+        let mut tests = test_list.iter_tests();
+        let (bar, foo) = (tests.next().unwrap(), tests.next().unwrap());
+        let config = NextestConfig::default_config("/fake/dir");
+        let profile = config.profile(NextestConfig::DEFAULT_PROFILE).unwrap();
+        let build_platforms = BuildPlatforms::new(None).unwrap();
+
+        let mut builder = TestReporterBuilder::default();
+        builder.set_stream_output(true);
+        let mut reporter_output = Vec::new();
+        let mut reporter = builder.build(
+            &test_list,
+            &profile.apply_build_platforms(&build_platforms),
+            ReporterStderr::Buffer(&mut reporter_output),
+        );
+
+        let mut write_chunk = |instance, stream, data: &'static [u8]| {
+            let mut buf = Vec::new();
+            reporter
+                .inner
+                .write_output_chunk(instance, stream, &Bytes::from_static(data), &mut buf)
+                .unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+
+        assert_eq!(
+            write_chunk(foo, TestOutputStream::Stdout, b"first "),
+            "",
+            "partial line isn't written"
+        );
+        assert_eq!(
+            write_chunk(bar, TestOutputStream::Stdout, b"other test\n"),
+            "[fake-package::fake-binary tests::test_bar] other test\n",
+            "other tests' lines are written independently"
+        );
+        assert_eq!(
+            write_chunk(foo, TestOutputStream::Stderr, b"error"),
+            "",
+            "partial line on another stream isn't written"
+        );
+        assert_eq!(
+            write_chunk(foo, TestOutputStream::Stdout, b"line\nsecond line\nthird"),
+            "[fake-package::fake-binary tests::test_foo] first line\n\
+             [fake-package::fake-binary tests::test_foo] second line\n",
+            "line split across chunks is joined, and several lines in a chunk are each written"
+        );
+
+        let mut buf = Vec::new();
+        reporter.inner.flush_partial_lines(foo, &mut buf).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "[fake-package::fake-binary tests::test_foo] third\n\
+             [fake-package::fake-binary tests::test_foo] error\n",
+            "partial lines are written out when the test finishes"
+        );
+
+        let mut buf = Vec::new();
+        reporter.inner.flush_partial_lines(foo, &mut buf).unwrap();
+        assert!(buf.is_empty(), "partial lines are only written out once");
+
+        let mut buf = Vec::new();
+        reporter
+            .inner
+            .write_output_chunk(
+                foo,
+                TestOutputStream::Stdout,
+                &Bytes::from(vec![b'a'; MAX_PARTIAL_LINE_LEN]),
+                &mut buf,
+            )
+            .unwrap();
+        assert_eq!(
+            buf.len(),
+            "[fake-package::fake-binary tests::test_foo] ".len() + MAX_PARTIAL_LINE_LEN + 1,
+            "overlong partial line is written out"
+        );
+        let mut buf = Vec::new();
+        reporter.inner.flush_partial_lines(foo, &mut buf).unwrap();
+        assert!(buf.is_empty(), "overlong partial line isn't buffered");
+    }
 }
@@ -11,20 +11,31 @@ use crate::{
     errors::WriteEventError,
     list::TestInstance,
     reporter::TestEventKind,
-    runner::{ExecuteStatus, ExecutionDescription, ExecutionResult},
+    run_store::{RunOutcomes, TestDurations},
+    runner::{ExecuteStatus, ExecutionDescription, ExecutionResult, RunStats},
 };
 use camino::Utf8PathBuf;
 use chrono::{DateTime, FixedOffset, Utc};
 use debug_ignore::DebugIgnore;
+use nextest_metadata::RustBinaryId;
 use once_cell::sync::Lazy;
-use quick_junit::{NonSuccessKind, Output, Report, TestCase, TestCaseStatus, TestRerun, TestSuite};
+use quick_junit::{
+    NonSuccessKind, Output, Property, Report, TestCase, TestCaseStatus, TestRerun, TestSuite,
+};
 use regex::{Regex, RegexBuilder};
-use std::{borrow::Cow, collections::HashMap, fs::File, time::SystemTime};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs::File,
+    time::{Duration, SystemTime},
+};
+use uuid::Uuid;
 
 #[derive(Clone, Debug)]
-#[allow(dead_code)]
 pub(crate) struct EventAggregator<'cfg> {
     store_dir: Utf8PathBuf,
+    outcomes: RunOutcomes,
+    durations: Vec<(RustBinaryId, String, Duration)>,
     // TODO: log information in a JSONable report (converting that to XML later) instead of directly
     // writing it to XML
     junit: Option<MetadataJunit<'cfg>>,
@@ -34,16 +45,72 @@ impl<'cfg> EventAggregator<'cfg> {
     pub(crate) fn new(profile: &NextestProfile<'cfg>) -> Self {
         Self {
             store_dir: profile.store_dir().to_owned(),
+            outcomes: RunOutcomes::default(),
+            durations: Vec::new(),
             junit: profile.junit().map(MetadataJunit::new),
         }
     }
 
     pub(crate) fn write_event(&mut self, event: TestEvent<'cfg>) -> Result<(), WriteEventError> {
+        match &event.kind {
+            TestEventKind::TestFinished {
+                test_instance,
+                run_statuses,
+                ..
+            } => {
+                self.outcomes.record_test(*test_instance, run_statuses);
+                self.durations.push((
+                    test_instance.suite_info.binary_id.clone(),
+                    test_instance.name.to_owned(),
+                    run_statuses.last_status().time_taken,
+                ));
+            }
+            TestEventKind::RunFinished {
+                run_id, run_stats, ..
+            } => {
+                self.write_run_store(*run_id, run_stats)?;
+            }
+            _ => {}
+        }
         if let Some(junit) = &mut self.junit {
             junit.write_event(event)?;
         }
         Ok(())
     }
+
+    fn write_run_store(
+        &mut self,
+        run_id: Uuid,
+        run_stats: &RunStats,
+    ) -> Result<(), WriteEventError> {
+        // Outcomes for tests that weren't run this time are carried over from previous runs.
+        let previous = match RunOutcomes::load(&self.store_dir) {
+            Ok(previous) => previous,
+            Err(error) => {
+                log::warn!("discarding previously recorded run outcomes: {error}");
+                None
+            }
+        };
+        self.outcomes.finish_run(previous, run_id, run_stats);
+        std::fs::create_dir_all(&self.store_dir).map_err(|error| WriteEventError::Fs {
+            file: self.store_dir.clone(),
+            error,
+        })?;
+        self.outcomes.write(&self.store_dir)?;
+
+        let mut durations = match TestDurations::load(&self.store_dir) {
+            Ok(durations) => durations.unwrap_or_default(),
+            Err(error) => {
+                log::warn!("discarding previously recorded test durations: {error}");
+                TestDurations::default()
+            }
+        };
+        for (binary_id, test_name, duration) in self.durations.drain(..) {
+            durations.record(&binary_id, &test_name, duration);
+        }
+        durations.write(&self.store_dir)?;
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -67,9 +134,10 @@ impl<'cfg> MetadataJunit<'cfg> {
             | TestEventKind::RunContinued { .. } => {}
             TestEventKind::SetupScriptStarted { .. }
             | TestEventKind::SetupScriptSlow { .. }
-            | TestEventKind::SetupScriptFinished { .. } => {}
+            | TestEventKind::SetupScriptFinished { .. }
+            | TestEventKind::WrapperScriptFinished { .. } => {}
             TestEventKind::TestStarted { .. } => {}
-            TestEventKind::TestSlow { .. } => {}
+            TestEventKind::TestSlow { .. } | TestEventKind::TestOutputChunk { .. } => {}
             TestEventKind::TestAttemptFailedWillRetry { .. }
             | TestEventKind::TestRetryStarted { .. } => {
                 // Retries are recorded in TestFinished.
@@ -79,6 +147,7 @@ impl<'cfg> MetadataJunit<'cfg> {
                 run_statuses,
                 junit_store_success_output,
                 junit_store_failure_output,
+                quarantined,
                 ..
             } => {
                 fn kind_ty(run_status: &ExecuteStatus) -> (NonSuccessKind, Cow<'static, str>) {
@@ -108,6 +177,10 @@ impl<'cfg> MetadataJunit<'cfg> {
                         ExecutionResult::Timeout => {
                             (NonSuccessKind::Failure, "test timeout".into())
                         }
+                        ExecutionResult::ResourceExceeded { kind } => (
+                            NonSuccessKind::Failure,
+                            format!("test exceeded {kind} limit").into(),
+                        ),
                         ExecutionResult::ExecFail => {
                             (NonSuccessKind::Error, "execution failure".into())
                         }
@@ -131,6 +204,21 @@ impl<'cfg> MetadataJunit<'cfg> {
                         last_status,
                         prior_statuses,
                     } => (TestCaseStatus::success(), last_status, prior_statuses),
+                    ExecutionDescription::Failure {
+                        first_status,
+                        retries,
+                        ..
+                    } if quarantined => {
+                        // Failures in quarantined tests are reported as skipped, so that they
+                        // don't fail CI jobs that consume the report. Note that skipped tests
+                        // can't have reruns.
+                        let (_, ty) = kind_ty(first_status);
+                        let mut testcase_status = TestCaseStatus::skipped();
+                        testcase_status
+                            .set_message("test failed, but is quarantined")
+                            .set_type(format!("quarantined {ty}"));
+                        (testcase_status, first_status, retries)
+                    }
                     ExecutionDescription::Failure {
                         first_status,
                         retries,
@@ -153,6 +241,13 @@ impl<'cfg> MetadataJunit<'cfg> {
                     if let Some(description) = stack_trace {
                         test_rerun.set_description(description);
                     }
+                    if let Some(stack_trace) = rerun
+                        .backtrace
+                        .as_ref()
+                        .or(rerun.timeout_diagnostics.as_ref())
+                    {
+                        test_rerun.set_stack_trace(stack_trace);
+                    }
                     test_rerun
                         .set_timestamp(to_datetime(rerun.start_time))
                         .set_time(rerun.time_taken)
@@ -168,6 +263,30 @@ impl<'cfg> MetadataJunit<'cfg> {
                     .set_classname(test_instance.suite_info.binary_id.as_str())
                     .set_timestamp(to_datetime(main_status.start_time))
                     .set_time(main_status.time_taken);
+                if quarantined {
+                    testcase.add_property(("nextest.quarantined", "true"));
+                }
+                if let Some(usage) = main_status.resource_usage {
+                    testcase
+                        .add_property(Property::new(
+                            "nextest.max-rss-bytes",
+                            usage.max_rss.to_string(),
+                        ))
+                        .add_property(Property::new(
+                            "nextest.user-time-secs",
+                            format!("{:.3}", usage.user_time.as_secs_f64()),
+                        ))
+                        .add_property(Property::new(
+                            "nextest.system-time-secs",
+                            format!("{:.3}", usage.system_time.as_secs_f64()),
+                        ));
+                }
+                if let Some(tmpdir) = &main_status.tmpdir {
+                    testcase.add_property(Property::new("nextest.tmpdir", tmpdir.as_str()));
+                }
+                for artifact in &main_status.artifacts {
+                    testcase.add_property(Property::new("nextest.artifact", artifact.as_str()));
+                }
 
                 // TODO: allure seems to want the output to be in a format where text files are
                 // written out to disk:
@@ -184,12 +303,40 @@ impl<'cfg> MetadataJunit<'cfg> {
                     }
                 }
 
-                if (junit_store_success_output && is_success)
-                    || (junit_store_failure_output && !is_success)
-                {
-                    testcase
-                        .set_system_out_lossy(&main_status.stdout)
-                        .set_system_err_lossy(&main_status.stderr);
+                // Failures can't have a separate stack trace, so any backtrace from a core dump,
+                // or the output of timeout diagnostics, is added to standard error instead.
+                let stack_trace = if is_success {
+                    None
+                } else {
+                    main_status
+                        .backtrace
+                        .as_ref()
+                        .or(main_status.timeout_diagnostics.as_ref())
+                };
+                let store_output = (junit_store_success_output && is_success)
+                    || (junit_store_failure_output && !is_success);
+                if store_output {
+                    testcase.set_system_out_lossy(&main_status.stdout);
+                }
+                match (store_output, stack_trace) {
+                    (true, Some(stack_trace)) => {
+                        let mut stderr = String::from_utf8_lossy(&main_status.stderr).into_owned();
+                        if !stderr.is_empty() {
+                            if !stderr.ends_with('\n') {
+                                stderr.push('\n');
+                            }
+                            stderr.push('\n');
+                        }
+                        stderr.push_str(stack_trace);
+                        testcase.set_system_err(stderr);
+                    }
+                    (true, None) => {
+                        testcase.set_system_err_lossy(&main_status.stderr);
+                    }
+                    (false, Some(stack_trace)) => {
+                        testcase.set_system_err(stack_trace);
+                    }
+                    (false, None) => {}
                 }
 
                 testsuite.add_test_case(testcase);
@@ -0,0 +1,605 @@
+// Copyright (c) The nextest Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Machine-readable output for test runs.
+//!
+//! The types serialized here are defined in `nextest-metadata`, so that external tools can
+//! deserialize them.
+
+use super::{CancelReason, TestEvent, TestEventKind};
+use crate::{
+    config::{ResourceLimitKind, ScriptPhase},
+    errors::WriteEventError,
+    list::TestInstance,
+    runner::{
+        AbortStatus, ExecuteStatus, ExecutionResult, RetryData, RunStats, SetupScriptExecuteStatus,
+        TestOutputStream,
+    },
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use nextest_metadata::{
+    AbortStatusSummary, CancelReasonSummary, ExecuteStatusSummary, ExecutionResultSummary,
+    ResourceLimitKindSummary, ResourceUsageSummary, RetryDataSummary, RunStatsSummary,
+    ScriptPhaseSummary, SetupScriptExecuteStatusSummary, TestEventKindSummary, TestEventSummary,
+    TestInstanceSummary, TestOutputStreamSummary, RUN_EVENTS_FORMAT_VERSION,
+};
+use std::{
+    collections::HashMap,
+    io::Write,
+    time::{Duration, SystemTime},
+};
+
+/// Writes test events as newline-delimited JSON.
+pub(crate) struct StructuredReporter<'a> {
+    writer: Box<dyn Write + Send + 'a>,
+    // The end of the last output chunk for each test and stream, if it stopped partway through a
+    // UTF-8 character. It's written out as part of the next chunk.
+    incomplete_chars: HashMap<(String, String, TestOutputStream), (RetryData, Vec<u8>)>,
+}
+
+impl<'a> StructuredReporter<'a> {
+    pub(crate) fn new(writer: Box<dyn Write + Send + 'a>) -> Self {
+        Self {
+            writer,
+            incomplete_chars: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn write_event(&mut self, event: &TestEvent<'_>) -> Result<(), WriteEventError> {
+        match &event.kind {
+            TestEventKind::TestOutputChunk {
+                test_instance,
+                retry_data,
+                stream,
+                data,
+            } => {
+                let key = output_key(test_instance, *stream);
+                let mut data = match self.incomplete_chars.remove(&key) {
+                    Some((_, mut incomplete)) => {
+                        incomplete.extend_from_slice(data);
+                        incomplete
+                    }
+                    None => data.to_vec(),
+                };
+                let complete_len = complete_utf8_len(&data);
+                if complete_len < data.len() {
+                    self.incomplete_chars
+                        .insert(key, (*retry_data, data.split_off(complete_len)));
+                }
+                if data.is_empty() {
+                    return Ok(());
+                }
+                self.write_summary(&TestEventSummary {
+                    format_version: RUN_EVENTS_FORMAT_VERSION,
+                    elapsed_secs: event.elapsed.as_secs_f64(),
+                    kind: output_chunk_summary(test_instance, *retry_data, *stream, &data),
+                })
+            }
+            TestEventKind::TestAttemptFailedWillRetry { test_instance, .. }
+            | TestEventKind::TestFinished { test_instance, .. } => {
+                self.flush_incomplete_chars(test_instance, event.elapsed)?;
+                self.write_summary(&event_summary(event))
+            }
+            _ => self.write_summary(&event_summary(event)),
+        }
+    }
+
+    /// Writes out the end of a test's output if it stopped partway through a UTF-8 character.
+    fn flush_incomplete_chars(
+        &mut self,
+        test_instance: &TestInstance<'_>,
+        elapsed: Duration,
+    ) -> Result<(), WriteEventError> {
+        for stream in [TestOutputStream::Stdout, TestOutputStream::Stderr] {
+            if let Some((retry_data, data)) = self
+                .incomplete_chars
+                .remove(&output_key(test_instance, stream))
+            {
+                self.write_summary(&TestEventSummary {
+                    format_version: RUN_EVENTS_FORMAT_VERSION,
+                    elapsed_secs: elapsed.as_secs_f64(),
+                    kind: output_chunk_summary(test_instance, retry_data, stream, &data),
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_summary(&mut self, summary: &TestEventSummary) -> Result<(), WriteEventError> {
+        // serde_json never produces newlines in compact mode, so each event is exactly one line.
+        serde_json::to_writer(&mut self.writer, summary)
+            .map_err(|err| WriteEventError::Io(err.into()))?;
+        self.writer.write_all(b"\n").map_err(WriteEventError::Io)?;
+        // Flush after each event so that consumers see events as they happen.
+        self.writer.flush().map_err(WriteEventError::Io)
+    }
+}
+
+fn output_key(
+    test_instance: &TestInstance<'_>,
+    stream: TestOutputStream,
+) -> (String, String, TestOutputStream) {
+    (
+        test_instance.suite_info.binary_id.to_string(),
+        test_instance.name.to_owned(),
+        stream,
+    )
+}
+
+/// Returns the length of `data` without any incomplete UTF-8 character at its end.
+fn complete_utf8_len(data: &[u8]) -> usize {
+    // A UTF-8 character is at most 4 bytes long, so an incomplete one starts within the last 3
+    // bytes.
+    for (i, byte) in data.iter().rev().take(3).enumerate() {
+        match byte.leading_ones() {
+            // A continuation byte.
+            1 => continue,
+            // The first byte of a character longer than what's left of the data.
+            len @ 2..=4 if len as usize > i + 1 => return data.len() - i - 1,
+            _ => break,
+        }
+    }
+    data.len()
+}
+
+fn event_summary(event: &TestEvent<'_>) -> TestEventSummary {
+    TestEventSummary {
+        format_version: RUN_EVENTS_FORMAT_VERSION,
+        elapsed_secs: event.elapsed.as_secs_f64(),
+        kind: event_kind_summary(&event.kind),
+    }
+}
+
+fn event_kind_summary(kind: &TestEventKind<'_>) -> TestEventKindSummary {
+    match kind {
+        TestEventKind::RunStarted { test_list, run_id } => TestEventKindSummary::RunStarted {
+            run_id: run_id.to_string(),
+            test_count: test_list.run_count(),
+        },
+        TestEventKind::SetupScriptStarted {
+            index,
+            total,
+            script_id,
+            phase,
+            command,
+            args,
+            no_capture,
+        } => TestEventKindSummary::SetupScriptStarted {
+            index: *index,
+            total: *total,
+            script_id: script_id.to_string(),
+            phase: script_phase_summary(*phase),
+            command: (*command).to_owned(),
+            args: args.to_vec(),
+            no_capture: *no_capture,
+        },
+        TestEventKind::SetupScriptSlow {
+            script_id,
+            phase,
+            command,
+            args,
+            elapsed,
+            will_terminate,
+        } => TestEventKindSummary::SetupScriptSlow {
+            script_id: script_id.to_string(),
+            phase: script_phase_summary(*phase),
+            command: (*command).to_owned(),
+            args: args.to_vec(),
+            elapsed_secs: elapsed.as_secs_f64(),
+            will_terminate: *will_terminate,
+        },
+        TestEventKind::SetupScriptFinished {
+            index,
+            total,
+            script_id,
+            phase,
+            command,
+            args,
+            no_capture,
+            run_status,
+        } => TestEventKindSummary::SetupScriptFinished {
+            index: *index,
+            total: *total,
+            script_id: script_id.to_string(),
+            phase: script_phase_summary(*phase),
+            command: (*command).to_owned(),
+            args: args.to_vec(),
+            no_capture: *no_capture,
+            run_status: setup_script_status_summary(run_status),
+        },
+        TestEventKind::WrapperScriptFinished {
+            test_instance,
+            script_id,
+            phase,
+            command,
+            args,
+            run_status,
+        } => TestEventKindSummary::WrapperScriptFinished {
+            test: test_instance_summary(test_instance),
+            script_id: script_id.to_string(),
+            phase: script_phase_summary(*phase),
+            command: (*command).to_owned(),
+            args: args.to_vec(),
+            run_status: setup_script_status_summary(run_status),
+        },
+        TestEventKind::TestStarted {
+            test_instance,
+            current_stats,
+            running,
+            cancel_state,
+        } => TestEventKindSummary::TestStarted {
+            test: test_instance_summary(test_instance),
+            current_stats: current_stats.into(),
+            running: *running,
+            cancel_state: cancel_state.map(cancel_reason_summary),
+        },
+        TestEventKind::TestSlow {
+            test_instance,
+            retry_data,
+            elapsed,
+            will_terminate,
+        } => TestEventKindSummary::TestSlow {
+            test: test_instance_summary(test_instance),
+            retry_data: retry_data_summary(*retry_data),
+            elapsed_secs: elapsed.as_secs_f64(),
+            will_terminate: *will_terminate,
+        },
+        TestEventKind::TestOutputChunk {
+            test_instance,
+            retry_data,
+            stream,
+            data,
+        } => output_chunk_summary(test_instance, *retry_data, *stream, data),
+        TestEventKind::TestAttemptFailedWillRetry {
+            test_instance,
+            run_status,
+            delay_before_next_attempt,
+            ..
+        } => TestEventKindSummary::TestAttemptFailedWillRetry {
+            test: test_instance_summary(test_instance),
+            run_status: execute_status_summary(run_status),
+            delay_before_next_attempt_secs: delay_before_next_attempt.as_secs_f64(),
+        },
+        TestEventKind::TestRetryStarted {
+            test_instance,
+            retry_data,
+        } => TestEventKindSummary::TestRetryStarted {
+            test: test_instance_summary(test_instance),
+            retry_data: retry_data_summary(*retry_data),
+        },
+        TestEventKind::TestFinished {
+            test_instance,
+            quarantined,
+            run_statuses,
+            current_stats,
+            running,
+            cancel_state,
+            ..
+        } => TestEventKindSummary::TestFinished {
+            test: test_instance_summary(test_instance),
+            quarantined: *quarantined,
+            run_statuses: run_statuses.iter().map(execute_status_summary).collect(),
+            current_stats: current_stats.into(),
+            running: *running,
+            cancel_state: cancel_state.map(cancel_reason_summary),
+        },
+        TestEventKind::TestSkipped {
+            test_instance,
+            reason,
+        } => TestEventKindSummary::TestSkipped {
+            test: test_instance_summary(test_instance),
+            reason: *reason,
+        },
+        TestEventKind::RunBeginCancel {
+            setup_scripts_running,
+            running,
+            reason,
+        } => TestEventKindSummary::RunBeginCancel {
+            setup_scripts_running: *setup_scripts_running,
+            running: *running,
+            reason: cancel_reason_summary(*reason),
+        },
+        TestEventKind::RunPaused {
+            setup_scripts_running,
+            running,
+        } => TestEventKindSummary::RunPaused {
+            setup_scripts_running: *setup_scripts_running,
+            running: *running,
+        },
+        TestEventKind::RunContinued {
+            setup_scripts_running,
+            running,
+        } => TestEventKindSummary::RunContinued {
+            setup_scripts_running: *setup_scripts_running,
+            running: *running,
+        },
+        TestEventKind::RunFinished {
+            run_id,
+            start_time,
+            elapsed,
+            run_stats,
+        } => TestEventKindSummary::RunFinished {
+            run_id: run_id.to_string(),
+            start_time: to_rfc3339(*start_time),
+            elapsed_secs: elapsed.as_secs_f64(),
+            run_stats: run_stats.into(),
+        },
+    }
+}
+
+fn output_chunk_summary(
+    test_instance: &TestInstance<'_>,
+    retry_data: RetryData,
+    stream: TestOutputStream,
+    data: &[u8],
+) -> TestEventKindSummary {
+    TestEventKindSummary::TestOutputChunk {
+        test: test_instance_summary(test_instance),
+        retry_data: retry_data_summary(retry_data),
+        stream: match stream {
+            TestOutputStream::Stdout => TestOutputStreamSummary::Stdout,
+            TestOutputStream::Stderr => TestOutputStreamSummary::Stderr,
+        },
+        data: String::from_utf8_lossy(data).into_owned(),
+    }
+}
+
+fn test_instance_summary(test_instance: &TestInstance<'_>) -> TestInstanceSummary {
+    TestInstanceSummary {
+        binary_id: test_instance.suite_info.binary_id.clone(),
+        name: test_instance.name.to_owned(),
+    }
+}
+
+fn retry_data_summary(retry_data: RetryData) -> RetryDataSummary {
+    RetryDataSummary {
+        attempt: retry_data.attempt,
+        total_attempts: retry_data.total_attempts,
+    }
+}
+
+fn execute_status_summary(status: &ExecuteStatus) -> ExecuteStatusSummary {
+    ExecuteStatusSummary {
+        retry_data: retry_data_summary(status.retry_data),
+        result: execution_result_summary(status.result),
+        start_time: to_rfc3339(status.start_time),
+        time_taken_secs: status.time_taken.as_secs_f64(),
+        resource_usage: status.resource_usage.map(|usage| ResourceUsageSummary {
+            max_rss_bytes: usage.max_rss,
+            user_time_secs: usage.user_time.as_secs_f64(),
+            system_time_secs: usage.system_time.as_secs_f64(),
+        }),
+        is_slow: status.is_slow,
+        delay_before_start_secs: status.delay_before_start.as_secs_f64(),
+        stdout: String::from_utf8_lossy(&status.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&status.stderr).into_owned(),
+    }
+}
+
+fn setup_script_status_summary(
+    status: &SetupScriptExecuteStatus,
+) -> SetupScriptExecuteStatusSummary {
+    SetupScriptExecuteStatusSummary {
+        result: execution_result_summary(status.result),
+        start_time: to_rfc3339(status.start_time),
+        time_taken_secs: status.time_taken.as_secs_f64(),
+        is_slow: status.is_slow,
+        env_count: status.env_count,
+        stdout: String::from_utf8_lossy(&status.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&status.stderr).into_owned(),
+    }
+}
+
+fn execution_result_summary(result: ExecutionResult) -> ExecutionResultSummary {
+    match result {
+        ExecutionResult::Pass => ExecutionResultSummary::Pass,
+        ExecutionResult::Leak => ExecutionResultSummary::Leak,
+        ExecutionResult::Fail {
+            abort_status,
+            leaked,
+        } => ExecutionResultSummary::Fail {
+            abort_status: abort_status.map(abort_status_summary),
+            leaked,
+        },
+        ExecutionResult::ExecFail => ExecutionResultSummary::ExecFail,
+        ExecutionResult::Timeout => ExecutionResultSummary::Timeout,
+        ExecutionResult::ResourceExceeded { kind } => ExecutionResultSummary::ResourceExceeded {
+            resource: match kind {
+                ResourceLimitKind::CpuTime => ResourceLimitKindSummary::CpuTime,
+            },
+        },
+    }
+}
+
+fn script_phase_summary(phase: ScriptPhase) -> ScriptPhaseSummary {
+    match phase {
+        ScriptPhase::Setup => ScriptPhaseSummary::Setup,
+        ScriptPhase::Teardown => ScriptPhaseSummary::Teardown,
+    }
+}
+
+fn abort_status_summary(abort_status: AbortStatus) -> AbortStatusSummary {
+    match abort_status {
+        #[cfg(unix)]
+        AbortStatus::UnixSignal(signal) => AbortStatusSummary::UnixSignal { signal },
+        #[cfg(windows)]
+        AbortStatus::WindowsNtStatus(nt_status) => {
+            AbortStatusSummary::WindowsNtStatus { code: nt_status.0 }
+        }
+    }
+}
+
+impl From<&RunStats> for RunStatsSummary {
+    fn from(stats: &RunStats) -> Self {
+        Self {
+            initial_run_count: stats.initial_run_count,
+            finished_count: stats.finished_count,
+            setup_scripts_initial_count: stats.setup_scripts_initial_count,
+            setup_scripts_finished_count: stats.setup_scripts_finished_count,
+            setup_scripts_passed: stats.setup_scripts_passed,
+            setup_scripts_failed: stats.setup_scripts_failed,
+            setup_scripts_exec_failed: stats.setup_scripts_exec_failed,
+            setup_scripts_timed_out: stats.setup_scripts_timed_out,
+            teardown_scripts_finished_count: stats.teardown_scripts_finished_count,
+            teardown_scripts_passed: stats.teardown_scripts_passed,
+            teardown_scripts_failed: stats.teardown_scripts_failed,
+            teardown_scripts_exec_failed: stats.teardown_scripts_exec_failed,
+            teardown_scripts_timed_out: stats.teardown_scripts_timed_out,
+            passed: stats.passed,
+            passed_slow: stats.passed_slow,
+            flaky: stats.flaky,
+            failed: stats.failed,
+            failed_slow: stats.failed_slow,
+            timed_out: stats.timed_out,
+            leaky: stats.leaky,
+            exec_failed: stats.exec_failed,
+            quarantined: stats.quarantined,
+            skipped: stats.skipped,
+        }
+    }
+}
+
+fn cancel_reason_summary(reason: CancelReason) -> CancelReasonSummary {
+    match reason {
+        CancelReason::SetupScriptFailure => CancelReasonSummary::SetupScriptFailure,
+        CancelReason::TestFailure => CancelReasonSummary::TestFailure,
+        CancelReason::ReportError => CancelReasonSummary::ReportError,
+        CancelReason::Requested => CancelReasonSummary::Requested,
+        CancelReason::Signal => CancelReasonSummary::Signal,
+        CancelReason::Interrupt => CancelReasonSummary::Interrupt,
+    }
+}
+
+fn to_rfc3339(system_time: SystemTime) -> String {
+    DateTime::<Utc>::from(system_time).to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::list::TestList;
+    use bytes::Bytes;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_run_events_ndjson() {
+        let test_list = TestList::empty();
+        let run_id = Uuid::new_v4();
+        let events = [
+            TestEvent {
+                elapsed: Duration::ZERO,
+                kind: TestEventKind::RunStarted {
+                    test_list: &test_list,
+                    run_id,
+                },
+            },
+            TestEvent {
+                elapsed: Duration::from_millis(1500),
+                kind: TestEventKind::RunFinished {
+                    run_id,
+                    start_time: SystemTime::UNIX_EPOCH,
+                    elapsed: Duration::from_millis(1500),
+                    run_stats: RunStats::default(),
+                },
+            },
+        ];
+
+        let mut buf = Vec::new();
+        let mut reporter = StructuredReporter::new(Box::new(&mut buf));
+        for event in &events {
+            reporter.write_event(event).expect("event written");
+        }
+        drop(reporter);
+
+        let output = String::from_utf8(buf).expect("output is valid UTF-8");
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines.len(), 2, "one line per event");
+
+        let started = TestEventSummary::parse_json(lines[0]).expect("valid event");
+        assert_eq!(started.format_version, RUN_EVENTS_FORMAT_VERSION);
+        assert_eq!(
+            started.kind,
+            TestEventKindSummary::RunStarted {
+                run_id: run_id.to_string(),
+                test_count: 0,
+            }
+        );
+
+        let finished = TestEventSummary::parse_json(lines[1]).expect("valid event");
+        assert_eq!(finished.elapsed_secs, 1.5);
+        assert_eq!(
+            finished.kind,
+            TestEventKindSummary::RunFinished {
+                run_id: run_id.to_string(),
+                start_time: "1970-01-01T00:00:00.000Z".to_owned(),
+                elapsed_secs: 1.5,
+                run_stats: RunStatsSummary::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_output_chunk_utf8() {
+        let test_list = TestList::with_fake_tests(&["tests::test_foo"]);
+        let test_instance = test_list.iter_tests().next().unwrap();
+        let retry_data = RetryData {
+            attempt: 1,
+            total_attempts: 1,
+        };
+        let output = "é 🦀\n".as_bytes();
+
+        let mut buf = Vec::new();
+        let mut reporter = StructuredReporter::new(Box::new(&mut buf));
+        // Split the output partway through both characters, and also end the stdout chunks
+        // partway through a character.
+        let chunks = [
+            (TestOutputStream::Stdout, &output[..1]),
+            (TestOutputStream::Stderr, &output[..2]),
+            (TestOutputStream::Stdout, &output[1..4]),
+            (TestOutputStream::Stdout, &output[4..7]),
+            (TestOutputStream::Stdout, &output[7..]),
+            (TestOutputStream::Stdout, &output[..1]),
+        ];
+        for (stream, data) in chunks {
+            let event = TestEvent {
+                elapsed: Duration::ZERO,
+                kind: TestEventKind::TestOutputChunk {
+                    test_instance,
+                    retry_data,
+                    stream,
+                    data: Bytes::copy_from_slice(data),
+                },
+            };
+            reporter.write_event(&event).expect("event written");
+        }
+        reporter
+            .flush_incomplete_chars(&test_instance, Duration::ZERO)
+            .expect("event written");
+        drop(reporter);
+
+        let output = String::from_utf8(buf).expect("output is valid UTF-8");
+        let data: Vec<_> = output
+            .lines()
+            .map(|line| {
+                match TestEventSummary::parse_json(line)
+                    .expect("valid event")
+                    .kind
+                {
+                    TestEventKindSummary::TestOutputChunk { stream, data, .. } => (stream, data),
+                    other => panic!("unexpected event: {other:?}"),
+                }
+            })
+            .collect();
+        assert_eq!(
+            data,
+            [
+                (TestOutputStreamSummary::Stderr, "é".to_owned()),
+                (TestOutputStreamSummary::Stdout, "é ".to_owned()),
+                (TestOutputStreamSummary::Stdout, "🦀".to_owned()),
+                (TestOutputStreamSummary::Stdout, "\n".to_owned()),
+                (TestOutputStreamSummary::Stdout, "\u{FFFD}".to_owned()),
+            ],
+            "characters split across chunks are kept whole, and the rest is written at the end"
+        );
+    }
+}
@@ -0,0 +1,384 @@
+// Copyright (c) The nextest Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Persistent storage for information about test runs.
+//!
+//! The main structures in this module are [`RunOutcomes`] and [`TestDurations`]. Both are stored
+//! within a profile's store directory and updated at the end of every test run.
+
+use crate::{
+    errors::RunStoreError,
+    list::TestInstance,
+    runner::{ExecutionDescription, ExecutionResult, ExecutionStatuses, RunStats},
+};
+use atomicwrites::{AtomicFile, OverwriteBehavior};
+use camino::{Utf8Path, Utf8PathBuf};
+use nextest_metadata::{RunStatsSummary, RustBinaryId};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Write,
+    time::Duration,
+};
+use uuid::Uuid;
+
+/// The outcomes of tests across previous runs, as recorded in a profile's store directory.
+///
+/// Each run updates the outcomes for the tests it ran. Tests that weren't run (for example,
+/// because they were filtered out) keep the outcome they had previously.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RunOutcomes {
+    /// The ID of the last run that updated these outcomes.
+    pub last_run_id: Option<Uuid>,
+
+    /// Statistics for the last run that updated these outcomes.
+    pub last_run_stats: Option<RunStatsSummary>,
+
+    /// A map of binary IDs to test names to outcomes.
+    pub tests: BTreeMap<RustBinaryId, BTreeMap<String, TestOutcome>>,
+}
+
+impl RunOutcomes {
+    /// The name of the file within the store directory that outcomes are stored in.
+    pub const FILE_NAME: &'static str = "run-outcomes.json";
+
+    /// Returns the path to the outcomes file within the given store directory.
+    pub fn path(store_dir: &Utf8Path) -> Utf8PathBuf {
+        store_dir.join(Self::FILE_NAME)
+    }
+
+    /// Loads outcomes from the given store directory.
+    ///
+    /// Returns `Ok(None)` if no outcomes have been recorded yet.
+    pub fn load(store_dir: &Utf8Path) -> Result<Option<Self>, RunStoreError> {
+        read_json(Self::path(store_dir))
+    }
+
+    /// Writes outcomes to the given store directory, replacing any existing ones.
+    pub fn write(&self, store_dir: &Utf8Path) -> Result<(), RunStoreError> {
+        write_json(Self::path(store_dir), self)
+    }
+
+    /// Records the outcome of a test that finished running.
+    pub fn record_test(
+        &mut self,
+        test_instance: TestInstance<'_>,
+        run_statuses: &ExecutionStatuses,
+    ) {
+        self.tests
+            .entry(test_instance.suite_info.binary_id.clone())
+            .or_default()
+            .insert(
+                test_instance.name.to_owned(),
+                TestOutcome::from_statuses(run_statuses),
+            );
+    }
+
+    /// Records the end of a run, merging the outcomes recorded in `self` on top of `previous`.
+    pub fn finish_run(&mut self, previous: Option<Self>, run_id: Uuid, run_stats: &RunStats) {
+        if let Some(mut previous) = previous {
+            for (binary_id, tests) in std::mem::take(&mut self.tests) {
+                previous.tests.entry(binary_id).or_default().extend(tests);
+            }
+            self.tests = previous.tests;
+        }
+        self.last_run_id = Some(run_id);
+        self.last_run_stats = Some(run_stats.into());
+    }
+
+    /// Returns the set of tests whose most recent outcome was a failure.
+    pub fn failed_tests(&self) -> FailedTests {
+        let tests = self
+            .tests
+            .iter()
+            .filter_map(|(binary_id, tests)| {
+                let failed: BTreeSet<_> = tests
+                    .iter()
+                    .filter(|(_, outcome)| outcome.is_failure())
+                    .map(|(name, _)| name.clone())
+                    .collect();
+                (!failed.is_empty()).then(|| (binary_id.clone(), failed))
+            })
+            .collect();
+        FailedTests { tests }
+    }
+}
+
+/// The outcome of a test in a run, as recorded in [`RunOutcomes`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+pub enum TestOutcome {
+    /// The test passed on the first attempt.
+    Passed,
+
+    /// The test failed at first but passed on a retry.
+    Flaky,
+
+    /// The test failed.
+    Failed,
+
+    /// The test timed out.
+    TimedOut,
+
+    /// The test could not be executed.
+    ExecFailed,
+}
+
+impl TestOutcome {
+    /// Computes the outcome of a test from its execution statuses.
+    pub fn from_statuses(run_statuses: &ExecutionStatuses) -> Self {
+        match run_statuses.describe() {
+            ExecutionDescription::Success { .. } => Self::Passed,
+            ExecutionDescription::Flaky { .. } => Self::Flaky,
+            ExecutionDescription::Failure { last_status, .. } => match last_status.result {
+                ExecutionResult::Timeout => Self::TimedOut,
+                ExecutionResult::ExecFail => Self::ExecFailed,
+                ExecutionResult::Pass
+                | ExecutionResult::Leak
+                | ExecutionResult::Fail { .. }
+                | ExecutionResult::ResourceExceeded { .. } => Self::Failed,
+            },
+        }
+    }
+
+    /// Returns true if this outcome is a failure that should be rerun with `--rerun-failed`.
+    pub fn is_failure(self) -> bool {
+        match self {
+            Self::Failed | Self::TimedOut | Self::ExecFailed => true,
+            Self::Passed | Self::Flaky => false,
+        }
+    }
+}
+
+/// The set of tests that failed in previous runs.
+///
+/// Returned by [`RunOutcomes::failed_tests`], and used to narrow down the tests run by a
+/// [`TestFilterBuilder`](crate::test_filter::TestFilterBuilder).
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FailedTests {
+    tests: BTreeMap<RustBinaryId, BTreeSet<String>>,
+}
+
+impl FailedTests {
+    /// Returns true if no tests failed.
+    pub fn is_empty(&self) -> bool {
+        self.tests.is_empty()
+    }
+
+    /// Returns the number of tests that failed.
+    pub fn len(&self) -> usize {
+        self.tests.values().map(|tests| tests.len()).sum()
+    }
+
+    /// Returns true if any test in this binary failed.
+    pub fn contains_binary(&self, binary_id: &RustBinaryId) -> bool {
+        self.tests.contains_key(binary_id)
+    }
+
+    /// Returns true if this test failed.
+    pub fn contains(&self, binary_id: &RustBinaryId, test_name: &str) -> bool {
+        self.tests
+            .get(binary_id)
+            .is_some_and(|tests| tests.contains(test_name))
+    }
+}
+
+/// Durations of tests across previous runs, as recorded in a profile's store directory.
+///
+/// For each test, an expected duration is maintained as a moving average of the time taken by
+/// recent runs, weighted towards more recent ones.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TestDurations {
+    tests: BTreeMap<RustBinaryId, BTreeMap<String, DurationRecord>>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct DurationRecord {
+    expected_secs: f64,
+    samples: usize,
+}
+
+impl TestDurations {
+    /// The name of the file within the store directory that durations are stored in.
+    pub const FILE_NAME: &'static str = "test-durations.json";
+
+    /// The number of samples after which older durations stop being weighted equally with newer
+    /// ones.
+    const WINDOW: usize = 10;
+
+    /// Returns the path to the durations file within the given store directory.
+    pub fn path(store_dir: &Utf8Path) -> Utf8PathBuf {
+        store_dir.join(Self::FILE_NAME)
+    }
+
+    /// Loads durations from the given store directory.
+    ///
+    /// Returns `Ok(None)` if no durations have been recorded yet.
+    pub fn load(store_dir: &Utf8Path) -> Result<Option<Self>, RunStoreError> {
+        read_json(Self::path(store_dir))
+    }
+
+    /// Reads durations from a file at the given path, in the same format as the durations file
+    /// within a store directory.
+    ///
+    /// Unlike [`Self::load`], it is an error for the file to not exist.
+    pub fn read_from_path(path: &Utf8Path) -> Result<Self, RunStoreError> {
+        let contents = std::fs::read_to_string(path).map_err(|error| RunStoreError::Read {
+            path: path.to_owned(),
+            error,
+        })?;
+        serde_json::from_str(&contents).map_err(|error| RunStoreError::Parse {
+            path: path.to_owned(),
+            error,
+        })
+    }
+
+    /// Writes durations to the given store directory, replacing any existing ones.
+    pub fn write(&self, store_dir: &Utf8Path) -> Result<(), RunStoreError> {
+        write_json(Self::path(store_dir), self)
+    }
+
+    /// Returns true if no durations have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.tests.is_empty()
+    }
+
+    /// Returns the expected duration of the given test, if any durations have been recorded for
+    /// it.
+    pub fn expected(&self, binary_id: &RustBinaryId, test_name: &str) -> Option<Duration> {
+        let record = self.tests.get(binary_id)?.get(test_name)?;
+        Duration::try_from_secs_f64(record.expected_secs).ok()
+    }
+
+    /// Records a new duration for the given test, updating its expected duration.
+    pub fn record(&mut self, binary_id: &RustBinaryId, test_name: &str, duration: Duration) {
+        let record = self
+            .tests
+            .entry(binary_id.clone())
+            .or_default()
+            .entry(test_name.to_owned())
+            .or_insert(DurationRecord {
+                expected_secs: 0.0,
+                samples: 0,
+            });
+        record.samples = (record.samples + 1).min(Self::WINDOW);
+        record.expected_secs +=
+            (duration.as_secs_f64() - record.expected_secs) / record.samples as f64;
+    }
+}
+
+fn read_json<T: DeserializeOwned>(path: Utf8PathBuf) -> Result<Option<T>, RunStoreError> {
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(RunStoreError::Read { path, error }),
+    };
+    serde_json::from_str(&contents)
+        .map(Some)
+        .map_err(|error| RunStoreError::Parse { path, error })
+}
+
+fn write_json<T: Serialize>(path: Utf8PathBuf, value: &T) -> Result<(), RunStoreError> {
+    let file = AtomicFile::new(&path, OverwriteBehavior::AllowOverwrite);
+    file.write(|f| {
+        serde_json::to_writer_pretty(&mut *f, value).map_err(std::io::Error::from)?;
+        f.write_all(b"\n")
+    })
+    .map_err(|error| RunStoreError::Write {
+        path,
+        error: error.into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use camino_tempfile::Utf8TempDir;
+
+    #[test]
+    fn test_outcomes_roundtrip() {
+        let dir = Utf8TempDir::new().unwrap();
+        assert_eq!(
+            RunOutcomes::load(dir.path()).unwrap(),
+            None,
+            "no outcomes recorded yet"
+        );
+
+        let binary_id = RustBinaryId::new("my-package::my-test");
+        let mut previous = RunOutcomes::default();
+        previous
+            .tests
+            .entry(binary_id.clone())
+            .or_default()
+            .extend([
+                ("test_fixed".to_owned(), TestOutcome::Failed),
+                ("test_not_rerun".to_owned(), TestOutcome::TimedOut),
+                ("test_passed".to_owned(), TestOutcome::Passed),
+            ]);
+
+        let mut outcomes = RunOutcomes::default();
+        outcomes
+            .tests
+            .entry(binary_id.clone())
+            .or_default()
+            .insert("test_fixed".to_owned(), TestOutcome::Passed);
+        let run_id = Uuid::new_v4();
+        outcomes.finish_run(Some(previous), run_id, &RunStats::default());
+        outcomes.write(dir.path()).unwrap();
+
+        let loaded = RunOutcomes::load(dir.path())
+            .unwrap()
+            .expect("outcomes were recorded");
+        assert_eq!(loaded, outcomes, "outcomes round-tripped");
+        assert_eq!(loaded.last_run_id, Some(run_id));
+
+        let failed = loaded.failed_tests();
+        assert_eq!(failed.len(), 1, "only one test is still failing");
+        assert!(failed.contains(&binary_id, "test_not_rerun"));
+        assert!(!failed.contains(&binary_id, "test_fixed"));
+        assert!(!failed.contains(&binary_id, "test_passed"));
+    }
+
+    #[test]
+    fn test_durations_moving_average() {
+        let dir = Utf8TempDir::new().unwrap();
+        let binary_id = RustBinaryId::new("my-package::my-test");
+
+        let mut durations = TestDurations::default();
+        assert_eq!(durations.expected(&binary_id, "test_foo"), None);
+
+        durations.record(&binary_id, "test_foo", Duration::from_secs(2));
+        assert_eq!(
+            durations.expected(&binary_id, "test_foo"),
+            Some(Duration::from_secs(2)),
+            "first sample is used as is"
+        );
+        durations.record(&binary_id, "test_foo", Duration::from_secs(4));
+        assert_eq!(
+            durations.expected(&binary_id, "test_foo"),
+            Some(Duration::from_secs(3)),
+            "samples within the window are averaged"
+        );
+
+        // Once the window is full, new samples are weighted at 1/WINDOW.
+        for _ in 0..TestDurations::WINDOW {
+            durations.record(&binary_id, "test_foo", Duration::from_secs(3));
+        }
+        durations.record(&binary_id, "test_foo", Duration::from_secs(13));
+        assert_eq!(
+            durations.expected(&binary_id, "test_foo"),
+            Some(Duration::from_secs(4)),
+        );
+
+        durations.write(dir.path()).unwrap();
+        let loaded = TestDurations::load(dir.path())
+            .unwrap()
+            .expect("durations were recorded");
+        assert_eq!(loaded, durations, "durations round-tripped");
+    }
+}
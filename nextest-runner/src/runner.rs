@@ -7,13 +7,15 @@
 
 use crate::{
     config::{
-        NextestProfile, RetryPolicy, ScriptConfig, ScriptId, SetupScript, SetupScriptEnvMap,
-        SetupScriptExecuteData, SlowTimeout, TestGroup, TestSettings, TestThreads,
+        ArtifactGlobs, NextestProfile, OutputOverflow, ResourceLimitKind, ResourceLimits,
+        RetryPolicy, ScriptConfig, ScriptId, ScriptKind, ScriptPhase, SetupScript,
+        SetupScriptEnvMap, SetupScriptExecuteData, SlowTimeout, TestGroup, TestSettings,
+        TestThreads, TimeoutDiagnostics,
     },
     double_spawn::DoubleSpawnInfo,
     errors::{
         CollectTestOutputError, ConfigureHandleInheritanceError, RunTestError, SetupScriptError,
-        TestRunnerBuildError,
+        StressConditionParseError, TestRunnerBuildError,
     },
     list::{TestExecuteContext, TestInstance, TestList},
     reporter::{
@@ -24,31 +26,41 @@ use crate::{
     time::{PausableSleep, StopwatchEnd, StopwatchStart},
 };
 use async_scoped::TokioScope;
-use bytes::{Bytes, BytesMut};
+use bytes::{Buf, Bytes, BytesMut};
+use camino::{Utf8Path, Utf8PathBuf};
+use camino_tempfile::Utf8TempDir;
 use display_error_chain::DisplayErrorChain;
 use future_queue::StreamExt;
 use futures::{future::try_join, prelude::*};
 use nextest_metadata::{FilterMatch, MismatchReason};
 use rand::{distributions::OpenClosed01, thread_rng, Rng};
 use std::{
+    cmp::Reverse,
+    collections::HashMap,
     convert::Infallible,
     fmt::Write,
+    hash::Hasher,
     marker::PhantomData,
     num::NonZeroUsize,
     pin::Pin,
     process::{ExitStatus, Stdio},
+    str::FromStr,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
     },
-    time::{Duration, SystemTime},
+    time::{Duration, Instant, SystemTime},
 };
 use tokio::{
-    io::{AsyncRead, AsyncReadExt},
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
     process::Child,
     runtime::Runtime,
-    sync::{broadcast, mpsc::UnboundedSender},
+    sync::{
+        broadcast,
+        mpsc::{UnboundedReceiver, UnboundedSender},
+    },
 };
+use twox_hash::XxHash64;
 use uuid::Uuid;
 
 #[derive(Debug)]
@@ -124,9 +136,11 @@ impl Iterator for BackoffIter {
 #[derive(Debug, Default)]
 pub struct TestRunnerBuilder {
     no_capture: bool,
+    stream_output: bool,
     retries: Option<RetryPolicy>,
     fail_fast: Option<bool>,
     test_threads: Option<TestThreads>,
+    stress: Option<StressCondition>,
 }
 
 impl TestRunnerBuilder {
@@ -138,6 +152,15 @@ impl TestRunnerBuilder {
         self
     }
 
+    /// Sets whether test output is streamed while tests run.
+    ///
+    /// In this mode, output is still captured, but each chunk of output is also reported through a
+    /// [`TestEventKind::TestOutputChunk`] event as soon as it's read.
+    pub fn set_stream_output(&mut self, stream_output: bool) -> &mut Self {
+        self.stream_output = stream_output;
+        self
+    }
+
     /// Sets the number of retries for this test runner.
     pub fn set_retries(&mut self, retries: RetryPolicy) -> &mut Self {
         self.retries = Some(retries);
@@ -156,6 +179,15 @@ impl TestRunnerBuilder {
         self
     }
 
+    /// Runs each test repeatedly according to the given condition, to detect flaky tests.
+    ///
+    /// In stress mode, retries are disabled, and fail-fast is off unless it is set explicitly with
+    /// [`Self::set_fail_fast`].
+    pub fn set_stress(&mut self, stress: StressCondition) -> &mut Self {
+        self.stress = Some(stress);
+        self
+    }
+
     /// Creates a new test runner.
     pub fn build<'a>(
         self,
@@ -172,21 +204,29 @@ impl TestRunnerBuilder {
                 .unwrap_or_else(|| profile.test_threads())
                 .compute(),
         };
-        let fail_fast = self.fail_fast.unwrap_or_else(|| profile.fail_fast());
+        let fail_fast = match self.stress {
+            // The point of stress mode is to find out how often tests fail, so don't stop at the
+            // first failure unless asked to.
+            Some(_) => self.fail_fast.unwrap_or(false),
+            None => self.fail_fast.unwrap_or_else(|| profile.fail_fast()),
+        };
 
         let runtime = Runtime::new().map_err(TestRunnerBuildError::TokioRuntimeCreate)?;
         let _guard = runtime.enter();
 
         // This must be called from within the guard.
         let handler = handler_kind.build()?;
+        let (cancel_sender, cancel_receiver) = tokio::sync::mpsc::unbounded_channel();
 
         Ok(TestRunner {
             inner: TestRunnerInner {
                 no_capture: self.no_capture,
+                stream_output: self.stream_output,
                 profile,
                 test_threads,
                 force_retries: self.retries,
                 fail_fast,
+                stress: self.stress,
                 test_list,
                 double_spawn,
                 target_runner,
@@ -194,6 +234,8 @@ impl TestRunnerBuilder {
                 run_id: Uuid::new_v4(),
             },
             handler,
+            cancel_sender,
+            cancel_receiver,
         })
     }
 }
@@ -205,9 +247,18 @@ impl TestRunnerBuilder {
 pub struct TestRunner<'a> {
     inner: TestRunnerInner<'a>,
     handler: SignalHandler,
+    cancel_sender: UnboundedSender<()>,
+    cancel_receiver: UnboundedReceiver<()>,
 }
 
 impl<'a> TestRunner<'a> {
+    /// Returns a handle that can be used to cancel this test run from another thread.
+    pub fn cancel_handle(&self) -> TestRunnerCancelHandle {
+        TestRunnerCancelHandle {
+            sender: self.cancel_sender.clone(),
+        }
+    }
+
     /// Executes the listed tests, each one in its own process.
     ///
     /// The callback is called with the results of each test.
@@ -231,7 +282,9 @@ impl<'a> TestRunner<'a> {
         F: FnMut(TestEvent<'a>) -> Result<(), E> + Send,
         E: Send,
     {
-        let run_stats = self.inner.try_execute(&mut self.handler, callback);
+        let run_stats =
+            self.inner
+                .try_execute(&mut self.handler, &mut self.cancel_receiver, callback);
         // On Windows, the stdout and stderr futures might spawn processes that keep the runner
         // stuck indefinitely if it's dropped the normal way. Shut it down aggressively, being OK
         // with leaked resources.
@@ -240,14 +293,35 @@ impl<'a> TestRunner<'a> {
     }
 }
 
+/// A handle used to cancel a test run from outside the runner.
+///
+/// Created using [`TestRunner::cancel_handle`]. Canceling a run has the same effect as a
+/// termination signal: no new tests are started, and running tests are asked to exit.
+#[derive(Clone, Debug)]
+pub struct TestRunnerCancelHandle {
+    sender: UnboundedSender<()>,
+}
+
+impl TestRunnerCancelHandle {
+    /// Requests cancellation of the test run, with [`CancelReason::Requested`].
+    ///
+    /// Does nothing if the run has already finished.
+    pub fn cancel(&self) {
+        // The receiver is gone if the run has finished: ignore the error.
+        let _ = self.sender.send(());
+    }
+}
+
 #[derive(Debug)]
 struct TestRunnerInner<'a> {
     no_capture: bool,
+    stream_output: bool,
     profile: &'a NextestProfile<'a>,
     test_threads: usize,
     // This is Some if the user specifies a retry policy over the command-line.
     force_retries: Option<RetryPolicy>,
     fail_fast: bool,
+    stress: Option<StressCondition>,
     test_list: &'a TestList<'a>,
     double_spawn: DoubleSpawnInfo,
     target_runner: TargetRunner,
@@ -259,6 +333,7 @@ impl<'a> TestRunnerInner<'a> {
     fn try_execute<E, F>(
         &self,
         signal_handler: &mut SignalHandler,
+        cancel_receiver: &mut UnboundedReceiver<()>,
         callback: F,
     ) -> Result<RunStats, E>
     where
@@ -273,6 +348,11 @@ impl<'a> TestRunnerInner<'a> {
         let canceled = AtomicBool::new(false);
         let canceled_ref = &canceled;
 
+        // Held by setup scripts that write to the terminal, so that their output isn't
+        // interleaved.
+        let terminal_lock = tokio::sync::Mutex::new(());
+        let terminal_lock_ref = &terminal_lock;
+
         let mut ctx = CallbackContext::new(
             callback,
             self.run_id,
@@ -308,6 +388,7 @@ impl<'a> TestRunnerInner<'a> {
             let exec_cancellation_sender = cancellation_sender.clone();
             let exec_fut = async move {
                 let mut signals_done = false;
+                let mut cancel_done = false;
 
                 loop {
                     let internal_event = tokio::select! {
@@ -329,6 +410,11 @@ impl<'a> TestRunnerInner<'a> {
                                 }
                             }
                         },
+                        _ = cancel_receiver.recv(), if !cancel_done => {
+                            // Only the first cancellation request matters.
+                            cancel_done = true;
+                            InternalEvent::CancelRequested
+                        },
                     };
 
                     match ctx_mut.handle_event(internal_event) {
@@ -429,65 +515,132 @@ impl<'a> TestRunnerInner<'a> {
             scope.spawn_cancellable(exec_fut, || ());
 
             {
-                let setup_scripts = self.profile.setup_scripts(self.test_list);
+                let mut setup_script_data = SetupScriptExecuteData::new();
+
+                // Wrapper scripts are run around each test, not up front.
+                let (wrapper_scripts, setup_scripts): (Vec<_>, Vec<_>) = self
+                    .profile
+                    .setup_scripts(self.test_list)
+                    .into_iter()
+                    .partition(|script| script.config.kind == ScriptKind::Wrapper);
+                for script in wrapper_scripts {
+                    setup_script_data.add_wrapper(script);
+                }
+
                 let total = setup_scripts.len();
                 log::debug!("running {} setup scripts", total);
 
-                let mut setup_script_data = SetupScriptExecuteData::new();
-
-                // Run setup scripts one by one.
+                // Each script waits for the scripts it depends on to finish, and is skipped if any
+                // of them didn't succeed. Scripts are ordered so that dependencies come first.
+                //
+                // Unless setup-scripts-parallel is set, each script also waits for the one before
+                // it, so that scripts run one at a time in the order they're listed. Otherwise,
+                // scripts that don't capture both stdout and stderr write directly to the
+                // terminal, so they're still run one at a time.
+                let parallel = self.profile.setup_scripts_parallel();
+                let (result_sender, mut result_receiver) = tokio::sync::mpsc::unbounded_channel();
+                let mut completions = HashMap::new();
+                let mut previous_completion = None;
                 for (index, script) in setup_scripts.into_iter().enumerate() {
                     let this_run_sender = run_sender.clone();
+                    let this_result_sender = result_sender.clone();
                     let (completion_sender, completion_receiver) = tokio::sync::oneshot::channel();
 
+                    let mut dependencies: Vec<_> = script
+                        .config
+                        .depends_on
+                        .iter()
+                        .map(|dependency| {
+                            completions
+                                .get(dependency)
+                                .cloned()
+                                .expect("dependencies are ordered before their dependents")
+                        })
+                        .collect();
+                    let completion_receiver = completion_receiver.shared();
+                    if !parallel {
+                        dependencies
+                            .extend(previous_completion.replace(completion_receiver.clone()));
+                    }
+                    completions.insert(script.id.clone(), completion_receiver);
+
                     let script_id = script.id.clone();
                     let config = script.config;
+                    let writes_to_terminal =
+                        self.no_capture || !config.capture_stdout || !config.capture_stderr;
 
                     let script_fut = async move {
+                        let dependencies_succeeded = future::join_all(dependencies)
+                            .await
+                            .into_iter()
+                            .all(|res| matches!(res, Ok(true)));
+
+                        let _terminal_guard = if writes_to_terminal {
+                            Some(terminal_lock_ref.lock().await)
+                        } else {
+                            None
+                        };
+
                         // Subscribe to the receiver *before* checking canceled_ref. The ordering is
                         // important to avoid race conditions with the code that first sets
                         // canceled_ref and then sends the notification.
                         let mut this_forward_receiver = forward_sender_ref.subscribe();
 
-                        if canceled_ref.load(Ordering::Acquire) {
+                        if !dependencies_succeeded || canceled_ref.load(Ordering::Acquire) {
                             // Check for test cancellation.
+                            _ = completion_sender.send(false);
+                            _ = this_result_sender.send((index, None));
                             return;
                         }
 
                         let _ = this_run_sender.send(InternalTestEvent::SetupScriptStarted {
                             script_id: script_id.clone(),
                             config,
+                            phase: ScriptPhase::Setup,
                             index,
                             total,
                         });
 
                         let (status, env_map) = self
-                            .run_setup_script(&script, &this_run_sender, &mut this_forward_receiver)
+                            .run_setup_script(
+                                &script,
+                                ScriptPhase::Setup,
+                                None,
+                                None,
+                                &this_run_sender,
+                                &mut this_forward_receiver,
+                            )
                             .await;
                         let status = status.into_external();
 
                         let _ = this_run_sender.send(InternalTestEvent::SetupScriptFinished {
                             script_id,
                             config,
+                            phase: ScriptPhase::Setup,
                             index,
                             total,
                             status,
                         });
 
                         drain_forward_receiver(this_forward_receiver).await;
-                        _ = completion_sender.send(env_map.map(|env_map| (script, env_map)));
+                        _ = completion_sender.send(env_map.is_some());
+                        _ = this_result_sender
+                            .send((index, env_map.map(|env_map| (script, env_map))));
                     };
 
-                    // Run this setup script to completion.
                     scope.spawn_cancellable(script_fut, || ());
-                    let script_and_env_map = completion_receiver.blocking_recv().unwrap_or_else(|_| {
-                        // This should never happen.
-                        log::warn!("setup script future did not complete -- this is a bug, please report it");
-                        None
-                    });
-                    if let Some((script, env_map)) = script_and_env_map {
-                        setup_script_data.add_script(script, env_map);
-                    }
+                }
+                drop(result_sender);
+
+                // Wait for all setup scripts to finish, then record them in order so that
+                // teardown happens in the reverse order.
+                let mut results = Vec::with_capacity(total);
+                while let Some(result) = result_receiver.blocking_recv() {
+                    results.push(result);
+                }
+                results.sort_unstable_by_key(|(index, _)| *index);
+                for (script, env_map) in results.into_iter().filter_map(|(_, result)| result) {
+                    setup_script_data.add_script(script, env_map);
                 }
 
                 // groups is going to be passed to future_queue_grouped.
@@ -498,8 +651,23 @@ impl<'a> TestRunnerInner<'a> {
                     .map(|(group_name, config)| (group_name, config.max_threads.compute()));
 
                 let setup_script_data = Arc::new(setup_script_data);
+                let teardown_script_data = setup_script_data.clone();
+                let teardown_run_sender = run_sender.clone();
+
+                // Start the tests expected to take the longest first, so that they don't end up
+                // holding up the end of the run. The sort is stable, so tests without a recorded
+                // duration keep their usual order and run after the others.
+                let mut test_instances: Vec<_> = self.test_list.iter_tests().collect();
+                test_instances.sort_by_key(|test_instance| {
+                    Reverse(
+                        self.test_list.expected_duration(
+                            &test_instance.suite_info.binary_id,
+                            test_instance.name,
+                        ),
+                    )
+                });
 
-                let run_fut = futures::stream::iter(self.test_list.iter_tests())
+                let run_fut = futures::stream::iter(test_instances)
                     .map(move |test_instance| {
                         let this_run_sender = run_sender.clone();
                         let mut cancellation_receiver = cancellation_sender.subscribe();
@@ -545,6 +713,56 @@ impl<'a> TestRunnerInner<'a> {
                             let _ =
                                 this_run_sender.send(InternalTestEvent::Started { test_instance });
 
+                            if let Some(stress) = self.stress {
+                                // In stress mode, the test is run repeatedly regardless of whether
+                                // it passes, and each iteration is recorded as an attempt.
+                                let stress_start = Instant::now();
+                                let mut run_statuses = vec![];
+                                loop {
+                                    let attempt = run_statuses.len() + 1;
+                                    let retry_data = RetryData {
+                                        attempt,
+                                        total_attempts: stress
+                                            .total_iterations()
+                                            .unwrap_or(attempt),
+                                    };
+                                    let run_status = self
+                                        .run_test(
+                                            test_instance,
+                                            retry_data,
+                                            &settings,
+                                            &setup_script_data,
+                                            &this_run_sender,
+                                            &mut this_forward_receiver,
+                                            Duration::ZERO,
+                                        )
+                                        .await
+                                        .into_external(retry_data);
+                                    run_statuses.push(run_status);
+
+                                    if canceled_ref.load(Ordering::Acquire)
+                                        || !stress.should_continue(attempt, stress_start.elapsed())
+                                    {
+                                        break;
+                                    }
+                                }
+
+                                let _ = this_run_sender.send(InternalTestEvent::Finished {
+                                    test_instance,
+                                    success_output: settings.success_output(),
+                                    failure_output: settings.failure_output(),
+                                    junit_store_success_output: settings
+                                        .junit_store_success_output(),
+                                    junit_store_failure_output: settings
+                                        .junit_store_failure_output(),
+                                    quarantined: settings.quarantined(),
+                                    run_statuses: ExecutionStatuses::new_stress(run_statuses),
+                                });
+
+                                drain_forward_receiver(this_forward_receiver).await;
+                                return;
+                            }
+
                             let mut run_statuses = vec![];
                             let mut delay = Duration::ZERO;
                             loop {
@@ -626,6 +844,7 @@ impl<'a> TestRunnerInner<'a> {
                                 failure_output: settings.failure_output(),
                                 junit_store_success_output: settings.junit_store_success_output(),
                                 junit_store_failure_output: settings.junit_store_failure_output(),
+                                quarantined: settings.quarantined(),
                                 run_statuses: ExecutionStatuses::new(run_statuses),
                             });
 
@@ -636,13 +855,26 @@ impl<'a> TestRunnerInner<'a> {
                     // future_queue_grouped means tests are spawned in order but returned in
                     // any order.
                     .future_queue_grouped(self.test_threads, groups)
-                    .collect();
+                    .collect::<()>();
+
+                // Once all tests have finished or the run has been canceled, run teardown commands
+                // for setup scripts.
+                let run_fut = run_fut.then(move |()| async move {
+                    self.run_teardown_scripts(
+                        &teardown_script_data,
+                        &teardown_run_sender,
+                        forward_sender_ref,
+                    )
+                    .await;
+                });
 
                 // Run the stream to completion.
                 scope.spawn_cancellable(run_fut, || ());
             }
         });
 
+        self.prune_tmpdirs();
+
         match ctx.run_finished() {
             Ok(()) => {}
             Err(err) => {
@@ -662,17 +894,228 @@ impl<'a> TestRunnerInner<'a> {
     // Helper methods
     // ---
 
-    /// Run an individual setup script in its own process.
+    /// Returns the directory within which per-test temporary directories are created for this run.
+    fn tmpdir_root(&self) -> Utf8PathBuf {
+        self.profile
+            .store_dir()
+            .join("tmp")
+            .join(self.run_id.to_string())
+    }
+
+    /// Creates a fresh temporary directory for a test attempt.
+    fn create_test_tmpdir(&self, test: TestInstance<'a>) -> std::io::Result<Utf8TempDir> {
+        let parent = self
+            .tmpdir_root()
+            .join(sanitize_file_name(test.suite_info.binary_id.as_str()));
+        std::fs::create_dir_all(&parent)?;
+        camino_tempfile::Builder::new()
+            .prefix(&format!("{}-", sanitize_file_name(test.name)))
+            .tempdir_in(parent)
+    }
+
+    /// Copies files matching the test's `collect-artifacts` patterns into the store directory.
+    ///
+    /// Returns the paths the files were copied to. Errors are logged and otherwise ignored, since
+    /// they shouldn't change the result of the test.
+    fn collect_artifacts(
+        &self,
+        test: TestInstance<'a>,
+        retry_data: RetryData,
+        globs: &ArtifactGlobs,
+    ) -> Vec<Utf8PathBuf> {
+        if globs.is_empty() {
+            return Vec::new();
+        }
+
+        let dest_dir = self.artifact_dir(test, retry_data);
+        let mut collected = Vec::new();
+        for (source, relative) in globs.find(&test.suite_info.cwd) {
+            let dest = dest_dir.join(relative);
+            let res = dest
+                .parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|()| std::fs::copy(&source, &dest));
+            match res {
+                Ok(_) => collected.push(dest),
+                Err(error) => {
+                    log::warn!(
+                        "error collecting artifact {source} for {}: {error}",
+                        test.name
+                    )
+                }
+            }
+        }
+        collected
+    }
+
+    /// Moves or copies the core file for a crashed test into its artifact directory, and gets a
+    /// backtrace from it.
+    ///
+    /// Returns the new path of the core file and the backtrace, if any. As with other artifacts,
+    /// errors are logged and otherwise ignored.
+    async fn collect_core_dump(
+        &self,
+        test: TestInstance<'a>,
+        retry_data: RetryData,
+        exit_status: ExitStatus,
+        pid: u32,
+    ) -> Option<(Utf8PathBuf, Option<String>)> {
+        let core = crate::core_dump::find_core_file(exit_status, pid, &test.suite_info.cwd)?;
+        let dest_dir = self.artifact_dir(test, retry_data);
+        let dest = dest_dir.join(core.file_name()?);
+        // Core files can be large, so move them rather than copying them where possible. Core
+        // files outside the workspace, e.g. in a system crash directory, are left alone for other
+        // tools to find.
+        let move_core = core.starts_with(self.test_list.workspace_root());
+        let res = std::fs::create_dir_all(&dest_dir).and_then(|()| {
+            if !move_core {
+                return std::fs::copy(&core, &dest).map(|_| ());
+            }
+            std::fs::rename(&core, &dest).or_else(|_| {
+                std::fs::copy(&core, &dest)?;
+                std::fs::remove_file(&core)
+            })
+        });
+        if let Err(error) = res {
+            log::warn!(
+                "error collecting core file {core} for {}: {error}",
+                test.name
+            );
+            return None;
+        }
+
+        let backtrace = crate::core_dump::backtrace(&test.suite_info.binary_path, &dest).await;
+        Some((dest, backtrace))
+    }
+
+    /// Returns how much output is captured from a test attempt, and where output past that goes.
+    fn output_limits(
+        &self,
+        test: TestInstance<'a>,
+        retry_data: RetryData,
+        settings: &TestSettings,
+    ) -> OutputLimits {
+        let max_output_size = settings.max_output_size();
+        let spill = settings.output_overflow() == OutputOverflow::Spill;
+        let limit = |max_size: Option<u64>, stream: &str| OutputLimit {
+            max_size,
+            spill_path: (spill && (max_size.is_some() || max_output_size.total.is_some())).then(
+                || {
+                    self.artifact_dir(test, retry_data)
+                        .join(format!("{stream}.log"))
+                },
+            ),
+        };
+        OutputLimits {
+            stdout: limit(max_output_size.stdout, "stdout"),
+            stderr: limit(max_output_size.stderr, "stderr"),
+            total: max_output_size.total,
+        }
+    }
+
+    /// Returns the directory that artifacts for a test attempt are collected into.
+    fn artifact_dir(&self, test: TestInstance<'a>, retry_data: RetryData) -> Utf8PathBuf {
+        self.profile
+            .store_dir()
+            .join("artifacts")
+            .join(self.run_id.to_string())
+            .join(unique_file_name(test.suite_info.binary_id.as_str()))
+            .join(unique_file_name(test.name))
+            .join(retry_data.attempt.to_string())
+    }
+
+    /// Removes directories left empty once temporary directories for passing tests are deleted,
+    /// along with the temporary directories kept by all but the last [`KEPT_TMPDIR_RUNS`] runs.
+    fn prune_tmpdirs(&self) {
+        let root = self.tmpdir_root();
+        if let Ok(entries) = root.read_dir_utf8() {
+            for entry in entries.flatten() {
+                // This fails if the directory isn't empty, which is fine.
+                let _ = std::fs::remove_dir(entry.path());
+            }
+        }
+        let _ = std::fs::remove_dir(&root);
+
+        if let Some(parent) = root.parent() {
+            remove_old_run_dirs(parent, KEPT_TMPDIR_RUNS);
+        }
+    }
+
+    /// Runs teardown commands for setup scripts one by one.
+    ///
+    /// Unlike setup scripts and tests, teardown commands are run even if the run has been
+    /// canceled.
+    async fn run_teardown_scripts(
+        &self,
+        setup_script_data: &SetupScriptExecuteData<'a>,
+        run_sender: &UnboundedSender<InternalTestEvent<'a>>,
+        forward_sender: &broadcast::Sender<SignalForwardEvent>,
+    ) {
+        let scripts: Vec<_> = setup_script_data.teardown_scripts().collect();
+        let total = scripts.len();
+        log::debug!("running {} teardown scripts", total);
+
+        for (index, (script, env_map)) in scripts.into_iter().enumerate() {
+            let mut forward_receiver = forward_sender.subscribe();
+
+            let _ = run_sender.send(InternalTestEvent::SetupScriptStarted {
+                script_id: script.id.clone(),
+                config: script.config,
+                phase: ScriptPhase::Teardown,
+                index,
+                total,
+            });
+
+            let (status, _) = self
+                .run_setup_script(
+                    script,
+                    ScriptPhase::Teardown,
+                    Some(env_map),
+                    None,
+                    run_sender,
+                    &mut forward_receiver,
+                )
+                .await;
+
+            let _ = run_sender.send(InternalTestEvent::SetupScriptFinished {
+                script_id: script.id.clone(),
+                config: script.config,
+                phase: ScriptPhase::Teardown,
+                index,
+                total,
+                status: status.into_external(),
+            });
+
+            drain_forward_receiver(forward_receiver).await;
+        }
+    }
+
+    /// Run the setup or teardown command for an individual setup script in its own process.
+    ///
+    /// `env_map` is the environment exported by the setup command, and is only passed in for
+    /// teardown. `test` is the test being wrapped, for wrapper scripts.
+    #[allow(clippy::too_many_arguments)]
     async fn run_setup_script(
         &self,
         script: &SetupScript<'a>,
+        phase: ScriptPhase,
+        env_map: Option<&SetupScriptEnvMap>,
+        test: Option<TestInstance<'a>>,
         run_sender: &UnboundedSender<InternalTestEvent<'a>>,
         forward_receiver: &mut tokio::sync::broadcast::Receiver<SignalForwardEvent>,
     ) -> (InternalSetupScriptExecuteStatus, Option<SetupScriptEnvMap>) {
         let mut stopwatch = crate::time::stopwatch();
 
         match self
-            .run_setup_script_inner(script, &mut stopwatch, run_sender, forward_receiver)
+            .run_setup_script_inner(
+                script,
+                phase,
+                env_map,
+                test,
+                &mut stopwatch,
+                run_sender,
+                forward_receiver,
+            )
             .await
         {
             Ok((status, env_map)) => (status, env_map),
@@ -700,15 +1143,27 @@ impl<'a> TestRunnerInner<'a> {
     async fn run_setup_script_inner(
         &self,
         script: &SetupScript<'a>,
+        phase: ScriptPhase,
+        env_map: Option<&SetupScriptEnvMap>,
+        test: Option<TestInstance<'a>>,
         stopwatch: &mut StopwatchStart,
         run_sender: &UnboundedSender<InternalTestEvent<'a>>,
         forward_receiver: &mut tokio::sync::broadcast::Receiver<SignalForwardEvent>,
     ) -> Result<(InternalSetupScriptExecuteStatus, Option<SetupScriptEnvMap>), SetupScriptError>
     {
-        let mut cmd = script.make_command(&self.double_spawn, self.test_list)?;
+        let mut cmd = script.make_command(phase, &self.double_spawn, self.test_list)?;
         let command_mut = cmd.command_mut();
 
+        if let Some(env_map) = env_map {
+            env_map.apply(command_mut);
+        }
+
         command_mut.env("NEXTEST_RUN_ID", format!("{}", self.run_id));
+        if let Some(test) = test {
+            // Let wrapper scripts know which test they're running around.
+            command_mut.env("NEXTEST_BINARY_ID", test.suite_info.binary_id.as_str());
+            command_mut.env("NEXTEST_TEST_NAME", test.name);
+        }
         command_mut.stdin(Stdio::null());
         imp::set_process_group(command_mut);
 
@@ -754,8 +1209,10 @@ impl<'a> TestRunnerInner<'a> {
         // TODO: capture output
         let child_stdout = child.stdout.take();
         let child_stderr = child.stderr.take();
-        let mut stdout = bytes::BytesMut::new();
-        let mut stderr = bytes::BytesMut::new();
+        // Output from setup scripts isn't limited.
+        let output_limits = OutputLimits::default();
+        let mut stdout = CapturedOutput::new(&output_limits.stdout, output_limits.total);
+        let mut stderr = CapturedOutput::new(&output_limits.stderr, output_limits.total);
 
         let (res, leaked) = {
             // Set up futures for reading from stdout and stderr.
@@ -763,7 +1220,8 @@ impl<'a> TestRunnerInner<'a> {
                 child_stdout,
                 &mut stdout,
                 child_stderr,
-                &mut stderr
+                &mut stderr,
+                &|_, _| {},
             ));
             let mut collect_output_done = false;
 
@@ -792,6 +1250,7 @@ impl<'a> TestRunnerInner<'a> {
                             let _ = run_sender.send(InternalTestEvent::SetupScriptSlow {
                                 script_id: script.id.clone(),
                                 config: script.config,
+                                phase,
                                 // Pass in the slow timeout period times timeout_hit, since stopwatch.elapsed() tends to be
                                 // slightly longer.
                                 elapsed: timeout_hit * slow_timeout.period,
@@ -854,10 +1313,12 @@ impl<'a> TestRunnerInner<'a> {
 
         let output = res.map_err(SetupScriptError::Wait)?;
         let exit_status = output;
+        let (stdout, stderr) = finish_output(stdout, stderr, &output_limits, &|_, _| {}).await;
 
         let status = status.unwrap_or_else(|| create_execution_result(exit_status, leaked));
 
-        let env_map = if status.is_success() {
+        // Only the setup command can export environment variables.
+        let env_map = if status.is_success() && phase == ScriptPhase::Setup {
             Some(SetupScriptEnvMap::new(&env_path).await?)
         } else {
             None
@@ -865,8 +1326,8 @@ impl<'a> TestRunnerInner<'a> {
 
         Ok((
             InternalSetupScriptExecuteStatus {
-                stdout: stdout.freeze(),
-                stderr: stderr.freeze(),
+                stdout,
+                stderr,
                 result: status,
                 stopwatch_end: stopwatch.end(),
                 is_slow,
@@ -888,37 +1349,127 @@ impl<'a> TestRunnerInner<'a> {
         forward_receiver: &mut broadcast::Receiver<SignalForwardEvent>,
         delay_before_start: Duration,
     ) -> InternalExecuteStatus {
+        // Run the commands of any wrapper scripts enabled for this test, stopping at the first one
+        // that fails.
+        let query = test.to_test_query();
+        let wrappers: Vec<_> = setup_script_data.wrappers_for(&query).collect();
+        let mut wrapper_env_maps = Vec::with_capacity(wrappers.len());
+        let mut wrapper_error = None;
+        for script in &wrappers {
+            match self
+                .run_wrapper_script(
+                    test,
+                    script,
+                    ScriptPhase::Setup,
+                    None,
+                    run_sender,
+                    forward_receiver,
+                )
+                .await
+            {
+                Some(env_map) => wrapper_env_maps.push(env_map),
+                None => {
+                    wrapper_error = Some(RunTestError::WrapperScriptSetup {
+                        script_id: script.id.clone(),
+                    });
+                    break;
+                }
+            }
+        }
+
+        // Wrapper scripts are timed and reported separately, so the test's own timing, including
+        // its slow and terminate timeouts, only starts once they're done.
         let mut stopwatch = crate::time::stopwatch();
+        let mut status = match wrapper_error {
+            None => self
+                .run_test_inner(
+                    test,
+                    retry_data,
+                    &mut stopwatch,
+                    settings,
+                    setup_script_data,
+                    &wrapper_env_maps,
+                    run_sender,
+                    forward_receiver,
+                    delay_before_start,
+                )
+                .await
+                .unwrap_or_else(|error| {
+                    InternalExecuteStatus::exec_fail(error, &stopwatch, delay_before_start)
+                }),
+            Some(error) => InternalExecuteStatus::exec_fail(error, &stopwatch, delay_before_start),
+        };
 
-        match self
-            .run_test_inner(
-                test,
-                retry_data,
-                &mut stopwatch,
-                settings,
-                setup_script_data,
+        // Run teardown commands for the wrappers whose setup commands succeeded, in reverse order.
+        for (script, env_map) in wrappers.iter().zip(&wrapper_env_maps).rev() {
+            if script.config.teardown.is_none() {
+                continue;
+            }
+            let succeeded = self
+                .run_wrapper_script(
+                    test,
+                    script,
+                    ScriptPhase::Teardown,
+                    Some(env_map),
+                    run_sender,
+                    forward_receiver,
+                )
+                .await
+                .is_some();
+            if !succeeded && status.result.is_success() {
+                // A test that passed but left things in a state that couldn't be cleaned up isn't
+                // really a pass.
+                let error = RunTestError::WrapperScriptTeardown {
+                    script_id: script.id.clone(),
+                };
+                let mut stderr = bytes::BytesMut::from(&status.stderr[..]);
+                if !stderr.is_empty() && !stderr.ends_with(b"\n") {
+                    stderr.extend_from_slice(b"\n");
+                }
+                writeln!(&mut stderr, "{}", DisplayErrorChain::new(error)).unwrap();
+                status.stderr = stderr.freeze();
+                status.result = ExecutionResult::ExecFail;
+            }
+        }
+
+        status
+    }
+
+    /// Runs the setup or teardown command of a wrapper script around `test`, and reports it.
+    ///
+    /// Returns the environment exported by the command if it succeeded, and `None` otherwise.
+    async fn run_wrapper_script(
+        &self,
+        test: TestInstance<'a>,
+        script: &SetupScript<'a>,
+        phase: ScriptPhase,
+        env_map: Option<&SetupScriptEnvMap>,
+        run_sender: &UnboundedSender<InternalTestEvent<'a>>,
+        forward_receiver: &mut broadcast::Receiver<SignalForwardEvent>,
+    ) -> Option<SetupScriptEnvMap> {
+        let (status, new_env_map) = self
+            .run_setup_script(
+                script,
+                phase,
+                env_map,
+                Some(test),
                 run_sender,
                 forward_receiver,
-                delay_before_start,
             )
-            .await
-        {
-            Ok(run_status) => run_status,
-            Err(error) => {
-                // Put the error chain inside stderr.
-                let mut stderr = bytes::BytesMut::new();
-                writeln!(&mut stderr, "{}", DisplayErrorChain::new(error)).unwrap();
+            .await;
+        let status = status.into_external();
+        let succeeded = status.result.is_success();
+
+        let _ = run_sender.send(InternalTestEvent::WrapperScriptFinished {
+            test_instance: test,
+            script_id: script.id.clone(),
+            config: script.config,
+            phase,
+            status,
+        });
 
-                InternalExecuteStatus {
-                    stdout: Bytes::new(),
-                    stderr: stderr.freeze(),
-                    result: ExecutionResult::ExecFail,
-                    stopwatch_end: stopwatch.end(),
-                    is_slow: false,
-                    delay_before_start,
-                }
-            }
-        }
+        // Teardown commands don't export an environment.
+        succeeded.then(|| new_env_map.unwrap_or_default())
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -929,6 +1480,7 @@ impl<'a> TestRunnerInner<'a> {
         stopwatch: &mut StopwatchStart,
         settings: &TestSettings,
         setup_script_data: &SetupScriptExecuteData<'a>,
+        wrapper_env_maps: &[SetupScriptEnvMap],
         run_sender: &UnboundedSender<InternalTestEvent<'a>>,
         forward_receiver: &mut broadcast::Receiver<SignalForwardEvent>,
         delay_before_start: Duration,
@@ -937,15 +1489,34 @@ impl<'a> TestRunnerInner<'a> {
             double_spawn: &self.double_spawn,
             target_runner: &self.target_runner,
         };
-        let mut cmd = test.make_command(&ctx, self.test_list);
+        let tmpdir = self
+            .create_test_tmpdir(test)
+            .map_err(RunTestError::TmpDir)?;
+        let mut cmd = test.make_command(&ctx, self.test_list, tmpdir.path());
         let command_mut = cmd.command_mut();
 
         // Debug environment variable for testing.
         command_mut.env("__NEXTEST_ATTEMPT", format!("{}", retry_data.attempt));
         command_mut.env("NEXTEST_RUN_ID", format!("{}", self.run_id));
         command_mut.stdin(Stdio::null());
-        setup_script_data.apply(&test.to_test_query(), command_mut);
+        setup_script_data.apply(&test.to_test_query(), wrapper_env_maps, command_mut);
         imp::set_process_group(command_mut);
+        let resource_limits = settings.resource_limits();
+        imp::set_resource_limits(command_mut, resource_limits);
+        let collect_core_dumps = settings.collect_core_dumps();
+        if collect_core_dumps {
+            crate::core_dump::enable(command_mut);
+        }
+        let sandbox = if settings.isolation() {
+            Some(crate::isolation::isolate(
+                command_mut,
+                &test.suite_info.binary_path,
+                self.test_list.workspace_root(),
+                &self.test_list.rust_build_meta().target_directory,
+            )?)
+        } else {
+            None
+        };
 
         // If creating a job fails, we might be on an old system. Ignore this -- job objects are a
         // best-effort thing.
@@ -958,11 +1529,16 @@ impl<'a> TestRunnerInner<'a> {
                 .stderr(std::process::Stdio::piped());
         };
 
-        let mut child = cmd.spawn().map_err(RunTestError::Spawn)?;
+        let mut waiter = imp::ChildWaiter::new().map_err(RunTestError::Wait)?;
+        let mut child = cmd.spawn().map_err(|error| match &sandbox {
+            Some(sandbox) => sandbox.spawn_error(error),
+            None => RunTestError::Spawn(error),
+        })?;
 
         // If assigning the child to the job fails, ignore this. This can happen if the process has
         // exited.
         let _ = imp::assign_process_to_job(&child, job.as_ref());
+        let pid = child.id();
 
         let mut status: Option<ExecutionResult> = None;
         let slow_timeout = settings.slow_timeout();
@@ -974,18 +1550,37 @@ impl<'a> TestRunnerInner<'a> {
         let mut interval_sleep = std::pin::pin!(crate::time::pausable_sleep(slow_timeout.period));
 
         let mut timeout_hit = 0;
+        let mut timeout_diagnostics = None;
+        // The timeout-diagnostics command, while it's running. It's polled alongside everything
+        // else so that signals are still handled and the test's output is still read.
+        let mut timeout_diagnostics_fut = std::pin::pin!(None);
 
         let child_stdout = child.stdout.take();
         let child_stderr = child.stderr.take();
-        let mut stdout = bytes::BytesMut::new();
-        let mut stderr = bytes::BytesMut::new();
+        let output_limits = self.output_limits(test, retry_data, settings);
+        let mut stdout = CapturedOutput::new(&output_limits.stdout, output_limits.total);
+        let mut stderr = CapturedOutput::new(&output_limits.stderr, output_limits.total);
+
+        let on_output_chunk = |stream, data: &[u8]| {
+            if self.stream_output {
+                // The receiver is only gone if the run has finished, in which case the chunk can be
+                // dropped.
+                let _ = run_sender.send(InternalTestEvent::OutputChunk {
+                    test_instance: test,
+                    retry_data,
+                    stream,
+                    data: Bytes::copy_from_slice(data),
+                });
+            }
+        };
 
         let (res, leaked) = {
             let mut collect_output_fut = std::pin::pin!(collect_output(
                 child_stdout,
                 &mut stdout,
                 child_stderr,
-                &mut stderr
+                &mut stderr,
+                &on_output_chunk,
             ));
             let mut collect_output_done = false;
 
@@ -995,7 +1590,7 @@ impl<'a> TestRunnerInner<'a> {
                         collect_output_done = true;
                         res?;
                     }
-                    res = child.wait() => {
+                    res = waiter.wait(&mut child) => {
                         // The test finished executing.
                         break res;
                     }
@@ -1022,20 +1617,46 @@ impl<'a> TestRunnerInner<'a> {
                         }
 
                         if will_terminate {
-                            // attempt to terminate the slow test.
-                            // as there is a race between shutting down a slow test and its own completion
-                            // we silently ignore errors to avoid printing false warnings.
-                            imp::terminate_child(&mut child, TerminateMode::Timeout, forward_receiver, job.as_ref(), slow_timeout.grace_period).await;
                             status = Some(ExecutionResult::Timeout);
-                            if slow_timeout.grace_period.is_zero() {
-                                break child.wait().await;
+                            if let (Some(diagnostics), Some(pid)) = (settings.timeout_diagnostics(), pid) {
+                                // Before terminating the test, find out where it's stuck. The test
+                                // is terminated once the command finishes.
+                                timeout_diagnostics_fut.set(Some(run_timeout_diagnostics(diagnostics, pid)));
+                            } else {
+                                // attempt to terminate the slow test.
+                                // as there is a race between shutting down a slow test and its own completion
+                                // we silently ignore errors to avoid printing false warnings.
+                                imp::terminate_child(&mut child, TerminateMode::Timeout, forward_receiver, job.as_ref(), slow_timeout.grace_period).await;
+                                if slow_timeout.grace_period.is_zero() {
+                                    break waiter.wait(&mut child).await;
+                                }
+                                // Don't break here to give the wait task a chance to finish.
                             }
-                            // Don't break here to give the wait task a chance to finish.
                         } else {
                             interval_sleep.as_mut().reset_original_duration();
                         }
                     }
+                    diagnostics = async {
+                        timeout_diagnostics_fut
+                            .as_mut()
+                            .as_pin_mut()
+                            .expect("this branch is only enabled while diagnostics are running")
+                            .await
+                    }, if timeout_diagnostics_fut.is_some() => {
+                        timeout_diagnostics_fut.set(None);
+                        timeout_diagnostics = Some(diagnostics);
+
+                        imp::terminate_child(&mut child, TerminateMode::Timeout, forward_receiver, job.as_ref(), slow_timeout.grace_period).await;
+                        if slow_timeout.grace_period.is_zero() {
+                            break waiter.wait(&mut child).await;
+                        }
+                    }
                     recv = forward_receiver.recv() => {
+                        if matches!(recv, Ok(SignalForwardEvent::Shutdown(_))) {
+                            // Don't wait for diagnostics while shutting down: dropping the future
+                            // kills the command.
+                            timeout_diagnostics_fut.set(None);
+                        }
                         handle_forward_event(
                             &mut child,
                             recv,
@@ -1074,15 +1695,45 @@ impl<'a> TestRunnerInner<'a> {
             (res, leaked)
         };
 
-        let output = res.map_err(RunTestError::Wait)?;
-        let exit_status = output;
+        let (exit_status, resource_usage) = res.map_err(RunTestError::Wait)?;
+        let (stdout, stderr) =
+            finish_output(stdout, stderr, &output_limits, &on_output_chunk).await;
 
-        let status = status.unwrap_or_else(|| create_execution_result(exit_status, leaked));
+        let mut status = status.unwrap_or_else(|| create_execution_result(exit_status, leaked));
+        if let Some(kind) = imp::resource_limit_exceeded(status, &resource_limits, resource_usage) {
+            status = ExecutionResult::ResourceExceeded { kind };
+        }
+
+        // Keep the temporary directory around for inspection if the test failed.
+        let (tmpdir, mut artifacts) = if status.is_success() {
+            // Errors while deleting the directory are ignored.
+            drop(tmpdir);
+            (None, Vec::new())
+        } else {
+            let artifacts = self.collect_artifacts(test, retry_data, settings.collect_artifacts());
+            (Some(tmpdir.into_path()), artifacts)
+        };
+
+        let mut backtrace = None;
+        if let (true, Some(pid)) = (collect_core_dumps, pid) {
+            if let Some((core, core_backtrace)) = self
+                .collect_core_dump(test, retry_data, exit_status, pid)
+                .await
+            {
+                artifacts.push(core);
+                backtrace = core_backtrace;
+            }
+        }
 
         Ok(InternalExecuteStatus {
-            stdout: stdout.freeze(),
-            stderr: stderr.freeze(),
+            stdout,
+            stderr,
             result: status,
+            resource_usage,
+            tmpdir,
+            artifacts,
+            backtrace,
+            timeout_diagnostics,
             stopwatch_end: stopwatch.end(),
             is_slow,
             delay_before_start,
@@ -1090,6 +1741,109 @@ impl<'a> TestRunnerInner<'a> {
     }
 }
 
+/// How long the `timeout-diagnostics` command can take to run.
+const TIMEOUT_DIAGNOSTICS_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Runs the `timeout-diagnostics` command for the test process `pid`, which is about to be
+/// terminated.
+///
+/// Returns the output of the command, or a description of why it failed.
+async fn run_timeout_diagnostics(diagnostics: &TimeoutDiagnostics, pid: u32) -> String {
+    let command_line = diagnostics.command_line(pid);
+    let display = shell_words::join(&command_line);
+    let child = tokio::process::Command::new(&command_line[0])
+        .args(&command_line[1..])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn();
+    let output = match child {
+        Ok(child) => {
+            tokio::time::timeout(TIMEOUT_DIAGNOSTICS_TIMEOUT, child.wait_with_output()).await
+        }
+        Err(error) => return format!("error running `{display}`: {error}\n"),
+    };
+
+    match output {
+        Ok(Ok(output)) => {
+            let mut diagnostics = String::from_utf8_lossy(&output.stdout).into_owned();
+            diagnostics.push_str(&String::from_utf8_lossy(&output.stderr));
+            if !output.status.success() {
+                _ = writeln!(diagnostics, "`{display}` failed: {}", output.status);
+            }
+            diagnostics
+        }
+        Ok(Err(error)) => format!("error waiting for `{display}`: {error}\n"),
+        Err(_) => format!(
+            "`{display}` timed out after {}s\n",
+            TIMEOUT_DIAGNOSTICS_TIMEOUT.as_secs()
+        ),
+    }
+}
+
+/// The number of runs whose temporary directories for failed tests are kept in the store
+/// directory.
+const KEPT_TMPDIR_RUNS: usize = 10;
+
+/// Removes all but the `keep` most recently modified run directories within `dir`.
+///
+/// Errors are logged and otherwise ignored, since they shouldn't affect the result of the run.
+fn remove_old_run_dirs(dir: &Utf8Path, keep: usize) {
+    let entries = match dir.read_dir_utf8() {
+        Ok(entries) => entries,
+        Err(error) => {
+            if error.kind() != std::io::ErrorKind::NotFound {
+                log::debug!("error reading {dir}: {error}");
+            }
+            return;
+        }
+    };
+    let mut run_dirs: Vec<_> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            metadata
+                .is_dir()
+                .then(|| (metadata.modified().ok(), entry.into_path()))
+        })
+        .collect();
+    // Newest first. Directories whose modification time can't be read are treated as the oldest.
+    run_dirs.sort_unstable_by(|(a, _), (b, _)| b.cmp(a));
+
+    for (_, path) in run_dirs.into_iter().skip(keep) {
+        if let Err(error) = std::fs::remove_dir_all(&path) {
+            log::warn!("error removing old temporary directory {path}: {error}");
+        }
+    }
+}
+
+/// Returns a file name for a test or binary name that's distinct from other names.
+///
+/// This is the name passed through [`sanitize_file_name`], followed by a hash of the full name so
+/// that names which are the same once sanitized don't share a file name.
+fn unique_file_name(name: &str) -> String {
+    let mut hasher = XxHash64::default();
+    hasher.write(name.as_bytes());
+    format!(
+        "{}-{:08x}",
+        sanitize_file_name(name),
+        hasher.finish() as u32
+    )
+}
+
+/// Replaces characters in a test or binary name that may not be valid in file names, such as `::`
+/// and `/`.
+fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .take(64)
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
 /// Drains the forward receiver of any messages, including those that are related to SIGTSTP.
 async fn drain_forward_receiver(mut receiver: broadcast::Receiver<SignalForwardEvent>) {
     loop {
@@ -1187,18 +1941,200 @@ fn create_execution_result(exit_status: ExitStatus, leaked: bool) -> ExecutionRe
     }
 }
 
+/// Limits on how much output is kept from a child's standard output and standard error.
+#[derive(Clone, Debug, Default)]
+struct OutputLimits {
+    stdout: OutputLimit,
+    stderr: OutputLimit,
+    // The maximum combined size of standard output and standard error.
+    total: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct OutputLimit {
+    max_size: Option<u64>,
+    // If set, the full output is written to this file if any of it is dropped.
+    spill_path: Option<Utf8PathBuf>,
+}
+
+/// Output read from one of a child's output streams.
+///
+/// If the output is larger than the size limit, its beginning and its end are kept, and the middle
+/// is dropped. The end is kept because that's where panic messages and backtraces are.
+///
+/// The size limit is enforced as the stream is read, so at most `max_size` bytes are held in memory.
+#[derive(Debug)]
+struct CapturedOutput {
+    max_size: usize,
+    head: BytesMut,
+    tail: BytesMut,
+    // The number of bytes dropped between head and tail.
+    dropped: usize,
+    // The number of bytes read, and how many bytes from the start were passed to on_chunk.
+    read: usize,
+    streamed: usize,
+    spill_path: Option<Utf8PathBuf>,
+    spill_file: Option<tokio::fs::File>,
+}
+
+impl CapturedOutput {
+    /// Creates a new `CapturedOutput`.
+    ///
+    /// A single stream can't be larger than the total limit either, so that's applied while
+    /// reading as well. [`finish_output`] then splits the total between the two streams.
+    fn new(limit: &OutputLimit, total: Option<u64>) -> Self {
+        let max_size = match (limit.max_size, total) {
+            (Some(max_size), Some(total)) => Some(max_size.min(total)),
+            (max_size, total) => max_size.or(total),
+        };
+        Self {
+            max_size: max_size.map_or(usize::MAX, |max_size| {
+                max_size.try_into().unwrap_or(usize::MAX)
+            }),
+            head: BytesMut::new(),
+            tail: BytesMut::new(),
+            dropped: 0,
+            read: 0,
+            streamed: 0,
+            spill_path: limit.spill_path.clone(),
+            spill_file: None,
+        }
+    }
+
+    /// Returns the number of bytes kept.
+    fn len(&self) -> usize {
+        self.head.len() + self.tail.len()
+    }
+
+    /// Adds a chunk of output read from the stream.
+    async fn push(&mut self, chunk: &[u8], on_chunk: impl Fn(&[u8])) {
+        let chunk_start = self.read;
+        self.read += chunk.len();
+
+        // Output is passed on as it's read until the stream goes past its limit. After that, which
+        // parts of it are kept is only known once the stream ends.
+        let stream_end = self.read.min(self.max_size);
+        if stream_end > self.streamed {
+            on_chunk(&chunk[self.streamed - chunk_start..stream_end - chunk_start]);
+            self.streamed = stream_end;
+        }
+
+        if self.read > self.max_size {
+            self.spill(chunk).await;
+        }
+
+        let head_size = self.max_size - self.max_size / 2;
+        let to_head = head_size.saturating_sub(self.head.len()).min(chunk.len());
+        self.head.extend_from_slice(&chunk[..to_head]);
+        self.tail.extend_from_slice(&chunk[to_head..]);
+
+        let tail_size = self.max_size / 2;
+        if self.tail.len() > tail_size {
+            let excess = self.tail.len() - tail_size;
+            self.tail.advance(excess);
+            self.dropped += excess;
+        }
+    }
+
+    /// Drops output from the middle so that at most `max_size` bytes are kept.
+    async fn shrink_to(&mut self, max_size: usize) {
+        if self.len() <= max_size {
+            return;
+        }
+        self.spill(&[]).await;
+
+        if self.dropped == 0 {
+            // The head and the tail are contiguous, so they can be split anywhere.
+            self.head.unsplit(std::mem::take(&mut self.tail));
+            self.tail = self.head.split_off(self.head.len() - max_size / 2);
+        }
+        let head_len = self.head.len().min(max_size - max_size / 2);
+        let tail_len = self.tail.len().min(max_size - head_len);
+        self.dropped += self.head.len() - head_len + self.tail.len() - tail_len;
+        self.head.truncate(head_len);
+        self.tail.advance(self.tail.len() - tail_len);
+        self.max_size = max_size;
+    }
+
+    /// Writes `chunk` to the spill file, if there is one.
+    ///
+    /// The first time this is called, everything kept so far is written out first, so that the
+    /// spill file has the full output.
+    async fn spill(&mut self, chunk: &[u8]) {
+        let Some(path) = &self.spill_path else {
+            return;
+        };
+        let res = match &mut self.spill_file {
+            Some(file) => file.write_all(chunk).await,
+            None => create_spill_file(path, &[&self.head, &self.tail, chunk])
+                .await
+                .map(|file| self.spill_file = Some(file)),
+        };
+        if let Err(error) = res {
+            log::warn!("error writing output to {path}: {error}");
+            // Drop any further output.
+            self.spill_path = None;
+            self.spill_file = None;
+        }
+    }
+
+    /// Waits for all output to be written to the spill file, if there is one.
+    async fn flush_spill(&mut self) {
+        if let (Some(path), Some(file)) = (&self.spill_path, &mut self.spill_file) {
+            if let Err(error) = file.flush().await {
+                log::warn!("error writing output to {path}: {error}");
+            }
+        }
+    }
+
+    /// Returns the output that was kept, with a marker where output was dropped.
+    ///
+    /// Kept output that wasn't passed to `on_chunk` while reading is passed to it now.
+    fn finish(self, on_chunk: impl Fn(&[u8])) -> Bytes {
+        let mut bytes = self.head;
+        if self.dropped > 0 {
+            let marker_start = bytes.len();
+            let destination = match &self.spill_path {
+                Some(path) => format!("; the full output was written to {path}"),
+                None => String::new(),
+            };
+            writeln!(
+                bytes,
+                "\n[nextest: {} bytes of output were dropped here to stay within the size limit{destination}]",
+                self.dropped,
+            )
+            .expect("writing to BytesMut never fails");
+
+            if self.streamed < self.read {
+                let tail_start = self.read - self.tail.len();
+                on_chunk(&bytes[marker_start..]);
+                on_chunk(&self.tail[self.streamed.saturating_sub(tail_start)..]);
+            }
+        }
+        bytes.extend_from_slice(&self.tail);
+        bytes.freeze()
+    }
+}
+
+/// Reads all of a child's standard output and standard error.
+///
+/// `on_chunk` is called with each chunk of output as soon as it's read. Once the future completes,
+/// [`finish_output`] returns the output that was kept.
 fn collect_output<'a>(
     child_stdout: Option<tokio::process::ChildStdout>,
-    stdout: &'a mut BytesMut,
+    stdout: &'a mut CapturedOutput,
     child_stderr: Option<tokio::process::ChildStderr>,
-    stderr: &'a mut BytesMut,
+    stderr: &'a mut CapturedOutput,
+    on_chunk: &'a (dyn Fn(TestOutputStream, &[u8]) + Sync),
 ) -> impl Future<Output = Result<(), CollectTestOutputError>> + 'a {
     // Set up futures for reading from stdout and stderr.
     let stdout_fut = async {
         if let Some(mut child_stdout) = child_stdout {
-            read_all_to_bytes(stdout, &mut child_stdout)
-                .await
-                .map_err(CollectTestOutputError::ReadStdout)
+            read_all_to_bytes(stdout, &mut child_stdout, |chunk| {
+                on_chunk(TestOutputStream::Stdout, chunk)
+            })
+            .await
+            .map_err(CollectTestOutputError::ReadStdout)
         } else {
             Ok(())
         }
@@ -1206,9 +2142,11 @@ fn collect_output<'a>(
 
     let stderr_fut = async {
         if let Some(mut child_stderr) = child_stderr {
-            read_all_to_bytes(stderr, &mut child_stderr)
-                .await
-                .map_err(CollectTestOutputError::ReadStderr)
+            read_all_to_bytes(stderr, &mut child_stderr, |chunk| {
+                on_chunk(TestOutputStream::Stderr, chunk)
+            })
+            .await
+            .map_err(CollectTestOutputError::ReadStderr)
         } else {
             Ok(())
         }
@@ -1217,30 +2155,83 @@ fn collect_output<'a>(
     try_join(stdout_fut, stderr_fut).map_ok(|_| ())
 }
 
+/// Applies the total output limit, and returns the output kept from standard output and standard
+/// error.
+async fn finish_output(
+    mut stdout: CapturedOutput,
+    mut stderr: CapturedOutput,
+    limits: &OutputLimits,
+    on_chunk: &(dyn Fn(TestOutputStream, &[u8]) + Sync),
+) -> (Bytes, Bytes) {
+    if let Some(total) = limits.total {
+        let total = total.try_into().unwrap_or(usize::MAX);
+        // Each stream gets at least half of the total, and the larger stream gets whatever the
+        // smaller one doesn't use.
+        let (smaller, larger) = if stdout.len() <= stderr.len() {
+            (&mut stdout, &mut stderr)
+        } else {
+            (&mut stderr, &mut stdout)
+        };
+        let smaller_size = smaller.len().min(total / 2);
+        smaller.shrink_to(smaller_size).await;
+        larger.shrink_to(total - smaller_size).await;
+    }
+    stdout.flush_spill().await;
+    stderr.flush_spill().await;
+
+    (
+        stdout.finish(|chunk| on_chunk(TestOutputStream::Stdout, chunk)),
+        stderr.finish(|chunk| on_chunk(TestOutputStream::Stderr, chunk)),
+    )
+}
+
+/// Reads all of `input` into `output`.
 async fn read_all_to_bytes(
-    bytes: &mut bytes::BytesMut,
+    output: &mut CapturedOutput,
     mut input: &mut (dyn AsyncRead + Unpin + Send),
+    on_chunk: impl Fn(&[u8]),
 ) -> std::io::Result<()> {
     // Reborrow it as AsyncReadExt::read_buf expects
     // Sized self.
     let input = &mut input;
 
+    let mut buf = BytesMut::with_capacity(4096);
     loop {
-        bytes.reserve(4096);
-        let bytes_read = input.read_buf(bytes).await?;
+        buf.clear();
+        let bytes_read = input.read_buf(&mut buf).await?;
         if bytes_read == 0 {
-            break Ok(());
+            break;
         }
+        output.push(&buf, &on_chunk).await;
+    }
+
+    Ok(())
+}
+
+/// Creates the file at `path` and writes `data` to it.
+async fn create_spill_file(path: &Utf8Path, data: &[&[u8]]) -> std::io::Result<tokio::fs::File> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let mut file = tokio::fs::File::create(path).await?;
+    for data in data {
+        file.write_all(data).await?;
     }
+    Ok(file)
 }
 
 /// Data related to retries.
+///
+/// In [stress mode](StressCondition), each iteration of a test is recorded as an attempt.
 #[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord)]
 pub struct RetryData {
     /// The current attempt. In the range `[1, total_attempts]`.
     pub attempt: usize,
 
     /// The total number of times this test can be run. Equal to `1 + retries`.
+    ///
+    /// In stress mode, this is the number of iterations, or the current attempt if tests are run
+    /// for a duration.
     pub total_attempts: usize,
 }
 
@@ -1251,17 +2242,93 @@ impl RetryData {
     }
 }
 
+/// The condition under which tests are run repeatedly in stress mode.
+///
+/// Stress mode is used to detect flaky tests: each test is run repeatedly whether or not it passes,
+/// and a test is considered to have failed if any of its iterations failed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StressCondition {
+    /// Run each test this many times.
+    Count(NonZeroUsize),
+
+    /// Run each test repeatedly until this much time has passed. Each test is run at least once.
+    Duration(Duration),
+}
+
+impl StressCondition {
+    /// Returns the total number of iterations, if known in advance.
+    pub fn total_iterations(&self) -> Option<usize> {
+        match self {
+            Self::Count(count) => Some(count.get()),
+            Self::Duration(_) => None,
+        }
+    }
+
+    fn should_continue(&self, iterations_done: usize, elapsed: Duration) -> bool {
+        match self {
+            Self::Count(count) => iterations_done < count.get(),
+            Self::Duration(duration) => elapsed < *duration,
+        }
+    }
+}
+
+impl FromStr for StressCondition {
+    type Err = StressConditionParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // A plain number is a count: anything else is parsed as a duration.
+        if let Ok(count) = s.parse::<usize>() {
+            return NonZeroUsize::new(count)
+                .map(Self::Count)
+                .ok_or_else(|| StressConditionParseError::new(s, "count must be at least 1"));
+        }
+        match humantime::parse_duration(s) {
+            Ok(duration) if duration.is_zero() => Err(StressConditionParseError::new(
+                s,
+                "duration must be greater than zero",
+            )),
+            Ok(duration) => Ok(Self::Duration(duration)),
+            Err(err) => Err(StressConditionParseError::new(s, err.to_string())),
+        }
+    }
+}
+
 /// Information about executions of a test, including retries.
 #[derive(Clone, Debug)]
 pub struct ExecutionStatuses {
     /// This is guaranteed to be non-empty.
     statuses: Vec<ExecuteStatus>,
+    stress: bool,
 }
 
 #[allow(clippy::len_without_is_empty)] // RunStatuses is never empty
 impl ExecutionStatuses {
     fn new(statuses: Vec<ExecuteStatus>) -> Self {
-        Self { statuses }
+        Self {
+            statuses,
+            stress: false,
+        }
+    }
+
+    fn new_stress(statuses: Vec<ExecuteStatus>) -> Self {
+        Self {
+            statuses,
+            stress: true,
+        }
+    }
+
+    /// Returns true if these statuses are for iterations of a test in stress mode, rather than
+    /// retries.
+    pub fn is_stress(&self) -> bool {
+        self.stress
+    }
+
+    /// Returns the number of executions that failed.
+    pub fn failed_count(&self) -> usize {
+        self.statuses
+            .iter()
+            .filter(|status| !status.result.is_success())
+            .count()
     }
 
     /// Returns the last execution status.
@@ -1283,8 +2350,28 @@ impl ExecutionStatuses {
         self.statuses.len()
     }
 
-    /// Returns a description of self.
-    pub fn describe(&self) -> ExecutionDescription<'_> {
+    /// Returns a description of self.
+    ///
+    /// In stress mode, the test is described as a failure if any iteration failed, with the first
+    /// failing iteration as its status.
+    pub fn describe(&self) -> ExecutionDescription<'_> {
+        if self.stress {
+            return match self
+                .statuses
+                .iter()
+                .find(|status| !status.result.is_success())
+            {
+                Some(failed_status) => ExecutionDescription::Failure {
+                    first_status: failed_status,
+                    last_status: failed_status,
+                    retries: &[],
+                },
+                None => ExecutionDescription::Success {
+                    single_status: self.last_status(),
+                },
+            };
+        }
+
         let last_status = self.last_status();
         if last_status.result.is_success() {
             if self.statuses.len() > 1 {
@@ -1395,6 +2482,16 @@ impl<'a> ExecutionDescription<'a> {
     }
 }
 
+/// A standard stream that a test writes output to.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum TestOutputStream {
+    /// Standard output.
+    Stdout,
+
+    /// Standard error.
+    Stderr,
+}
+
 /// Information about a single execution of a test.
 #[derive(Clone, Debug)]
 pub struct ExecuteStatus {
@@ -1410,22 +2507,82 @@ pub struct ExecuteStatus {
     pub start_time: SystemTime,
     /// The time it took for the test to run.
     pub time_taken: Duration,
+    /// The resources used by the test process, if known.
+    ///
+    /// This is only collected on Linux. Elsewhere, the resource usage of an exited process can only
+    /// be read while reaping it, and the process is reaped by tokio, so this is always `None`.
+    pub resource_usage: Option<ResourceUsage>,
+    /// The temporary directory for this attempt (`NEXTEST_TEST_TMPDIR`), if it was kept because
+    /// the attempt failed.
+    pub tmpdir: Option<Utf8PathBuf>,
+    /// Files collected from this attempt because of the `collect-artifacts` setting.
+    ///
+    /// Artifacts are only collected if the attempt failed.
+    pub artifacts: Vec<Utf8PathBuf>,
+    /// A backtrace of all threads, obtained from the core file if this attempt crashed and
+    /// `collect-core-dumps` is set.
+    pub backtrace: Option<String>,
+    /// The output of the `timeout-diagnostics` command, if this attempt timed out and the command
+    /// was run before it was terminated.
+    pub timeout_diagnostics: Option<String>,
     /// Whether this test counts as slow.
     pub is_slow: bool,
     /// The delay will be non-zero if this is a retry and delay was specified.
     pub delay_before_start: Duration,
 }
 
+/// Resources used by a test process, as reported by the operating system.
+///
+/// Part of [`ExecuteStatus`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ResourceUsage {
+    /// The peak resident set size of the process, in bytes.
+    pub max_rss: u64,
+    /// The amount of CPU time spent in user mode.
+    pub user_time: Duration,
+    /// The amount of CPU time spent in the kernel.
+    pub system_time: Duration,
+}
+
 struct InternalExecuteStatus {
     stdout: Bytes,
     stderr: Bytes,
     result: ExecutionResult,
+    resource_usage: Option<ResourceUsage>,
+    tmpdir: Option<Utf8PathBuf>,
+    artifacts: Vec<Utf8PathBuf>,
+    backtrace: Option<String>,
+    timeout_diagnostics: Option<String>,
     stopwatch_end: StopwatchEnd,
     is_slow: bool,
     delay_before_start: Duration,
 }
 
 impl InternalExecuteStatus {
+    /// Returns the status for a test that couldn't be run, with the error chain inside stderr.
+    fn exec_fail(
+        error: RunTestError,
+        stopwatch: &StopwatchStart,
+        delay_before_start: Duration,
+    ) -> Self {
+        let mut stderr = bytes::BytesMut::new();
+        writeln!(&mut stderr, "{}", DisplayErrorChain::new(error)).unwrap();
+
+        Self {
+            stdout: Bytes::new(),
+            stderr: stderr.freeze(),
+            result: ExecutionResult::ExecFail,
+            resource_usage: None,
+            tmpdir: None,
+            artifacts: Vec::new(),
+            backtrace: None,
+            timeout_diagnostics: None,
+            stopwatch_end: stopwatch.end(),
+            is_slow: false,
+            delay_before_start,
+        }
+    }
+
     fn into_external(self, retry_data: RetryData) -> ExecuteStatus {
         ExecuteStatus {
             retry_data,
@@ -1434,6 +2591,11 @@ impl InternalExecuteStatus {
             result: self.result,
             start_time: self.stopwatch_end.start_time,
             time_taken: self.stopwatch_end.duration,
+            resource_usage: self.resource_usage,
+            tmpdir: self.tmpdir,
+            artifacts: self.artifacts,
+            backtrace: self.backtrace,
+            timeout_diagnostics: self.timeout_diagnostics,
             is_slow: self.is_slow,
             delay_before_start: self.delay_before_start,
         }
@@ -1513,6 +2675,23 @@ pub struct RunStats {
     /// The number of setup scripts that timed out.
     pub setup_scripts_timed_out: usize,
 
+    /// The total number of teardown commands that finished running.
+    ///
+    /// Teardown commands aren't counted in the `setup_scripts_*` fields.
+    pub teardown_scripts_finished_count: usize,
+
+    /// The number of teardown commands that passed.
+    pub teardown_scripts_passed: usize,
+
+    /// The number of teardown commands that failed.
+    pub teardown_scripts_failed: usize,
+
+    /// The number of teardown commands that encountered an execution failure.
+    pub teardown_scripts_exec_failed: usize,
+
+    /// The number of teardown commands that timed out.
+    pub teardown_scripts_timed_out: usize,
+
     /// The number of tests that passed. Includes `passed_slow`, `flaky` and `leaky`.
     pub passed: usize,
 
@@ -1537,6 +2716,12 @@ pub struct RunStats {
     /// The number of tests that encountered an execution failure.
     pub exec_failed: usize,
 
+    /// The number of quarantined tests that failed, timed out or encountered an execution failure.
+    ///
+    /// These tests are not included in `failed`, `timed_out` or `exec_failed`, and don't cause the
+    /// run to fail.
+    pub quarantined: usize,
+
     /// The number of tests that were skipped.
     pub skipped: usize,
 }
@@ -1546,8 +2731,8 @@ impl RunStats {
     ///
     /// A run can be marked as failed if any of the following are true:
     /// * the run was canceled: the initial run count is greater than the final run count
-    /// * any tests failed
-    /// * any tests encountered an execution failure
+    /// * any tests failed, other than quarantined tests
+    /// * any tests encountered an execution failure, other than quarantined tests
     pub fn is_success(&self) -> bool {
         if self.setup_scripts_initial_count > self.setup_scripts_finished_count {
             return false;
@@ -1568,6 +2753,9 @@ impl RunStats {
         if self.setup_scripts_failed > 0
             || self.setup_scripts_exec_failed > 0
             || self.setup_scripts_timed_out > 0
+            || self.teardown_scripts_failed > 0
+            || self.teardown_scripts_exec_failed > 0
+            || self.teardown_scripts_timed_out > 0
         {
             return Some(RunStatsFailureKind::SetupScript);
         }
@@ -1579,26 +2767,48 @@ impl RunStats {
         None
     }
 
-    fn on_setup_script_finished(&mut self, status: &SetupScriptExecuteStatus) {
-        self.setup_scripts_finished_count += 1;
+    fn on_setup_script_finished(&mut self, phase: ScriptPhase, status: &SetupScriptExecuteStatus) {
+        // Teardown commands are counted separately, so that setup_scripts_finished_count can be
+        // compared against setup_scripts_initial_count.
+        let (finished_count, passed, failed, exec_failed, timed_out) = match phase {
+            ScriptPhase::Setup => (
+                &mut self.setup_scripts_finished_count,
+                &mut self.setup_scripts_passed,
+                &mut self.setup_scripts_failed,
+                &mut self.setup_scripts_exec_failed,
+                &mut self.setup_scripts_timed_out,
+            ),
+            ScriptPhase::Teardown => (
+                &mut self.teardown_scripts_finished_count,
+                &mut self.teardown_scripts_passed,
+                &mut self.teardown_scripts_failed,
+                &mut self.teardown_scripts_exec_failed,
+                &mut self.teardown_scripts_timed_out,
+            ),
+        };
+        *finished_count += 1;
 
         match status.result {
             ExecutionResult::Pass | ExecutionResult::Leak => {
-                self.setup_scripts_passed += 1;
+                *passed += 1;
             }
             ExecutionResult::Fail { .. } => {
-                self.setup_scripts_failed += 1;
+                *failed += 1;
             }
             ExecutionResult::ExecFail => {
-                self.setup_scripts_exec_failed += 1;
+                *exec_failed += 1;
             }
             ExecutionResult::Timeout => {
-                self.setup_scripts_timed_out += 1;
+                *timed_out += 1;
+            }
+            ExecutionResult::ResourceExceeded { .. } => {
+                // Resource limits aren't applied to setup scripts, but count this as a failure.
+                *failed += 1;
             }
         }
     }
 
-    fn on_test_finished(&mut self, run_statuses: &ExecutionStatuses) {
+    fn on_test_finished(&mut self, run_statuses: &ExecutionStatuses, quarantined: bool) {
         self.finished_count += 1;
         // run_statuses is guaranteed to have at least one element.
         // * If the last element is success, treat it as success (and possibly flaky).
@@ -1608,14 +2818,22 @@ impl RunStats {
         //
         // This is not likely to matter much in practice since failures are likely to be of the
         // same type.
-        let last_status = run_statuses.last_status();
+        //
+        // In stress mode, the description uses the first failing iteration instead.
+        let describe = run_statuses.describe();
+        let last_status = describe.last_status();
+        if quarantined && !last_status.result.is_success() {
+            self.quarantined += 1;
+            return;
+        }
+        let is_flaky = matches!(describe, ExecutionDescription::Flaky { .. });
         match last_status.result {
             ExecutionResult::Pass => {
                 self.passed += 1;
                 if last_status.is_slow {
                     self.passed_slow += 1;
                 }
-                if run_statuses.len() > 1 {
+                if is_flaky {
                     self.flaky += 1;
                 }
             }
@@ -1625,11 +2843,11 @@ impl RunStats {
                 if last_status.is_slow {
                     self.passed_slow += 1;
                 }
-                if run_statuses.len() > 1 {
+                if is_flaky {
                     self.flaky += 1;
                 }
             }
-            ExecutionResult::Fail { .. } => {
+            ExecutionResult::Fail { .. } | ExecutionResult::ResourceExceeded { .. } => {
                 self.failed += 1;
                 if last_status.is_slow {
                     self.failed_slow += 1;
@@ -1644,7 +2862,7 @@ impl RunStats {
 /// A type summarizing the possible failures within a test run.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum RunStatsFailureKind {
-    /// A setup script failed.
+    /// A setup script, or its teardown command, failed.
     SetupScript,
 
     /// A test failed.
@@ -1750,50 +2968,62 @@ where
             InternalEvent::Test(InternalTestEvent::SetupScriptStarted {
                 script_id,
                 config,
+                phase,
                 index,
                 total,
             }) => {
                 self.setup_scripts_running += 1;
+                let (command, args) = config.command_for(phase);
                 self.callback(TestEventKind::SetupScriptStarted {
                     index,
                     total,
                     script_id,
-                    command: config.program(),
-                    args: config.args(),
+                    phase,
+                    command,
+                    args,
                     no_capture: config.no_capture(),
                 })
             }
             InternalEvent::Test(InternalTestEvent::SetupScriptSlow {
                 script_id,
                 config,
+                phase,
                 elapsed,
                 will_terminate,
-            }) => self.callback(TestEventKind::SetupScriptSlow {
-                script_id,
-                command: config.program(),
-                args: config.args(),
-                elapsed,
-                will_terminate,
-            }),
+            }) => {
+                let (command, args) = config.command_for(phase);
+                self.callback(TestEventKind::SetupScriptSlow {
+                    script_id,
+                    phase,
+                    command,
+                    args,
+                    elapsed,
+                    will_terminate,
+                })
+            }
             InternalEvent::Test(InternalTestEvent::SetupScriptFinished {
                 script_id,
                 config,
+                phase,
                 index,
                 total,
                 status,
             }) => {
                 self.setup_scripts_running -= 1;
-                self.run_stats.on_setup_script_finished(&status);
+                self.run_stats.on_setup_script_finished(phase, &status);
                 // Setup scripts failing always cause the entire test run to be cancelled
-                // (--no-fail-fast is ignored).
-                let fail_cancel = !status.result.is_success();
+                // (--no-fail-fast is ignored). Teardown happens once tests are done, so there's
+                // nothing left to cancel: failures only cause the run to fail.
+                let fail_cancel = !status.result.is_success() && phase == ScriptPhase::Setup;
 
+                let (command, args) = config.command_for(phase);
                 self.callback(TestEventKind::SetupScriptFinished {
                     index,
                     total,
                     script_id,
-                    command: config.program(),
-                    args: config.args(),
+                    phase,
+                    command,
+                    args,
                     no_capture: config.no_capture(),
                     run_status: status,
                 })?;
@@ -1806,6 +3036,25 @@ where
                     Ok(None)
                 }
             }
+            InternalEvent::Test(InternalTestEvent::WrapperScriptFinished {
+                test_instance,
+                script_id,
+                config,
+                phase,
+                status,
+            }) => {
+                // Wrapper script failures are reported through the test they were run around, so
+                // they aren't tracked separately in run_stats.
+                let (command, args) = config.command_for(phase);
+                self.callback(TestEventKind::WrapperScriptFinished {
+                    test_instance,
+                    script_id,
+                    phase,
+                    command,
+                    args,
+                    run_status: status,
+                })
+            }
             InternalEvent::Test(InternalTestEvent::Started { test_instance }) => {
                 self.running += 1;
                 self.callback(TestEventKind::TestStarted {
@@ -1826,6 +3075,17 @@ where
                 elapsed,
                 will_terminate,
             }),
+            InternalEvent::Test(InternalTestEvent::OutputChunk {
+                test_instance,
+                retry_data,
+                stream,
+                data,
+            }) => self.callback(TestEventKind::TestOutputChunk {
+                test_instance,
+                retry_data,
+                stream,
+                data,
+            }),
             InternalEvent::Test(InternalTestEvent::AttemptFailedWillRetry {
                 test_instance,
                 failure_output,
@@ -1850,13 +3110,17 @@ where
                 failure_output,
                 junit_store_success_output,
                 junit_store_failure_output,
+                quarantined,
                 run_statuses,
             }) => {
                 self.running -= 1;
-                self.run_stats.on_test_finished(&run_statuses);
+                self.run_stats.on_test_finished(&run_statuses, quarantined);
 
-                // should this run be canceled because of a failure?
-                let fail_cancel = self.fail_fast && !run_statuses.last_status().result.is_success();
+                // should this run be canceled because of a failure? (failures in quarantined tests
+                // are ignored)
+                let fail_cancel = self.fail_fast
+                    && !quarantined
+                    && !run_statuses.describe().last_status().result.is_success();
 
                 self.callback(TestEventKind::TestFinished {
                     test_instance,
@@ -1864,6 +3128,7 @@ where
                     failure_output,
                     junit_store_success_output,
                     junit_store_failure_output,
+                    quarantined,
                     run_statuses,
                     current_stats: self.run_stats,
                     running: self.running,
@@ -1904,6 +3169,18 @@ where
                     self.begin_cancel(cancel_reason).err(),
                 ))
             }
+            InternalEvent::CancelRequested => {
+                // Ask running tests to exit the same way a termination signal would.
+                #[cfg(unix)]
+                let event = ShutdownEvent::Term;
+                #[cfg(not(unix))]
+                let event = ShutdownEvent::Interrupt;
+
+                Err(InternalError::SignalCanceled(
+                    ShutdownForwardEvent::Once(event),
+                    self.begin_cancel(CancelReason::Requested).err(),
+                ))
+            }
             #[cfg(unix)]
             InternalEvent::Signal(SignalEvent::JobControl(JobControlEvent::Stop)) => {
                 // Debounce stop signals.
@@ -1978,6 +3255,7 @@ where
 enum InternalEvent<'a> {
     Test(InternalTestEvent<'a>),
     Signal(SignalEvent),
+    CancelRequested,
 }
 
 #[derive(Debug)]
@@ -1985,22 +3263,32 @@ enum InternalTestEvent<'a> {
     SetupScriptStarted {
         script_id: ScriptId,
         config: &'a ScriptConfig,
+        phase: ScriptPhase,
         index: usize,
         total: usize,
     },
     SetupScriptSlow {
         script_id: ScriptId,
         config: &'a ScriptConfig,
+        phase: ScriptPhase,
         elapsed: Duration,
         will_terminate: bool,
     },
     SetupScriptFinished {
         script_id: ScriptId,
         config: &'a ScriptConfig,
+        phase: ScriptPhase,
         index: usize,
         total: usize,
         status: SetupScriptExecuteStatus,
     },
+    WrapperScriptFinished {
+        test_instance: TestInstance<'a>,
+        script_id: ScriptId,
+        config: &'a ScriptConfig,
+        phase: ScriptPhase,
+        status: SetupScriptExecuteStatus,
+    },
     Started {
         test_instance: TestInstance<'a>,
     },
@@ -2010,6 +3298,12 @@ enum InternalTestEvent<'a> {
         elapsed: Duration,
         will_terminate: bool,
     },
+    OutputChunk {
+        test_instance: TestInstance<'a>,
+        retry_data: RetryData,
+        stream: TestOutputStream,
+        data: Bytes,
+    },
     AttemptFailedWillRetry {
         test_instance: TestInstance<'a>,
         failure_output: TestOutputDisplay,
@@ -2026,6 +3320,7 @@ enum InternalTestEvent<'a> {
         failure_output: TestOutputDisplay,
         junit_store_success_output: bool,
         junit_store_failure_output: bool,
+        quarantined: bool,
         run_statuses: ExecutionStatuses,
     },
     Skipped {
@@ -2066,6 +3361,11 @@ pub enum ExecutionResult {
     ExecFail,
     /// The test was terminated due to timeout.
     Timeout,
+    /// The test failed because it exceeded one of its resource limits.
+    ResourceExceeded {
+        /// The resource whose limit was exceeded.
+        kind: ResourceLimitKind,
+    },
 }
 
 impl ExecutionResult {
@@ -2073,9 +3373,10 @@ impl ExecutionResult {
     pub fn is_success(self) -> bool {
         match self {
             ExecutionResult::Pass | ExecutionResult::Leak => true,
-            ExecutionResult::Fail { .. } | ExecutionResult::ExecFail | ExecutionResult::Timeout => {
-                false
-            }
+            ExecutionResult::Fail { .. }
+            | ExecutionResult::ExecFail
+            | ExecutionResult::Timeout
+            | ExecutionResult::ResourceExceeded { .. } => false,
         }
     }
 }
@@ -2112,6 +3413,9 @@ pub fn configure_handle_inheritance(
     imp::configure_handle_inheritance_impl(no_capture)
 }
 
+#[cfg(unix)]
+pub(crate) use imp::raise_stop;
+
 #[cfg(windows)]
 mod imp {
     use super::*;
@@ -2161,6 +3465,43 @@ mod imp {
         // TODO: set process group on Windows for better ctrl-C handling.
     }
 
+    pub(super) fn set_resource_limits(_cmd: &mut std::process::Command, limits: ResourceLimits) {
+        // TODO: resource limits could be enforced through job objects.
+        if !limits.is_empty() {
+            static WARN_ONCE: std::sync::Once = std::sync::Once::new();
+            WARN_ONCE.call_once(|| {
+                log::warn!(
+                    "resource limits are only supported on Unix, so tests with `memory-limit`, \
+                     `cpu-time-limit` or `max-open-files` set are run without them"
+                );
+            });
+        }
+    }
+
+    /// Waits for test processes to exit. Resource usage is only collected on Linux.
+    pub(super) struct ChildWaiter(());
+
+    impl ChildWaiter {
+        pub(super) fn new() -> std::io::Result<Self> {
+            Ok(Self(()))
+        }
+
+        pub(super) async fn wait(
+            &mut self,
+            child: &mut Child,
+        ) -> std::io::Result<(ExitStatus, Option<ResourceUsage>)> {
+            Ok((child.wait().await?, None))
+        }
+    }
+
+    pub(super) fn resource_limit_exceeded(
+        _result: ExecutionResult,
+        _limits: &ResourceLimits,
+        _resource_usage: Option<ResourceUsage>,
+    ) -> Option<ResourceLimitKind> {
+        None
+    }
+
     pub(super) fn assign_process_to_job(
         child: &tokio::process::Child,
         job: Option<&Job>,
@@ -2210,7 +3551,7 @@ mod imp {
 #[cfg(unix)]
 mod imp {
     use super::*;
-    use libc::{SIGCONT, SIGHUP, SIGINT, SIGKILL, SIGSTOP, SIGTERM, SIGTSTP};
+    use libc::{SIGCONT, SIGHUP, SIGINT, SIGKILL, SIGSTOP, SIGTERM, SIGTSTP, SIGXCPU};
     use std::os::unix::process::CommandExt;
 
     // This is a no-op on non-windows platforms.
@@ -2228,6 +3569,237 @@ mod imp {
         cmd.process_group(0);
     }
 
+    /// Applies resource limits to the test process with `setrlimit`.
+    pub(super) fn set_resource_limits(cmd: &mut std::process::Command, limits: ResourceLimits) {
+        // Compute the limits before forking, since allocating afterwards isn't safe.
+        let mut rlimits = Vec::new();
+        if let Some(memory) = limits.memory {
+            rlimits.push((libc::RLIMIT_AS, memory, memory));
+        }
+        if let Some(cpu_time) = limits.cpu_time {
+            // The hard limit is a second past the soft one so that the test gets SIGXCPU before
+            // it's killed with SIGKILL.
+            let secs = cpu_time_limit_secs(cpu_time);
+            rlimits.push((libc::RLIMIT_CPU, secs, secs + 1));
+        }
+        if let Some(open_files) = limits.open_files {
+            rlimits.push((libc::RLIMIT_NOFILE, open_files, open_files));
+        }
+        if rlimits.is_empty() {
+            return;
+        }
+
+        // SAFETY: setrlimit is async-signal-safe, and nothing is allocated in the closure.
+        unsafe {
+            cmd.pre_exec(move || {
+                for &(resource, soft, hard) in &rlimits {
+                    let rlimit = libc::rlimit {
+                        rlim_cur: soft as libc::rlim_t,
+                        rlim_max: hard as libc::rlim_t,
+                    };
+                    if libc::setrlimit(resource, &rlimit) != 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                }
+                Ok(())
+            })
+        };
+    }
+
+    /// Waits for test processes to exit, collecting their resource usage on Linux.
+    ///
+    /// tokio owns the child and is responsible for reaping it: reaping it here instead would let
+    /// its pid be reused while tokio still considers it running. So the child's resource usage is
+    /// read without reaping it, through `waitid` with `WNOWAIT`, and tokio reaps it afterwards.
+    ///
+    /// Other Unix platforms only report resource usage while reaping the child (through `wait4`),
+    /// so resource usage isn't collected on them.
+    pub(super) struct ChildWaiter {
+        #[cfg(target_os = "linux")]
+        sigchld: tokio::signal::unix::Signal,
+    }
+
+    impl ChildWaiter {
+        /// Creates a new waiter. This must be called before the child is spawned.
+        pub(super) fn new() -> std::io::Result<Self> {
+            Ok(Self {
+                #[cfg(target_os = "linux")]
+                sigchld: tokio::signal::unix::signal(tokio::signal::unix::SignalKind::child())?,
+            })
+        }
+
+        #[cfg(target_os = "linux")]
+        pub(super) async fn wait(
+            &mut self,
+            child: &mut Child,
+        ) -> std::io::Result<(ExitStatus, Option<ResourceUsage>)> {
+            loop {
+                let Some(pid) = child.id() else {
+                    // tokio already reaped the child (this can happen while the child is being
+                    // terminated), so its resource usage is no longer available.
+                    return Ok((child.wait().await?, None));
+                };
+
+                match exited_rusage(pid) {
+                    Ok(Some(rusage)) => {
+                        // The child has exited but hasn't been reaped yet, so tokio collects its
+                        // exit status right away.
+                        let status = child.wait().await?;
+                        return Ok((status, Some(resource_usage_from_rusage(&rusage))));
+                    }
+                    Ok(None) => {}
+                    Err(error) => match error.raw_os_error() {
+                        Some(libc::EINTR) => continue,
+                        // The child was reaped by tokio in the meantime.
+                        Some(libc::ECHILD) => return Ok((child.wait().await?, None)),
+                        _ => return Err(error),
+                    },
+                }
+
+                // The child is still running: wait for the next SIGCHLD. Since the signal handler
+                // was installed before the child was spawned, an exit can't be missed.
+                self.sigchld.recv().await;
+            }
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        pub(super) async fn wait(
+            &mut self,
+            child: &mut Child,
+        ) -> std::io::Result<(ExitStatus, Option<ResourceUsage>)> {
+            Ok((child.wait().await?, None))
+        }
+    }
+
+    /// Returns the resource usage of the child with the given pid if it has exited, without
+    /// reaping it.
+    #[cfg(target_os = "linux")]
+    fn exited_rusage(pid: u32) -> std::io::Result<Option<libc::rusage>> {
+        // SAFETY: all-zero siginfo_t and rusage structs are valid.
+        let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+        let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+        // The waitid system call, unlike the libc wrapper, also returns the resource usage of the
+        // child.
+        let ret = unsafe {
+            libc::syscall(
+                libc::SYS_waitid,
+                libc::P_PID,
+                pid as libc::id_t,
+                &mut info as *mut libc::siginfo_t,
+                libc::WEXITED | libc::WNOWAIT | libc::WNOHANG,
+                &mut rusage as *mut libc::rusage,
+            )
+        };
+        if ret < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        // With WNOHANG, si_pid is left as 0 if the child hasn't exited yet.
+        if unsafe { info.si_pid() } == 0 {
+            Ok(None)
+        } else {
+            Ok(Some(rusage))
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn resource_usage_from_rusage(rusage: &libc::rusage) -> ResourceUsage {
+        let timeval_to_duration = |tv: libc::timeval| {
+            Duration::new(tv.tv_sec as u64, (tv.tv_usec as u32).saturating_mul(1000))
+        };
+        ResourceUsage {
+            // ru_maxrss is in kilobytes on Linux.
+            max_rss: rusage.ru_maxrss as u64 * 1024,
+            user_time: timeval_to_duration(rusage.ru_utime),
+            system_time: timeval_to_duration(rusage.ru_stime),
+        }
+    }
+
+    /// Determines whether a failed test failed because it ran into one of its resource limits.
+    ///
+    /// This is based only on how the test exited and the resources it used, so that tests aren't
+    /// misclassified based on what they print out. Running into the memory or open files limits
+    /// makes allocations or opening files fail, which doesn't tell a test that ran into its limit
+    /// apart from one that failed for other reasons, so those are reported as regular failures.
+    pub(super) fn resource_limit_exceeded(
+        result: ExecutionResult,
+        limits: &ResourceLimits,
+        resource_usage: Option<ResourceUsage>,
+    ) -> Option<ResourceLimitKind> {
+        let ExecutionResult::Fail {
+            abort_status: Some(AbortStatus::UnixSignal(signal)),
+            ..
+        } = result
+        else {
+            return None;
+        };
+
+        if let Some(cpu_time) = limits.cpu_time {
+            // The soft limit sends SIGXCPU. If the test handles or ignores it, it's killed with
+            // SIGKILL once it reaches the hard limit: tell that apart from other ways the test
+            // could have been killed by how much CPU time it used.
+            let soft_limit = Duration::from_secs(cpu_time_limit_secs(cpu_time));
+            let reached_limit = resource_usage
+                .is_some_and(|usage| usage.user_time + usage.system_time >= soft_limit);
+            if signal == SIGXCPU || (signal == SIGKILL && reached_limit) {
+                return Some(ResourceLimitKind::CpuTime);
+            }
+        }
+        None
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_resource_limit_exceeded() {
+            let limits = ResourceLimits {
+                memory: Some(1 << 30),
+                cpu_time: Some(Duration::from_millis(1500)),
+                open_files: Some(64),
+            };
+            let fail = |signal: Option<i32>| ExecutionResult::Fail {
+                abort_status: signal.map(AbortStatus::UnixSignal),
+                leaked: false,
+            };
+            let usage = |secs| ResourceUsage {
+                max_rss: 0,
+                user_time: Duration::from_secs(secs),
+                system_time: Duration::ZERO,
+            };
+
+            let check = |result, usage, expected| {
+                assert_eq!(
+                    resource_limit_exceeded(result, &limits, usage),
+                    expected,
+                    "for {result:?} with {usage:?}"
+                );
+            };
+            check(fail(Some(SIGXCPU)), None, Some(ResourceLimitKind::CpuTime));
+            check(
+                fail(Some(SIGKILL)),
+                Some(usage(2)),
+                Some(ResourceLimitKind::CpuTime),
+            );
+            check(fail(Some(SIGKILL)), Some(usage(1)), None);
+            // Running out of memory or file descriptors can't be told apart from other failures.
+            check(fail(Some(libc::SIGABRT)), Some(usage(0)), None);
+            check(fail(None), Some(usage(0)), None);
+            check(ExecutionResult::Pass, Some(usage(2)), None);
+            assert_eq!(
+                resource_limit_exceeded(fail(Some(SIGXCPU)), &ResourceLimits::default(), None),
+                None,
+                "no CPU time limit set"
+            );
+        }
+    }
+
+    /// Returns the soft limit applied for `cpu_time`, in seconds.
+    fn cpu_time_limit_secs(cpu_time: Duration) -> u64 {
+        // CPU time limits are in whole seconds, so round up.
+        cpu_time.as_secs() + u64::from(cpu_time.subsec_nanos() > 0)
+    }
+
     #[derive(Debug)]
     pub(super) struct Job(());
 
@@ -2263,7 +3835,7 @@ mod imp {
     }
 
     // Note this is SIGSTOP rather than SIGTSTP to avoid triggering our signal handler.
-    pub(super) fn raise_stop() {
+    pub(crate) fn raise_stop() {
         // This can never error out because SIGSTOP is a valid signal.
         unsafe { libc::raise(SIGSTOP) };
     }
@@ -2387,6 +3959,230 @@ mod tests {
         assert_eq!(runner.inner.test_threads, 1, "tests run serially");
     }
 
+    #[test]
+    fn test_remove_old_run_dirs() {
+        let dir = camino_tempfile::tempdir().unwrap();
+        for name in ["run-1", "run-2", "run-3", "run-4"] {
+            std::fs::create_dir_all(dir.path().join(name).join("binary")).unwrap();
+            // Make sure each run has a distinct modification time.
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        remove_old_run_dirs(dir.path(), 2);
+        let mut remaining: Vec<_> = dir
+            .path()
+            .read_dir_utf8()
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_owned())
+            .collect();
+        remaining.sort();
+        assert_eq!(
+            remaining,
+            ["run-3", "run-4"],
+            "the two newest runs are kept"
+        );
+
+        // A missing directory is ignored.
+        remove_old_run_dirs(&dir.path().join("missing"), 2);
+    }
+
+    #[test]
+    fn test_unique_file_name() {
+        let prefix = "tests::some_module::nested_module::another_module::long_test_name_";
+        let a = unique_file_name(&format!("{prefix}a"));
+        let b = unique_file_name(&format!("{prefix}b"));
+        assert_ne!(a, b, "names with a long common prefix are distinct");
+        assert!(
+            a.starts_with("tests__some_module__nested_module__another_module__long_test_"),
+            "name is sanitized: {a}"
+        );
+        assert_ne!(
+            unique_file_name("foo::bar"),
+            unique_file_name("foo__bar"),
+            "names that are the same once sanitized are distinct"
+        );
+        assert_eq!(
+            unique_file_name("foo::bar"),
+            unique_file_name("foo::bar"),
+            "names are deterministic"
+        );
+    }
+
+    /// Reads `chunks` with the given limits, returning the captured output and what was passed to
+    /// `on_chunk`.
+    fn read_output(
+        chunks: &[&[u8]],
+        limit: &OutputLimit,
+        total: Option<u64>,
+    ) -> (CapturedOutput, Vec<u8>) {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let mut output = CapturedOutput::new(limit, total);
+        let streamed = std::cell::RefCell::new(Vec::new());
+        for chunk in chunks {
+            let mut input = *chunk;
+            runtime
+                .block_on(read_all_to_bytes(&mut output, &mut input, |chunk| {
+                    streamed.borrow_mut().extend_from_slice(chunk)
+                }))
+                .unwrap();
+        }
+        (output, streamed.into_inner())
+    }
+
+    #[test]
+    fn test_read_all_to_bytes_under_limit() {
+        let limit = OutputLimit {
+            max_size: Some(10),
+            spill_path: None,
+        };
+        let (output, streamed) = read_output(&[b"hello", b"world"], &limit, None);
+        assert_eq!(streamed, b"helloworld", "all output is streamed");
+        assert_eq!(
+            output.finish(|_| {}),
+            &b"helloworld"[..],
+            "all output is kept"
+        );
+    }
+
+    #[test]
+    fn test_read_all_to_bytes_drop() {
+        let limit = OutputLimit {
+            max_size: Some(10),
+            spill_path: None,
+        };
+        let (output, streamed) =
+            read_output(&[b"0123", b"4567", b"89abcdef", b"ghij"], &limit, None);
+        assert_eq!(
+            streamed, b"0123456789",
+            "output up to the limit is streamed"
+        );
+
+        let finished = std::cell::RefCell::new(Vec::new());
+        let bytes = output.finish(|chunk| finished.borrow_mut().extend_from_slice(chunk));
+        let marker =
+            "\n[nextest: 10 bytes of output were dropped here to stay within the size limit]\n";
+        assert_eq!(
+            bytes,
+            format!("01234{marker}fghij").as_bytes(),
+            "the beginning and the end are kept"
+        );
+        assert_eq!(
+            finished.into_inner(),
+            format!("{marker}fghij").as_bytes(),
+            "the marker and the end are streamed once the stream ends"
+        );
+    }
+
+    #[test]
+    fn test_read_all_to_bytes_spill() {
+        let dir = camino_tempfile::tempdir().unwrap();
+        let spill_path = dir.path().join("artifacts/stdout.log");
+        let limit = OutputLimit {
+            max_size: Some(10),
+            spill_path: Some(spill_path.clone()),
+        };
+        let (output, _) = read_output(&[b"0123456", b"789abcdef", b"ghij"], &limit, None);
+        assert_eq!(
+            output.finish(|_| {}),
+            format!(
+                "01234\n[nextest: 10 bytes of output were dropped here to stay within the size \
+                 limit; the full output was written to {spill_path}]\nfghij"
+            )
+            .as_bytes(),
+            "the marker points to the spill file"
+        );
+        assert_eq!(
+            std::fs::read(&spill_path).unwrap(),
+            b"0123456789abcdefghij",
+            "the spill file has the full output"
+        );
+    }
+
+    #[test]
+    fn test_finish_output_total() {
+        let dir = camino_tempfile::tempdir().unwrap();
+        let spill_path = dir.path().join("stdout.log");
+        let limits = OutputLimits {
+            stdout: OutputLimit {
+                max_size: None,
+                spill_path: Some(spill_path.clone()),
+            },
+            stderr: OutputLimit::default(),
+            total: Some(20),
+        };
+        let (stdout, _) = read_output(&[b"0123456789abcdefghij"], &limits.stdout, limits.total);
+        let (stderr, _) = read_output(&[b"error"], &limits.stderr, limits.total);
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let (stdout, stderr) = runtime.block_on(finish_output(stdout, stderr, &limits, &|_, _| {}));
+        assert_eq!(stderr, &b"error"[..], "the smaller stream is kept in full");
+        assert_eq!(
+            stdout,
+            format!(
+                "01234567\n[nextest: 5 bytes of output were dropped here to stay within the size \
+                 limit; the full output was written to {spill_path}]\ndefghij"
+            )
+            .as_bytes(),
+            "the larger stream gets the rest of the total"
+        );
+        assert_eq!(
+            std::fs::read(&spill_path).unwrap(),
+            b"0123456789abcdefghij",
+            "the spill file has the full output"
+        );
+    }
+
+    #[test]
+    fn test_read_all_to_bytes_total_only() {
+        let dir = camino_tempfile::tempdir().unwrap();
+        let spill_path = dir.path().join("stdout.log");
+        let limit = OutputLimit {
+            max_size: None,
+            spill_path: Some(spill_path.clone()),
+        };
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let mut output = CapturedOutput::new(&limit, Some(10));
+        for chunk in [&b"0123"[..], b"4567", b"89abcdef", b"ghij"] {
+            let mut input = chunk;
+            runtime
+                .block_on(read_all_to_bytes(&mut output, &mut input, |_| {}))
+                .unwrap();
+            assert!(
+                output.len() <= 10,
+                "at most the total limit is kept while reading (kept {} bytes)",
+                output.len()
+            );
+        }
+        runtime.block_on(output.flush_spill());
+        assert_eq!(
+            std::fs::read(&spill_path).unwrap(),
+            b"0123456789abcdefghij",
+            "output past the total limit is spilled while reading"
+        );
+    }
+
+    #[test]
+    fn test_stress_condition_from_str() {
+        assert_eq!(
+            "100".parse::<StressCondition>().unwrap(),
+            StressCondition::Count(NonZeroUsize::new(100).unwrap())
+        );
+        assert_eq!(
+            "5m".parse::<StressCondition>().unwrap(),
+            StressCondition::Duration(Duration::from_secs(300))
+        );
+        for input in ["0", "0s", "", "foo", "-1"] {
+            input
+                .parse::<StressCondition>()
+                .expect_err(&format!("expected input '{input}' to fail"));
+        }
+    }
+
     #[test]
     fn test_is_success() {
         assert!(RunStats::default().is_success(), "empty run => success");
@@ -2498,6 +4294,17 @@ mod tests {
             .is_success(),
             "setup scripts passed => not considered a failure"
         );
+        assert!(
+            RunStats {
+                initial_run_count: 42,
+                finished_count: 42,
+                passed: 41,
+                quarantined: 1,
+                ..RunStats::default()
+            }
+            .is_success(),
+            "quarantined test failed => not considered a failure"
+        );
     }
 
     #[test]
@@ -2607,4 +4414,87 @@ mod tests {
             "setup scripts passed => not considered a failure"
         );
     }
+
+    #[test]
+    fn test_run_stats_teardown() {
+        let status = |result| SetupScriptExecuteStatus {
+            stdout: Bytes::new(),
+            stderr: Bytes::new(),
+            result,
+            start_time: SystemTime::UNIX_EPOCH,
+            time_taken: Duration::ZERO,
+            is_slow: false,
+            env_count: 0,
+        };
+
+        // Setup was canceled after 1 of 2 scripts ran, and that script's teardown then ran.
+        let mut stats = RunStats {
+            setup_scripts_initial_count: 2,
+            ..RunStats::default()
+        };
+        stats.on_setup_script_finished(ScriptPhase::Setup, &status(ExecutionResult::Pass));
+        stats.on_setup_script_finished(ScriptPhase::Teardown, &status(ExecutionResult::Pass));
+        assert_eq!(
+            stats,
+            RunStats {
+                setup_scripts_initial_count: 2,
+                setup_scripts_finished_count: 1,
+                setup_scripts_passed: 1,
+                teardown_scripts_finished_count: 1,
+                teardown_scripts_passed: 1,
+                ..RunStats::default()
+            },
+            "teardowns are counted separately from setup scripts"
+        );
+        assert!(
+            !stats.is_success(),
+            "setup script not finished => failure, even after teardowns ran"
+        );
+
+        let mut stats = RunStats {
+            setup_scripts_initial_count: 1,
+            ..RunStats::default()
+        };
+        stats.on_setup_script_finished(ScriptPhase::Setup, &status(ExecutionResult::Pass));
+        stats.on_setup_script_finished(ScriptPhase::Teardown, &status(ExecutionResult::Timeout));
+        assert_eq!(stats.teardown_scripts_timed_out, 1);
+        assert_eq!(
+            stats.failure_kind(),
+            Some(RunStatsFailureKind::SetupScript),
+            "teardown timed out => failure"
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_child_waiter_resource_usage() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let mut waiter = imp::ChildWaiter::new().unwrap();
+            // dd allocates and fills a 64 MiB buffer.
+            let mut child = tokio::process::Command::new("dd")
+                .args(["if=/dev/zero", "of=/dev/null", "bs=64M", "count=8"])
+                .stderr(std::process::Stdio::null())
+                .spawn()
+                .unwrap();
+
+            let (status, resource_usage) = waiter.wait(&mut child).await.unwrap();
+            assert!(status.success(), "dd succeeded");
+            let resource_usage = resource_usage.expect("resource usage was collected");
+            assert!(
+                resource_usage.max_rss >= 64 * 1024 * 1024,
+                "peak memory usage includes the buffer: {resource_usage:?}"
+            );
+            assert!(
+                !(resource_usage.user_time + resource_usage.system_time).is_zero(),
+                "CPU time was recorded: {resource_usage:?}"
+            );
+
+            // tokio reaped the child, so it no longer hands out its pid.
+            assert_eq!(child.id(), None, "child was reaped");
+        });
+    }
 }
@@ -0,0 +1,282 @@
+// Copyright (c) The nextest Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::{
+    config::TestGroupAssignments,
+    errors::WriteTestListError,
+    helpers::{write_test_name, QuotedDisplay},
+    list::{OutputFormat, Styles as ListStyles, TestInstance, TestList},
+};
+use nextest_filtering::{FilteringExpr, TestMatchExplanation};
+use nextest_metadata::{
+    DecidingPredicateSummary, FilterExplanationSummary, FilterExpressionExplanationSummary,
+    FilterMatch, RustTestCaseFilterExplanationSummary, RustTestSuiteFilterExplanationSummary,
+    RustTestSuiteStatusSummary,
+};
+use owo_colors::{OwoColorize, Style};
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+};
+
+/// Shows why each test in a test list matched the provided filters or not.
+#[derive(Debug)]
+pub struct ShowFilter<'a> {
+    exprs: &'a [FilteringExpr],
+    test_count: usize,
+    summary: FilterExplanationSummary,
+}
+
+impl<'a> ShowFilter<'a> {
+    /// Creates a new `ShowFilter` from the given test list and filter expressions.
+    ///
+    /// The test list must have been built with the same filter expressions. `test_groups` is used
+    /// to evaluate `group()` predicates.
+    pub fn new(
+        test_list: &TestList<'_>,
+        exprs: &'a [FilteringExpr],
+        test_groups: &TestGroupAssignments,
+    ) -> Self {
+        let mut summary =
+            FilterExplanationSummary::new(exprs.iter().map(|expr| expr.input.clone()).collect());
+
+        for suite in test_list.iter() {
+            let (status, _) = suite.status.to_summary();
+
+            // Tests aren't listed for skipped binaries, so explain which binary-level predicates
+            // rejected them instead. If any expression can't be decided from the binary alone, it
+            // was skipped for some other reason.
+            let suite_expressions = if status == RustTestSuiteStatusSummary::SKIPPED {
+                let query = suite.to_binary_query();
+                exprs
+                    .iter()
+                    .map(|expr| expr.explain_binary(&query).map(explanation_summary))
+                    .collect::<Option<Vec<_>>>()
+                    .unwrap_or_default()
+            } else {
+                Vec::new()
+            };
+
+            let mut test_cases = BTreeMap::new();
+            for (test_name, test_case) in suite.status.test_cases() {
+                let test_instance = TestInstance::new(test_name, suite, test_case);
+                let query = test_instance.to_test_query();
+                let query = query.with_test_group(Some(test_groups.group_for(&query).as_str()));
+
+                let expressions = exprs
+                    .iter()
+                    .map(|expr| explanation_summary(expr.explain_test(&query)))
+                    .collect();
+
+                if test_case.filter_match.is_match() {
+                    summary.match_count += 1;
+                }
+                test_cases.insert(
+                    test_name.to_owned(),
+                    RustTestCaseFilterExplanationSummary {
+                        filter_match: test_case.filter_match,
+                        expressions,
+                    },
+                );
+            }
+
+            summary.rust_suites.insert(
+                suite.binary_id.clone(),
+                RustTestSuiteFilterExplanationSummary {
+                    status,
+                    expressions: suite_expressions,
+                    test_cases,
+                },
+            );
+        }
+
+        Self {
+            exprs,
+            test_count: test_list.test_count(),
+            summary,
+        }
+    }
+
+    /// Returns a serializable summary of the explanations.
+    pub fn to_summary(&self) -> FilterExplanationSummary {
+        self.summary.clone()
+    }
+
+    /// Writes the explanations in the given format.
+    pub fn write(
+        &self,
+        output_format: OutputFormat,
+        writer: impl Write,
+        colorize: bool,
+    ) -> Result<(), WriteTestListError> {
+        match output_format {
+            OutputFormat::Human { .. } => self
+                .write_human(writer, colorize)
+                .map_err(WriteTestListError::Io),
+            OutputFormat::Serializable(format) => format
+                .to_writer(&self.summary, writer)
+                .map_err(WriteTestListError::Json),
+        }
+    }
+
+    /// Writes the explanations to the given writer in a human-friendly format.
+    pub fn write_human(&self, mut writer: impl Write, colorize: bool) -> io::Result<()> {
+        let mut styles = Styles::default();
+        if colorize {
+            styles.colorize();
+        }
+
+        let mut skipped_binaries = 0;
+        for (binary_id, suite) in &self.summary.rust_suites {
+            writeln!(writer, "{}:", binary_id.style(styles.list.binary_id))?;
+            if suite.status == RustTestSuiteStatusSummary::SKIPPED {
+                skipped_binaries += 1;
+                if suite.expressions.is_empty() {
+                    writeln!(writer, "    (test binary skipped)")?;
+                } else {
+                    writeln!(
+                        writer,
+                        "    (test binary did not match filter expressions, skipped)"
+                    )?;
+                    self.write_explanations(&suite.expressions, &styles, &mut writer)?;
+                }
+                continue;
+            }
+            if suite.test_cases.is_empty() {
+                writeln!(writer, "    (no tests)")?;
+                continue;
+            }
+
+            for (test_name, test_case) in &suite.test_cases {
+                write!(writer, "    ")?;
+                write_test_name(test_name, &styles.list, &mut writer)?;
+                match test_case.filter_match {
+                    FilterMatch::Matches => {
+                        writeln!(writer, ": {}", "matches".style(styles.matches))?;
+                    }
+                    FilterMatch::Mismatch { reason } => {
+                        writeln!(
+                            writer,
+                            ": {} (test {reason})",
+                            "skipped".style(styles.mismatch)
+                        )?;
+                    }
+                }
+
+                self.write_explanations(&test_case.expressions, &styles, &mut writer)?;
+            }
+        }
+
+        write!(
+            writer,
+            "{} of {} {} matched",
+            self.summary.match_count.style(styles.count),
+            self.test_count.style(styles.count),
+            plural_tests(self.test_count),
+        )?;
+        if skipped_binaries > 0 {
+            write!(
+                writer,
+                " ({} {} skipped)",
+                skipped_binaries.style(styles.count),
+                if skipped_binaries == 1 {
+                    "binary"
+                } else {
+                    "binaries"
+                },
+            )?;
+        }
+        writeln!(writer)?;
+
+        Ok(())
+    }
+
+    /// Writes out the explanation for each filter expression, one per line.
+    fn write_explanations(
+        &self,
+        explanations: &[FilterExpressionExplanationSummary],
+        styles: &Styles,
+        writer: &mut dyn Write,
+    ) -> io::Result<()> {
+        for (expr, explanation) in self.exprs.iter().zip(explanations) {
+            write!(
+                writer,
+                "        {}: ",
+                QuotedDisplay(&expr.parsed).style(styles.expr)
+            )?;
+            if explanation.matches {
+                write!(writer, "{}", "matches".style(styles.matches))?;
+            } else {
+                write!(writer, "{}", "doesn't match".style(styles.mismatch))?;
+            }
+            if !explanation.deciding.is_empty() {
+                write!(writer, ", because ")?;
+            }
+            for (i, predicate) in explanation.deciding.iter().enumerate() {
+                if i > 0 {
+                    let separator = if i + 1 == explanation.deciding.len() {
+                        " and "
+                    } else {
+                        ", "
+                    };
+                    write!(writer, "{separator}")?;
+                }
+                write!(
+                    writer,
+                    "{} {}",
+                    predicate.predicate.style(styles.predicate),
+                    if predicate.matches {
+                        "matched"
+                    } else {
+                        "didn't match"
+                    },
+                )?;
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+}
+
+fn explanation_summary(explanation: TestMatchExplanation) -> FilterExpressionExplanationSummary {
+    FilterExpressionExplanationSummary {
+        matches: explanation.matches,
+        deciding: explanation
+            .deciding
+            .into_iter()
+            .map(|predicate| DecidingPredicateSummary {
+                predicate: predicate.predicate,
+                matches: predicate.matches,
+            })
+            .collect(),
+    }
+}
+
+fn plural_tests(count: usize) -> &'static str {
+    if count == 1 {
+        "test"
+    } else {
+        "tests"
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct Styles {
+    list: ListStyles,
+    matches: Style,
+    mismatch: Style,
+    expr: Style,
+    predicate: Style,
+    count: Style,
+}
+
+impl Styles {
+    fn colorize(&mut self) {
+        self.list.colorize();
+        self.matches = Style::new().green().bold();
+        self.mismatch = Style::new().red().bold();
+        self.expr = Style::new().yellow();
+        self.predicate = Style::new().bold();
+        self.count = Style::new().bold();
+    }
+}
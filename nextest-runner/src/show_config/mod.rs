@@ -4,8 +4,12 @@
 //! Functionality for showing configuration output of various kinds.
 
 // mod overrides;
+mod filter;
 mod nextest_version;
 mod test_groups;
+mod test_settings;
 
+pub use filter::*;
 pub use nextest_version::*;
 pub use test_groups::*;
+pub use test_settings::*;
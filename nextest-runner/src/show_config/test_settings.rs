@@ -0,0 +1,536 @@
+// Copyright (c) The nextest Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::{
+    config::{
+        ArtifactGlobs, CompiledProfileScripts, FinalConfig, MaxOutputSize, MaybeTargetSpec,
+        NextestProfile, OutputOverflow, RetryPolicy, ScriptId, ScriptKind, SettingSource,
+        SlowTimeout, ThreadsRequired, TimeoutDiagnostics,
+    },
+    errors::ShowTestSettingsError,
+    helpers::{format_bytes, write_test_name, QuotedDisplay},
+    list::{Styles as ListStyles, TestInstance, TestList},
+    reporter::TestOutputDisplay,
+};
+use nextest_metadata::RustBinaryId;
+use owo_colors::{OwoColorize, Style};
+use std::{
+    io::{self, Write},
+    time::Duration,
+};
+
+/// Shows the settings that apply to a test, along with where each setting came from.
+#[derive(Debug)]
+pub struct ShowTestSettings<'a> {
+    profile: &'a NextestProfile<'a>,
+    tests: Vec<ShowTestSettingsData<'a>>,
+}
+
+impl<'a> ShowTestSettings<'a> {
+    /// Creates a new `ShowTestSettings` for tests with the given name.
+    ///
+    /// `name` is matched against full test names. If no tests have that exact name, it's matched
+    /// against the trailing components of test names instead, so `test_foo` matches
+    /// `tests::test_foo`. If several tests match, settings are shown for each of them.
+    pub fn new(
+        profile: &'a NextestProfile<'a>,
+        test_list: &'a TestList<'a>,
+        name: &str,
+    ) -> Result<Self, ShowTestSettingsError> {
+        let mut tests: Vec<_> = test_list
+            .iter_tests()
+            .filter(|test| test.name == name)
+            .collect();
+        if tests.is_empty() {
+            let suffix = format!("::{name}");
+            tests = test_list
+                .iter_tests()
+                .filter(|test| test.name.ends_with(&suffix))
+                .collect();
+        }
+        if tests.is_empty() {
+            return Err(ShowTestSettingsError::NoMatchingTests {
+                name: name.to_owned(),
+            });
+        }
+
+        let tests = tests
+            .into_iter()
+            .map(|test| ShowTestSettingsData::new(profile, test))
+            .collect();
+        Ok(Self { profile, tests })
+    }
+
+    /// Writes the settings to the given writer in a human-friendly format.
+    pub fn write_human(&self, mut writer: impl Write, colorize: bool) -> io::Result<()> {
+        let mut styles = Styles::default();
+        if colorize {
+            styles.colorize();
+        }
+
+        let width = self
+            .tests
+            .iter()
+            .flat_map(|test| test.settings.iter().map(|(key, _, _)| key.len()))
+            .max()
+            .unwrap_or_default();
+
+        for (i, test) in self.tests.iter().enumerate() {
+            if i > 0 {
+                writeln!(writer)?;
+            }
+            write!(writer, "{} ", test.binary_id.style(styles.list.binary_id))?;
+            write_test_name(test.name, &styles.list, &mut writer)?;
+            writeln!(writer, ":")?;
+
+            for (key, value, source) in &test.settings {
+                write!(
+                    writer,
+                    "  {}{:pad$} {} (",
+                    key.style(styles.key),
+                    ":",
+                    value.style(styles.value),
+                    pad = width - key.len() + 1,
+                )?;
+                self.write_setting_source(source, &styles, &mut writer)?;
+                writeln!(writer, ")")?;
+            }
+
+            write!(writer, "  {}", "setup scripts:".style(styles.key))?;
+            if test.scripts.is_empty() {
+                write!(writer, " (none)")?;
+            }
+            writeln!(writer)?;
+            for (script_id, rule) in &test.scripts {
+                let kind = match self.profile.script_config()[*script_id].kind {
+                    ScriptKind::Setup => "setup script",
+                    ScriptKind::Wrapper => "wrapper script",
+                };
+                write!(
+                    writer,
+                    "    * {} ({kind}), enabled by a rule",
+                    script_id.style(styles.value)
+                )?;
+                write_rule(rule.filter(), rule.target_spec(), &styles, &mut writer)?;
+                writeln!(writer)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_setting_source(
+        &self,
+        source: &SettingSource<'_>,
+        styles: &Styles,
+        mut writer: impl Write,
+    ) -> io::Result<()> {
+        match source {
+            SettingSource::Profile(profile_name) => {
+                write!(
+                    writer,
+                    "from {} profile",
+                    profile_name.style(styles.profile)
+                )
+            }
+            SettingSource::Override(override_) => {
+                write!(
+                    writer,
+                    "from override for {} profile",
+                    override_.id().profile_name.style(styles.profile)
+                )?;
+                write_rule(override_.filter(), override_.target_spec(), styles, writer)
+            }
+        }
+    }
+}
+
+fn write_rule(
+    filter: Option<&nextest_filtering::FilteringExpr>,
+    target_spec: &MaybeTargetSpec,
+    styles: &Styles,
+    mut writer: impl Write,
+) -> io::Result<()> {
+    if let Some(expr) = filter {
+        write!(
+            writer,
+            " with filter {}",
+            QuotedDisplay(&expr.parsed).style(styles.filter)
+        )?;
+    }
+    if let MaybeTargetSpec::Provided(target_spec) = target_spec {
+        write!(
+            writer,
+            " on platform {}",
+            QuotedDisplay(target_spec).style(styles.filter)
+        )?;
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+struct ShowTestSettingsData<'a> {
+    binary_id: &'a RustBinaryId,
+    name: &'a str,
+    settings: Vec<(&'static str, String, SettingSource<'a>)>,
+    scripts: Vec<(&'a ScriptId, &'a CompiledProfileScripts<FinalConfig>)>,
+}
+
+impl<'a> ShowTestSettingsData<'a> {
+    fn new(profile: &'a NextestProfile<'a>, test: TestInstance<'a>) -> Self {
+        let mut query = test.to_test_query();
+        let test_settings = profile.settings_with_source_for(&query);
+        let test_group = test_settings.test_group_with_source().0.to_string();
+        query.test_group = Some(&test_group);
+        let scripts = profile.scripts_with_source_for(&query);
+
+        let mut settings = Vec::new();
+        let mut add = |key, value, source| settings.push((key, value, source));
+
+        let (threads_required, source) = test_settings.threads_required_with_source();
+        add(
+            "threads-required",
+            format_threads_required(threads_required),
+            source,
+        );
+        let (retries, source) = test_settings.retries_with_source();
+        add("retries", format_retries(retries), source);
+        let (slow_timeout, source) = test_settings.slow_timeout_with_source();
+        add("slow-timeout", format_slow_timeout(slow_timeout), source);
+        let (leak_timeout, source) = test_settings.leak_timeout_with_source();
+        add("leak-timeout", format_duration(leak_timeout), source);
+        let (test_group, source) = test_settings.test_group_with_source();
+        add("test-group", test_group.to_string(), *source);
+        let (success_output, source) = test_settings.success_output_with_source();
+        add(
+            "success-output",
+            format_output_display(success_output),
+            source,
+        );
+        let (failure_output, source) = test_settings.failure_output_with_source();
+        add(
+            "failure-output",
+            format_output_display(failure_output),
+            source,
+        );
+        let (store_success_output, source) = test_settings.junit_store_success_output_with_source();
+        add(
+            "junit.store-success-output",
+            store_success_output.to_string(),
+            source,
+        );
+        let (store_failure_output, source) = test_settings.junit_store_failure_output_with_source();
+        add(
+            "junit.store-failure-output",
+            store_failure_output.to_string(),
+            source,
+        );
+        let (quarantined, source) = test_settings.quarantined_with_source();
+        add("quarantine", quarantined.to_string(), source);
+        let (memory_limit, source) = test_settings.memory_limit_with_source();
+        add(
+            "memory-limit",
+            format_optional(memory_limit, |memory_limit| {
+                format!("{} of address space", format_bytes(memory_limit))
+            }),
+            source,
+        );
+        let (cpu_time_limit, source) = test_settings.cpu_time_limit_with_source();
+        add(
+            "cpu-time-limit",
+            format_optional(cpu_time_limit, format_duration),
+            source,
+        );
+        let (max_open_files, source) = test_settings.max_open_files_with_source();
+        add(
+            "max-open-files",
+            format_optional(max_open_files, |n| n.to_string()),
+            source,
+        );
+        let (isolation, source) = test_settings.isolation_with_source();
+        add("isolation", isolation.to_string(), source);
+        let (collect_artifacts, source) = test_settings.collect_artifacts_with_source();
+        add(
+            "collect-artifacts",
+            format_artifact_globs(collect_artifacts),
+            *source,
+        );
+        let (collect_core_dumps, source) = test_settings.collect_core_dumps_with_source();
+        add("collect-core-dumps", collect_core_dumps.to_string(), source);
+        let (max_output_size, source) = test_settings.max_output_size_with_source();
+        add(
+            "max-output-size",
+            format_max_output_size(max_output_size),
+            source,
+        );
+        let (output_overflow, source) = test_settings.output_overflow_with_source();
+        add(
+            "output-overflow",
+            format_output_overflow(output_overflow),
+            source,
+        );
+        let (timeout_diagnostics, source) = test_settings.timeout_diagnostics_with_source();
+        add(
+            "timeout-diagnostics",
+            format_optional(timeout_diagnostics.as_ref(), format_timeout_diagnostics),
+            *source,
+        );
+
+        Self {
+            binary_id: &test.suite_info.binary_id,
+            name: test.name,
+            settings,
+            scripts,
+        }
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    humantime::format_duration(duration).to_string()
+}
+
+fn format_optional<T>(value: Option<T>, format: impl FnOnce(T) -> String) -> String {
+    value.map_or_else(|| "none".to_owned(), format)
+}
+
+fn format_threads_required(threads_required: ThreadsRequired) -> String {
+    match threads_required {
+        ThreadsRequired::Count(count) => count.to_string(),
+        ThreadsRequired::NumCpus => "num-cpus".to_owned(),
+        ThreadsRequired::NumTestThreads => "num-test-threads".to_owned(),
+    }
+}
+
+fn format_retries(retries: RetryPolicy) -> String {
+    match retries {
+        RetryPolicy::Fixed {
+            count,
+            delay,
+            jitter,
+        } => {
+            if delay.is_zero() && !jitter {
+                count.to_string()
+            } else {
+                format!(
+                    "{count} (fixed backoff, delay {}{})",
+                    format_duration(delay),
+                    if jitter { ", with jitter" } else { "" },
+                )
+            }
+        }
+        RetryPolicy::Exponential {
+            count,
+            delay,
+            jitter,
+            max_delay,
+        } => {
+            let mut out = format!(
+                "{count} (exponential backoff, delay {}",
+                format_duration(delay)
+            );
+            if let Some(max_delay) = max_delay {
+                out.push_str(&format!(", max delay {}", format_duration(max_delay)));
+            }
+            if jitter {
+                out.push_str(", with jitter");
+            }
+            out.push(')');
+            out
+        }
+    }
+}
+
+fn format_slow_timeout(slow_timeout: SlowTimeout) -> String {
+    let mut out = format_duration(slow_timeout.period);
+    if let Some(terminate_after) = slow_timeout.terminate_after {
+        out.push_str(&format!(
+            " (terminate after {terminate_after} periods, grace period {})",
+            format_duration(slow_timeout.grace_period),
+        ));
+    }
+    out
+}
+
+fn format_output_display(display: TestOutputDisplay) -> String {
+    match display {
+        TestOutputDisplay::Immediate => "immediate",
+        TestOutputDisplay::ImmediateFinal => "immediate-final",
+        TestOutputDisplay::Final => "final",
+        TestOutputDisplay::Never => "never",
+    }
+    .to_owned()
+}
+
+fn format_artifact_globs(globs: &ArtifactGlobs) -> String {
+    if globs.is_empty() {
+        "none".to_owned()
+    } else {
+        globs.patterns().collect::<Vec<_>>().join(", ")
+    }
+}
+
+fn format_max_output_size(max_output_size: MaxOutputSize) -> String {
+    if max_output_size.is_empty() {
+        return "unlimited".to_owned();
+    }
+    let total = max_output_size.total.map_or_else(String::new, |total| {
+        format!(", total {}", format_bytes(total))
+    });
+    format!(
+        "stdout {}, stderr {}{total}",
+        format_optional(max_output_size.stdout, format_bytes),
+        format_optional(max_output_size.stderr, format_bytes),
+    )
+}
+
+fn format_output_overflow(output_overflow: OutputOverflow) -> String {
+    match output_overflow {
+        OutputOverflow::Drop => "drop",
+        OutputOverflow::Spill => "spill",
+    }
+    .to_owned()
+}
+
+fn format_timeout_diagnostics(timeout_diagnostics: &TimeoutDiagnostics) -> String {
+    shell_words::join(
+        std::iter::once(timeout_diagnostics.program())
+            .chain(timeout_diagnostics.args().iter().map(|arg| arg.as_str())),
+    )
+}
+
+#[derive(Clone, Debug, Default)]
+struct Styles {
+    list: ListStyles,
+    key: Style,
+    value: Style,
+    profile: Style,
+    filter: Style,
+}
+
+impl Styles {
+    fn colorize(&mut self) {
+        self.list.colorize();
+        self.key = Style::new().bold();
+        self.value = Style::new().bold();
+        self.profile = Style::new().bold();
+        self.filter = Style::new().yellow();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cargo_config::EnvironmentMap,
+        config::{
+            test_helpers::{build_platforms, temp_workspace},
+            NextestConfig,
+        },
+        list::{RustBuildMeta, RustTestArtifact},
+        reuse_build::PathMapper,
+        test_filter::{RunIgnored, TestFilterBuilder},
+    };
+    use camino_tempfile::tempdir;
+    use indoc::indoc;
+    use nextest_metadata::{BuildPlatform, RustTestBinaryKind};
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_write_human() {
+        let config_contents = indoc! {r#"
+            [profile.default]
+            retries = 1
+            leak-timeout = "200ms"
+
+            [[profile.default.overrides]]
+            filter = 'test(test_foo)'
+            threads-required = 2
+            retries = 4
+
+            [profile.ci]
+            slow-timeout = "2m"
+
+            [[profile.ci.overrides]]
+            filter = 'test(=tests::test_foo)'
+            retries = 3
+            memory-limit = "1GiB"
+
+            [[profile.ci.overrides]]
+            platform = 'cfg(unix)'
+            isolation = true
+        "#};
+
+        let workspace_dir = tempdir().unwrap();
+        let graph = temp_workspace(workspace_dir.path(), config_contents);
+        let package = graph.workspace().iter().next().unwrap();
+        let config = NextestConfig::from_sources(
+            graph.workspace().root(),
+            &graph,
+            None,
+            &[][..],
+            &Default::default(),
+        )
+        .expect("config is valid");
+        let profile = config
+            .profile("ci")
+            .expect("valid profile name")
+            .apply_build_platforms(&build_platforms());
+
+        let test_filter =
+            TestFilterBuilder::new(RunIgnored::Default, None, Vec::<String>::new(), Vec::new())
+                .unwrap();
+        let test_binary = RustTestArtifact {
+            binary_path: "/fake/binary".into(),
+            cwd: "/fake/cwd".into(),
+            package,
+            binary_name: "test-package".to_owned(),
+            binary_id: "test-package".into(),
+            kind: RustTestBinaryKind::LIB,
+            non_test_binaries: BTreeSet::new(),
+            build_platform: BuildPlatform::Target,
+        };
+        let test_list = TestList::new_with_outputs(
+            [(test_binary, "tests::test_foo: test\n", "")],
+            graph.workspace().root().to_owned(),
+            RustBuildMeta::new("/fake", None).map_paths(&PathMapper::noop()),
+            &test_filter,
+            EnvironmentMap::empty(),
+        )
+        .expect("valid output");
+
+        let show_test_settings =
+            ShowTestSettings::new(&profile, &test_list, "test_foo").expect("test_foo matches");
+        let mut output = Vec::new();
+        show_test_settings
+            .write_human(&mut output, false)
+            .expect("writing to a Vec succeeds");
+
+        // Settings the ci profile doesn't set are shown as coming from the default profile.
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            indoc! {"
+                test-package tests::test_foo:
+                  threads-required:           2 (from override for default profile with filter 'test(test_foo)')
+                  retries:                    3 (from override for ci profile with filter 'test(=tests::test_foo)')
+                  slow-timeout:               2m (from ci profile)
+                  leak-timeout:               200ms (from default profile)
+                  test-group:                 @global (from default profile)
+                  success-output:             never (from default profile)
+                  failure-output:             immediate (from default profile)
+                  junit.store-success-output: false (from default profile)
+                  junit.store-failure-output: false (from default profile)
+                  quarantine:                 false (from default profile)
+                  memory-limit:               1.0 GiB of address space (from override for ci profile with filter 'test(=tests::test_foo)')
+                  cpu-time-limit:             none (from default profile)
+                  max-open-files:             none (from default profile)
+                  isolation:                  true (from override for ci profile on platform 'unix')
+                  collect-artifacts:          none (from default profile)
+                  collect-core-dumps:         false (from default profile)
+                  max-output-size:            unlimited (from default profile)
+                  output-overflow:            drop (from default profile)
+                  timeout-diagnostics:        none (from default profile)
+                  setup scripts: (none)
+            "}
+        );
+    }
+}
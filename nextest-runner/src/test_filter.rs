@@ -10,9 +10,11 @@
 // result
 
 use crate::{
+    config::TestGroupAssignments,
     errors::TestFilterBuilderError,
     list::RustTestArtifact,
     partition::{Partitioner, PartitionerBuilder},
+    run_store::FailedTests,
 };
 use aho_corasick::AhoCorasick;
 use nextest_filtering::{FilteringExpr, TestQuery};
@@ -41,6 +43,9 @@ pub struct TestFilterBuilder {
     partitioner_builder: Option<PartitionerBuilder>,
     name_match: NameMatch,
     exprs: Vec<FilteringExpr>,
+    required_expr: Option<FilteringExpr>,
+    rerun_failed: Option<FailedTests>,
+    test_groups: Option<TestGroupAssignments>,
 }
 
 #[derive(Clone, Debug)]
@@ -94,6 +99,9 @@ impl TestFilterBuilder {
             partitioner_builder,
             name_match,
             exprs,
+            required_expr: None,
+            rerun_failed: None,
+            test_groups: None,
         })
     }
 
@@ -104,15 +112,55 @@ impl TestFilterBuilder {
             partitioner_builder: None,
             name_match: NameMatch::EmptyPatterns,
             exprs: Vec::new(),
+            required_expr: None,
+            rerun_failed: None,
+            test_groups: None,
         }
     }
 
+    /// Restricts this filter to tests that failed in a previous run.
+    ///
+    /// Tests not in `failed_tests` are skipped with [`MismatchReason::RerunFailed`].
+    pub fn set_rerun_failed(&mut self, failed_tests: FailedTests) -> &mut Self {
+        self.rerun_failed = Some(failed_tests);
+        self
+    }
+
+    /// Restricts this filter to tests that match `expr`.
+    ///
+    /// Unlike the expressions passed into [`Self::new`], which are combined with "or", a test must
+    /// match this expression in addition to the others. Tests that don't match it are skipped with
+    /// [`MismatchReason::Expression`].
+    pub fn set_required_expr(&mut self, expr: FilteringExpr) -> &mut Self {
+        self.required_expr = Some(expr);
+        self
+    }
+
+    /// Uses `test_groups` to determine the test group of each test, for `group()` predicates in
+    /// filter expressions.
+    ///
+    /// If this isn't called, `group()` predicates don't match any tests.
+    pub fn set_test_groups(&mut self, test_groups: TestGroupAssignments) -> &mut Self {
+        self.test_groups = Some(test_groups);
+        self
+    }
+
     /// Returns a value indicating whether this binary should or should not be run to obtain the
     /// list of tests within it.
     ///
     /// This method is implemented directly on `TestFilterBuilder`. The statefulness of `TestFilter`
     /// is only used for counted test partitioning, and is not currently relevant for binaries.
     pub fn should_obtain_test_list_from_binary(&self, test_binary: &RustTestArtifact<'_>) -> bool {
+        if let Some(failed_tests) = &self.rerun_failed {
+            if !failed_tests.contains_binary(&test_binary.binary_id) {
+                return false;
+            }
+        }
+        if let Some(required_expr) = &self.required_expr {
+            if required_expr.matches_binary(&test_binary.to_binary_query()) == Some(false) {
+                return false;
+            }
+        }
         if self.exprs.is_empty() {
             // No expressions means match all tests.
             return true;
@@ -136,7 +184,7 @@ impl TestFilterBuilder {
         let partitioner = self
             .partitioner_builder
             .as_ref()
-            .map(|partitioner_builder| partitioner_builder.build());
+            .and_then(|partitioner_builder| partitioner_builder.build());
         TestFilter {
             builder: self,
             partitioner,
@@ -160,6 +208,7 @@ impl<'filter> TestFilter<'filter> {
         ignored: bool,
     ) -> FilterMatch {
         self.filter_ignored_mismatch(ignored)
+            .or_else(|| self.filter_rerun_failed_mismatch(test_binary, test_name))
             .or_else(|| {
                 // ---
                 // NOTE
@@ -184,7 +233,7 @@ impl<'filter> TestFilter<'filter> {
                 use FilterNameMatch::*;
                 match (
                     self.filter_name_match(test_name),
-                    self.filter_expression_match(test_binary, test_name),
+                    self.filter_expression_match(test_binary, test_name, ignored),
                 ) {
                     // Tests must be accepted by both expressions and filters.
                     (
@@ -225,6 +274,21 @@ impl<'filter> TestFilter<'filter> {
         None
     }
 
+    fn filter_rerun_failed_mismatch(
+        &self,
+        test_binary: &RustTestArtifact<'_>,
+        test_name: &str,
+    ) -> Option<FilterMatch> {
+        match &self.builder.rerun_failed {
+            Some(failed_tests) if !failed_tests.contains(&test_binary.binary_id, test_name) => {
+                Some(FilterMatch::Mismatch {
+                    reason: MismatchReason::RerunFailed,
+                })
+            }
+            _ => None,
+        }
+    }
+
     fn filter_name_match(&self, test_name: &str) -> FilterNameMatch {
         match &self.builder.name_match {
             NameMatch::EmptyPatterns => FilterNameMatch::MatchEmptyPatterns,
@@ -242,11 +306,26 @@ impl<'filter> TestFilter<'filter> {
         &self,
         test_binary: &RustTestArtifact<'_>,
         test_name: &str,
+        ignored: bool,
     ) -> FilterNameMatch {
-        let query = TestQuery {
-            binary_query: test_binary.to_binary_query(),
-            test_name,
+        let query = TestQuery::new(test_binary.to_binary_query(), test_name).with_ignored(ignored);
+        // Looking up the test group evaluates overrides, so only do that if it's needed.
+        let uses_group = self
+            .builder
+            .exprs
+            .iter()
+            .chain(&self.builder.required_expr)
+            .any(|expr| expr.uses_group());
+        let test_group = match &self.builder.test_groups {
+            Some(test_groups) if uses_group => Some(test_groups.group_for(&query).as_str()),
+            _ => None,
         };
+        let query = query.with_test_group(test_group);
+        if let Some(required_expr) = &self.builder.required_expr {
+            if !required_expr.matches_test(&query) {
+                return FilterNameMatch::Mismatch(MismatchReason::Expression);
+            }
+        }
         if self.builder.exprs.is_empty() {
             FilterNameMatch::MatchEmptyPatterns
         } else if self
@@ -0,0 +1,402 @@
+// Copyright (c) The nextest Contributors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Watching workspace sources for changes.
+//!
+//! The main structure in this module is [`WorkspaceWatcher`], which is used by `cargo nextest
+//! watch` to find out which packages need to be retested.
+
+#[cfg(unix)]
+use crate::signal::JobControlEvent;
+use crate::{
+    errors::WatcherBuildError,
+    runner::TestRunnerCancelHandle,
+    signal::{SignalEvent, SignalHandler, SignalHandlerKind},
+};
+use camino::{Utf8Path, Utf8PathBuf};
+use guppy::{graph::PackageGraph, PackageId};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+use tokio::{
+    runtime::Runtime,
+    sync::mpsc::{UnboundedReceiver, UnboundedSender},
+};
+
+/// How often the workspace is scanned for changes, at most.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The interval between scans is at least this many times as long as the last scan took, so that
+/// scanning large workspaces doesn't keep a CPU busy.
+const SCAN_TIME_FACTOR: u32 = 10;
+
+/// Watches the sources of workspace packages for changes.
+///
+/// Files are polled for modification time changes in a background thread. Directories that start
+/// with `.`, Cargo target directories (directories containing a `CACHEDIR.TAG` file), and the
+/// directories passed in as `ignore_dirs` are not watched.
+///
+/// Changes are debounced: they're reported once a scan finds no further changes, so that a burst
+/// of writes, such as a formatter rewriting many files, results in a single rerun.
+#[derive(Debug)]
+pub struct WorkspaceWatcher {
+    runtime: Runtime,
+    handler: SignalHandler,
+    receiver: UnboundedReceiver<BTreeSet<PackageId>>,
+    cancel_state: Arc<Mutex<CancelState>>,
+}
+
+impl WorkspaceWatcher {
+    /// Creates a new watcher for the workspace packages in `graph`, and starts watching them.
+    ///
+    /// `ignore_dirs` are directories that aren't watched even if they're within a package, such
+    /// as the Cargo target directory and the nextest store directory.
+    ///
+    /// Changes made before this is called are not reported.
+    pub fn new(
+        graph: &PackageGraph,
+        ignore_dirs: impl IntoIterator<Item = Utf8PathBuf>,
+        handler_kind: SignalHandlerKind,
+    ) -> Result<Self, WatcherBuildError> {
+        let runtime = Runtime::new().map_err(WatcherBuildError::TokioRuntimeCreate)?;
+        let handler = {
+            // This must be called from within the guard.
+            let _guard = runtime.enter();
+            handler_kind.build()?
+        };
+
+        let sources = WorkspaceSources::new(graph, ignore_dirs.into_iter().collect());
+        let cancel_state = Arc::new(Mutex::new(CancelState::default()));
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        let poll_cancel_state = cancel_state.clone();
+        std::thread::spawn(move || sources.poll(sender, poll_cancel_state));
+
+        Ok(Self {
+            runtime,
+            handler,
+            receiver,
+            cancel_state,
+        })
+    }
+
+    /// Cancels the given test run as soon as a change is detected.
+    ///
+    /// Call [`Self::finish_run`] once the run is over.
+    pub fn start_run(&self, cancel_handle: TestRunnerCancelHandle) {
+        *self.cancel_state.lock().unwrap() = CancelState {
+            handle: Some(cancel_handle),
+            canceled: false,
+        };
+    }
+
+    /// Stops canceling the test run passed into [`Self::start_run`].
+    ///
+    /// Returns true if the run was canceled because of a change.
+    pub fn finish_run(&self) -> bool {
+        std::mem::take(&mut *self.cancel_state.lock().unwrap()).canceled
+    }
+
+    /// Waits until the sources of at least one workspace package change, and returns the IDs of
+    /// all packages that changed since the last call.
+    ///
+    /// Returns `None` if an interrupt or termination signal was received, either while waiting or
+    /// since the last call.
+    pub fn wait_for_changes(&mut self) -> Option<BTreeSet<PackageId>> {
+        let Self {
+            runtime,
+            handler,
+            receiver,
+            ..
+        } = self;
+
+        runtime.block_on(async {
+            loop {
+                tokio::select! {
+                    // Check for signals first, so that a signal that canceled the last run stops
+                    // the watch even if changes are also pending.
+                    biased;
+
+                    event = handler.recv() => match event {
+                        Some(SignalEvent::Shutdown(_)) => return None,
+                        #[cfg(unix)]
+                        Some(SignalEvent::JobControl(JobControlEvent::Stop)) => {
+                            // Nothing is running, so stop right away.
+                            crate::runner::raise_stop();
+                        }
+                        #[cfg(unix)]
+                        Some(SignalEvent::JobControl(JobControlEvent::Continue)) => {}
+                        // Signals aren't being handled, so wait for changes alone.
+                        None => return receiver.recv().await.map(|changed| drain(receiver, changed)),
+                    },
+                    changed = receiver.recv() => {
+                        return changed.map(|changed| drain(receiver, changed));
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// Merges any changes that are already queued into `changed`.
+fn drain(
+    receiver: &mut UnboundedReceiver<BTreeSet<PackageId>>,
+    mut changed: BTreeSet<PackageId>,
+) -> BTreeSet<PackageId> {
+    while let Ok(more) = receiver.try_recv() {
+        changed.extend(more);
+    }
+    changed
+}
+
+#[derive(Debug, Default)]
+struct CancelState {
+    handle: Option<TestRunnerCancelHandle>,
+    canceled: bool,
+}
+
+#[derive(Debug)]
+struct WorkspaceSources {
+    // Sorted so that nested packages come before the packages they're nested in.
+    roots: Vec<(Utf8PathBuf, PackageId)>,
+    // The directories to scan: package directories not nested within other package directories.
+    scan_dirs: Vec<Utf8PathBuf>,
+    // Directories that are skipped while scanning.
+    ignore_dirs: Vec<Utf8PathBuf>,
+}
+
+impl WorkspaceSources {
+    fn new(graph: &PackageGraph, ignore_dirs: Vec<Utf8PathBuf>) -> Self {
+        let mut roots: Vec<_> = graph
+            .resolve_workspace()
+            .packages(guppy::graph::DependencyDirection::Forward)
+            .filter_map(|package| {
+                let dir = package.manifest_path().parent()?;
+                Some((dir.to_owned(), package.id().clone()))
+            })
+            .collect();
+        roots.sort_by_key(|(dir, _)| Reverse(dir.components().count()));
+        let scan_dirs = roots
+            .iter()
+            .filter(|(dir, _)| {
+                !roots
+                    .iter()
+                    .any(|(other, _)| other != dir && dir.starts_with(other))
+            })
+            .map(|(dir, _)| dir.clone())
+            .collect();
+        Self {
+            roots,
+            scan_dirs,
+            ignore_dirs,
+        }
+    }
+
+    fn poll(
+        self,
+        sender: UnboundedSender<BTreeSet<PackageId>>,
+        cancel_state: Arc<Mutex<CancelState>>,
+    ) {
+        let mut snapshot = self.snapshot();
+        let mut interval = POLL_INTERVAL;
+        let mut debouncer = Debouncer::default();
+        loop {
+            std::thread::sleep(interval);
+            let start = Instant::now();
+            let new_snapshot = self.snapshot();
+            interval = POLL_INTERVAL.max(start.elapsed() * SCAN_TIME_FACTOR);
+            let changed: BTreeSet<_> = changed_paths(&snapshot, &new_snapshot)
+                .filter_map(|path| self.package_for(path))
+                .cloned()
+                .collect();
+            snapshot = new_snapshot;
+
+            let Some(changed) = debouncer.observe(changed) else {
+                continue;
+            };
+            log::debug!("sources changed for packages: {changed:?}");
+            if sender.send(changed).is_err() {
+                // The watcher was dropped.
+                return;
+            }
+            let mut cancel_state = cancel_state.lock().unwrap();
+            if let Some(handle) = &cancel_state.handle {
+                handle.cancel();
+                cancel_state.canceled = true;
+            }
+        }
+    }
+
+    fn snapshot(&self) -> BTreeMap<Utf8PathBuf, SystemTime> {
+        let mut snapshot = BTreeMap::new();
+        for dir in &self.scan_dirs {
+            self.walk_dir(dir, &mut snapshot);
+        }
+        snapshot
+    }
+
+    fn walk_dir(&self, dir: &Utf8Path, snapshot: &mut BTreeMap<Utf8PathBuf, SystemTime>) {
+        // Cargo marks its target directories with this file, but custom target and store
+        // directories might not have it (yet), so they're also skipped explicitly.
+        if self.ignore_dirs.iter().any(|ignore_dir| ignore_dir == dir)
+            || dir.join("CACHEDIR.TAG").exists()
+        {
+            return;
+        }
+        let Ok(entries) = dir.read_dir_utf8() else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            // Skip hidden files and directories like .git, along with editor backup files.
+            if name.starts_with('.') || name.ends_with('~') {
+                continue;
+            }
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                self.walk_dir(entry.path(), snapshot);
+            } else if let Ok(modified) = metadata.modified() {
+                snapshot.insert(entry.into_path(), modified);
+            }
+        }
+    }
+
+    fn package_for(&self, path: &Utf8Path) -> Option<&PackageId> {
+        self.roots
+            .iter()
+            .find(|(root, _)| path.starts_with(root))
+            .map(|(_, id)| id)
+    }
+}
+
+/// Holds back changes until a scan finds no further changes.
+#[derive(Debug, Default)]
+struct Debouncer {
+    pending: BTreeSet<PackageId>,
+}
+
+impl Debouncer {
+    /// Records the packages changed in the latest scan, and returns all pending changes if the
+    /// sources have settled.
+    fn observe(&mut self, changed: BTreeSet<PackageId>) -> Option<BTreeSet<PackageId>> {
+        if changed.is_empty() {
+            (!self.pending.is_empty()).then(|| std::mem::take(&mut self.pending))
+        } else {
+            self.pending.extend(changed);
+            None
+        }
+    }
+}
+
+/// Returns paths that were added, modified or removed between two snapshots.
+fn changed_paths<'a>(
+    old: &'a BTreeMap<Utf8PathBuf, SystemTime>,
+    new: &'a BTreeMap<Utf8PathBuf, SystemTime>,
+) -> impl Iterator<Item = &'a Utf8Path> {
+    let added_or_modified = new
+        .iter()
+        .filter(|(path, modified)| old.get(*path) != Some(modified))
+        .map(|(path, _)| path.as_path());
+    let removed = old
+        .keys()
+        .filter(|path| !new.contains_key(*path))
+        .map(|path| path.as_path());
+    added_or_modified.chain(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use camino_tempfile::tempdir;
+
+    #[test]
+    fn test_changed_paths() {
+        let t0 = SystemTime::UNIX_EPOCH;
+        let t1 = t0 + Duration::from_secs(1);
+        let old: BTreeMap<_, _> = [
+            (Utf8PathBuf::from("a/src/lib.rs"), t0),
+            (Utf8PathBuf::from("a/src/removed.rs"), t0),
+            (Utf8PathBuf::from("b/src/lib.rs"), t0),
+        ]
+        .into_iter()
+        .collect();
+        let new: BTreeMap<_, _> = [
+            (Utf8PathBuf::from("a/src/lib.rs"), t0),
+            (Utf8PathBuf::from("b/src/lib.rs"), t1),
+            (Utf8PathBuf::from("b/tests/added.rs"), t0),
+        ]
+        .into_iter()
+        .collect();
+
+        let changed: Vec<_> = changed_paths(&old, &new).collect();
+        assert_eq!(
+            changed,
+            ["b/src/lib.rs", "b/tests/added.rs", "a/src/removed.rs"],
+        );
+    }
+
+    #[test]
+    fn test_debouncer() {
+        let a = PackageId::new("a");
+        let b = PackageId::new("b");
+        let mut debouncer = Debouncer::default();
+
+        assert_eq!(debouncer.observe(BTreeSet::new()), None, "nothing changed");
+        assert_eq!(
+            debouncer.observe([a.clone()].into()),
+            None,
+            "a is still changing"
+        );
+        assert_eq!(
+            debouncer.observe([b.clone()].into()),
+            None,
+            "b is still changing"
+        );
+        assert_eq!(
+            debouncer.observe(BTreeSet::new()),
+            Some([a, b].into()),
+            "changes settled"
+        );
+        assert_eq!(
+            debouncer.observe(BTreeSet::new()),
+            None,
+            "changes were reported"
+        );
+    }
+
+    #[test]
+    fn test_snapshot_skips_ignored_dirs() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        for path in [
+            "src/lib.rs",
+            "target-custom/debug/foo",
+            "target/debug/bar",
+            "store/tmp/baz",
+            ".git/HEAD",
+            "src/lib.rs~",
+        ] {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+        // A Cargo target directory, which is skipped without being passed in.
+        std::fs::write(root.join("target/CACHEDIR.TAG"), "").unwrap();
+
+        let sources = WorkspaceSources {
+            roots: vec![(root.to_owned(), PackageId::new("a"))],
+            scan_dirs: vec![root.to_owned()],
+            ignore_dirs: vec![root.join("target-custom"), root.join("store")],
+        };
+        let snapshot = sources.snapshot();
+        let paths: Vec<_> = snapshot
+            .keys()
+            .map(|path| path.strip_prefix(root).unwrap().as_str())
+            .collect();
+        assert_eq!(paths, ["src/lib.rs"]);
+    }
+}